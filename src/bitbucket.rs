@@ -1,10 +1,33 @@
-use crate::config::PrStatus;
-use anyhow::{Context, Result};
-use reqwest::Url;
-use reqwest::blocking::{Client, RequestBuilder};
-use serde::Deserialize;
+use crate::config::{AuthorIdentifier, PrStatus, SizeCategory, SizeThresholds};
+use crate::debug_log;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use reqwest::blocking::Client;
+use reqwest::{Certificate, Method, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
 
-#[derive(Debug, Clone)]
+const MAX_PAGELEN: u32 = 50;
+const MAX_REPOSITORY_PAGES: usize = 20;
+const MAX_PULL_REQUEST_PAGES: usize = 20;
+const MAX_AGGREGATE_PAGES: usize = 20;
+const MAX_DIFFSTAT_PAGES: usize = 10;
+const MAX_DIVERGENCE_PAGES: usize = 4;
+const FALLBACK_DEFAULT_BRANCH: &str = "master";
+/// Partial response for PR listings: exactly what `PullRequestValue` maps,
+/// plus `next`. Keep in sync when fields are added to or removed from it.
+const PULL_REQUEST_FIELDS: &str = "values.id,values.title,values.description,\
+values.summary.raw,values.state,values.comment_count,values.created_on,values.updated_on,\
+values.author.display_name,values.author.nickname,values.author.uuid,values.author.account_id,\
+values.links.html.href,values.participants.role,values.participants.approved,\
+values.participants.user.display_name,values.participants.user.nickname,\
+values.participants.user.uuid,values.participants.user.account_id,\
+values.source.branch.name,values.destination.branch.name,\
+values.destination.repository.full_name,values.close_source_branch,next";
+
+#[derive(Debug, Clone, Serialize)]
 pub struct PullRequest {
     pub workspace: String,
     pub repo: String,
@@ -12,130 +35,1564 @@ pub struct PullRequest {
     pub title: String,
     pub description: String,
     pub author: String,
+    /// Author identifiers, for matching the author against reviewers.
+    #[serde(skip)]
+    pub author_uuid: Option<String>,
+    #[serde(skip)]
+    pub author_account_id: Option<String>,
     pub state: String,
     pub comment_count: u64,
+    pub created_on: String,
     pub updated_on: String,
     pub url: String,
+    pub source_branch: Option<String>,
+    pub destination_branch: Option<String>,
+    /// Whether the source branch is deleted when the PR is merged.
+    pub close_source_branch: bool,
+    pub build_url: Option<String>,
+    pub diffstat: Option<DiffStat>,
+    pub relation: Relation,
+    pub participants: Vec<Participant>,
+    /// Issue keys parsed from the title and description by the client.
+    pub issue_keys: Vec<String>,
 }
 
-pub struct BitbucketClient {
+/// How a pull request relates to the current user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Relation {
+    #[default]
+    Authored,
+    Reviewing,
+    Both,
+    /// Neither the author nor a reviewer matched the user, e.g. when the
+    /// stored identity is stale. Only describes fetched PRs; it cannot be
+    /// queried for.
+    Other,
+}
+
+impl Relation {
+    /// Short row marker used in the combined view.
+    pub fn marker(self) -> &'static str {
+        match self {
+            Self::Authored => "A",
+            Self::Reviewing => "R",
+            Self::Both => "AR",
+            Self::Other => "?",
+        }
+    }
+}
+
+impl std::str::FromStr for Relation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "authored" => Ok(Self::Authored),
+            "reviewing" => Ok(Self::Reviewing),
+            "both" => Ok(Self::Both),
+            _ => bail!("invalid relation '{s}'. expected: authored|reviewing|both"),
+        }
+    }
+}
+
+impl std::fmt::Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            Self::Authored => "authored",
+            Self::Reviewing => "reviewing",
+            Self::Both => "both",
+            Self::Other => "other",
+        };
+        f.write_str(value)
+    }
+}
+
+/// Which pull requests to list from a repo: those related to `user` as
+/// `relation`, filtered by `status` or, when set, by the raw `custom_query`.
+pub struct PullRequestQuery<'a> {
+    pub user: &'a UserId,
+    pub relation: Relation,
+    pub status: PrStatus,
+    pub max_results: Option<u32>,
+    pub custom_query: Option<&'a str>,
+}
+
+/// Size of a pull request's diff, summed over all changed files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct DiffStat {
+    pub files_changed: usize,
+    pub lines_added: u64,
+    pub lines_removed: u64,
+}
+
+/// Most recent comment on a pull request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub author: String,
+    pub created_on: String,
+    pub raw: String,
+}
+
+/// Commit count and newest commit subject of a pull request. `count` is
+/// `None` when Bitbucket leaves out the page size.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommitSummary {
+    pub count: Option<u64>,
+    pub latest_subject: Option<String>,
+}
+
+/// Commits counted on one side of a branch comparison. Counting stops after
+/// a few pages, so `capped` marks a lower bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CommitCount {
+    pub count: u64,
+    pub capped: bool,
+}
+
+impl fmt::Display for CommitCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.count, if self.capped { "+" } else { "" })
+    }
+}
+
+/// How a PR's source branch compares with its destination: commits only on
+/// the source (`ahead`) and only on the destination (`behind`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Divergence {
+    pub ahead: CommitCount,
+    pub behind: CommitCount,
+}
+
+#[cfg(test)]
+impl PullRequest {
+    /// An open, authored PR with no participants, shared by the test suites.
+    pub fn fixture(repo: &str, id: u64) -> Self {
+        Self {
+            workspace: "team".to_string(),
+            repo: repo.to_string(),
+            id,
+            title: format!("PR {id}"),
+            description: String::new(),
+            author: "Me".to_string(),
+            author_uuid: None,
+            author_account_id: None,
+            state: "OPEN".to_string(),
+            comment_count: 0,
+            created_on: String::new(),
+            updated_on: String::new(),
+            url: String::new(),
+            source_branch: None,
+            destination_branch: None,
+            close_source_branch: false,
+            build_url: None,
+            diffstat: None,
+            relation: Relation::Authored,
+            participants: Vec::new(),
+            issue_keys: Vec::new(),
+        }
+    }
+}
+
+impl PullRequest {
+    pub fn approvals(&self) -> usize {
+        self.participants
+            .iter()
+            .filter(|participant| participant.approved)
+            .count()
+    }
+
+    /// Whole days since the PR was created (or last updated, when
+    /// `created_on` is missing). `None` without a parseable timestamp.
+    pub fn age_days(&self, now: DateTime<Utc>) -> Option<i64> {
+        let since = if self.created_on.is_empty() {
+            &self.updated_on
+        } else {
+            &self.created_on
+        };
+        let since = DateTime::parse_from_rfc3339(since).ok()?;
+        Some((now - since.with_timezone(&Utc)).num_days().max(0))
+    }
+
+    /// The repo's pull request list page, cut from the PR's own web link so
+    /// it works for Cloud (`/{workspace}/{repo}/pull-requests/{id}`) and
+    /// Server (`/projects/{key}/repos/{repo}/pull-requests/{id}/overview`)
+    /// alike. Falls back to the Cloud path when the link has neither shape.
+    pub fn repo_pull_requests_url(&self) -> String {
+        const SEGMENT: &str = "/pull-requests";
+        match self.url.find(&format!("{SEGMENT}/")) {
+            Some(index) => self.url[..index + SEGMENT.len()].to_string(),
+            None => format!(
+                "https://bitbucket.org/{}/{}/pull-requests",
+                self.workspace, self.repo
+            ),
+        }
+    }
+
+    /// Size bucket by changed lines, once the diffstat is known.
+    pub fn size_category(&self, thresholds: &SizeThresholds) -> Option<SizeCategory> {
+        self.diffstat
+            .map(|diffstat| thresholds.category(diffstat.lines_added + diffstat.lines_removed))
+    }
+
+    /// A merged PR whose source branch was not set to be deleted on merge.
+    pub fn source_branch_left_behind(&self) -> bool {
+        self.state == "MERGED" && !self.close_source_branch && self.source_branch.is_some()
+    }
+
+    /// Age of an open PR that has no approvals yet; `None` once it is
+    /// approved or closed.
+    pub fn waiting_for_review_days(&self, now: DateTime<Utc>) -> Option<i64> {
+        if self.state != "OPEN" || self.approvals() > 0 {
+            return None;
+        }
+        self.age_days(now)
+    }
+
+    /// Display names of the repo's default reviewers who have not approved
+    /// yet, while a branch restriction on the destination still needs more
+    /// of their approvals to merge. The author never counts.
+    pub fn pending_required_reviewers<'a>(&self, required: &'a RequiredReviewers) -> Vec<&'a str> {
+        let needed = self
+            .destination_branch
+            .as_deref()
+            .map(|branch| required.approvals_needed(branch))
+            .unwrap_or(0);
+        let (approved, pending): (Vec<_>, Vec<_>) = required
+            .reviewers
+            .iter()
+            .filter(|reviewer| !self.is_author(reviewer))
+            .partition(|reviewer| {
+                self.participants
+                    .iter()
+                    .any(|participant| participant.approved && participant.is_same_user(reviewer))
+            });
+        if approved.len() >= needed as usize {
+            return Vec::new();
+        }
+        pending
+            .into_iter()
+            .map(|reviewer| reviewer.display_name.as_str())
+            .collect()
+    }
+
+    fn is_author(&self, user: &Participant) -> bool {
+        let same = |left: &Option<String>, right: &Option<String>| left.is_some() && left == right;
+        same(&self.author_uuid, &user.uuid) || same(&self.author_account_id, &user.account_id)
+    }
+
+    /// True when nobody but the author has contributed yet: no other
+    /// participant approved or took part (commented). Reviewers who were
+    /// only added don't count. A PR without participant details is solo.
+    pub fn is_solo(&self) -> bool {
+        !self.participants.iter().any(|participant| {
+            participant.display_name != self.author
+                && (participant.approved || participant.role == "PARTICIPANT")
+        })
+    }
+
+    /// Whether the PR merges into `default_branch`, or into `master` as the
+    /// fallback for repos that never moved to the configured name.
+    pub fn targets_default_branch(&self, default_branch: &str) -> bool {
+        self.destination_branch
+            .as_deref()
+            .is_some_and(|branch| branch == default_branch || branch == FALLBACK_DEFAULT_BRANCH)
+    }
+
+    pub fn is_approved_by(&self, user_id: &UserId) -> bool {
+        self.participants
+            .iter()
+            .any(|participant| participant.approved && user_id.matches(participant))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Participant {
+    pub uuid: Option<String>,
+    pub account_id: Option<String>,
+    pub nickname: Option<String>,
+    pub display_name: String,
+    pub role: String,
+    pub approved: bool,
+}
+
+impl Participant {
+    fn is_same_user(&self, other: &Participant) -> bool {
+        let same = |left: &Option<String>, right: &Option<String>| {
+            left.as_ref()
+                .zip(right.as_ref())
+                .map(|(left, right)| left == right)
+        };
+        same(&self.uuid, &other.uuid)
+            .or_else(|| same(&self.account_id, &other.account_id))
+            .or_else(|| same(&self.nickname, &other.nickname))
+            .unwrap_or(false)
+    }
+}
+
+/// A repo's default reviewers and the branch restrictions that require
+/// approvals from them before merging.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RequiredReviewers {
+    pub reviewers: Vec<Participant>,
+    pub rules: Vec<DefaultReviewerRule>,
+}
+
+/// `require_default_reviewer_approvals_to_merge` on branches matching the
+/// glob `pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefaultReviewerRule {
+    pub pattern: String,
+    pub approvals: u32,
+}
+
+impl RequiredReviewers {
+    /// Default reviewer approvals needed to merge into `branch`; 0 when no
+    /// restriction applies.
+    pub fn approvals_needed(&self, branch: &str) -> u32 {
+        self.rules
+            .iter()
+            .filter(|rule| glob_matches(&rule.pattern, branch))
+            .map(|rule| rule.approvals)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Matches `text` against a branch restriction pattern, where `*` stands
+/// for any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Identifier used to filter pull requests by author. Some token scopes omit
+/// `uuid` from `/user`, so the remaining identifiers act as fallbacks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserId {
+    Uuid(String),
+    AccountId(String),
+    Nickname(String),
+}
+
+impl UserId {
+    pub fn query_field(&self) -> &'static str {
+        match self {
+            Self::Uuid(_) => "uuid",
+            Self::AccountId(_) => "account_id",
+            Self::Nickname(_) => "nickname",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Uuid(value) | Self::AccountId(value) | Self::Nickname(value) => value,
+        }
+    }
+
+    pub fn matches(&self, participant: &Participant) -> bool {
+        self.matches_ids(
+            participant.uuid.as_deref(),
+            participant.account_id.as_deref(),
+            participant.nickname.as_deref(),
+        )
+    }
+
+    fn matches_ids(
+        &self,
+        uuid: Option<&str>,
+        account_id: Option<&str>,
+        nickname: Option<&str>,
+    ) -> bool {
+        let candidate = match self {
+            Self::Uuid(_) => uuid,
+            Self::AccountId(_) => account_id,
+            Self::Nickname(_) => nickname,
+        };
+        candidate == Some(self.value())
+    }
+}
+
+/// Pull requests fetched for one repo, plus why more were available than
+/// were returned, if they were.
+#[derive(Debug, Clone)]
+pub struct PullRequestBatch {
+    pub pull_requests: Vec<PullRequest>,
+    pub truncated: Option<Truncation>,
+}
+
+/// What stopped a PR listing before its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Truncation {
+    /// The configured `max_results_per_repo`.
+    Cap(u32),
+    /// `MAX_PULL_REQUEST_PAGES` pages were read.
+    PageLimit(usize),
+}
+
+impl fmt::Display for Truncation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cap(limit) => write!(f, "truncated to {limit} PR(s)"),
+            Self::PageLimit(pages) => write!(f, "truncated after {pages} pages"),
+        }
+    }
+}
+
+/// TLS settings for self-hosted instances that use an internal CA.
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    pub ca_cert_path: Option<String>,
+    pub accept_invalid_certs: bool,
+}
+
+/// Status and body of an API response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiResponse {
+    pub status: StatusCode,
+    pub body: String,
+}
+
+impl ApiResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn error_for_status(self) -> Result<Self, HttpStatusError> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            Err(HttpStatusError(self.status))
+        } else {
+            Ok(self)
+        }
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.body)
+    }
+}
+
+/// An API response with a 4xx or 5xx status; see `http_status`.
+#[derive(Debug)]
+pub struct HttpStatusError(pub StatusCode);
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP status {}", self.0)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Sends API requests for `BitbucketClient`: over the network by default,
+/// or recorded to and replayed from disk (see the `capture` module).
+pub trait Transport: Send + Sync {
+    fn send(&self, method: Method, endpoint: &Url) -> Result<ApiResponse>;
+}
+
+struct NetworkTransport {
     http: Client,
-    base_url: String,
     email: String,
     api_token: String,
 }
 
+impl Transport for NetworkTransport {
+    fn send(&self, method: Method, endpoint: &Url) -> Result<ApiResponse> {
+        let result = self
+            .http
+            .request(method.clone(), endpoint.clone())
+            .basic_auth(&self.email, Some(&self.api_token))
+            .send();
+        match &result {
+            Ok(response) => debug_log::record(
+                "DEBUG",
+                &format!("{method} {endpoint} -> {}", response.status()),
+            ),
+            Err(err) => debug_log::record("ERROR", &format!("{method} {endpoint} failed: {err}")),
+        }
+        let response = result?;
+        Ok(ApiResponse {
+            status: response.status(),
+            body: response.text()?,
+        })
+    }
+}
+
+/// HTTP client trusting the configured CA, shared by the API clients.
+pub fn http_client(tls: &TlsOptions) -> Result<Client> {
+    let mut builder = Client::builder();
+    if let Some(path) = &tls.ca_cert_path {
+        let pem =
+            fs::read(path).with_context(|| format!("failed to read CA certificate at {path}"))?;
+        let certificate = Certificate::from_pem(&pem)
+            .with_context(|| format!("failed to parse PEM CA certificate at {path}"))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    if tls.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().context("failed to build HTTP client")
+}
+
+pub struct BitbucketClient {
+    transport: Box<dyn Transport>,
+    endpoints: Endpoints,
+}
+
 impl BitbucketClient {
-    pub fn new(base_url: String, email: String, api_token: String) -> Self {
-        Self {
-            http: Client::new(),
-            base_url,
+    pub fn new(
+        base_url: String,
+        email: String,
+        api_token: String,
+        tls: &TlsOptions,
+    ) -> Result<Self> {
+        let transport = NetworkTransport {
+            http: http_client(tls)?,
             email,
             api_token,
+        };
+        Ok(Self::with_transport(&base_url, Box::new(transport)))
+    }
+
+    pub fn with_transport(base_url: &str, transport: Box<dyn Transport>) -> Self {
+        Self {
+            transport,
+            endpoints: Endpoints::new(base_url),
+        }
+    }
+
+    /// Replaces the transport with one built from the current one, e.g. to
+    /// record every response it returns.
+    pub fn wrap_transport(
+        self,
+        wrap: impl FnOnce(Box<dyn Transport>) -> Box<dyn Transport>,
+    ) -> Self {
+        Self {
+            transport: wrap(self.transport),
+            endpoints: self.endpoints,
+        }
+    }
+
+    /// Identifiers of the current user to try, in order, when filtering by
+    /// author: `preferred` first, then the other of uuid and account_id.
+    pub fn current_user_ids(&self, preferred: AuthorIdentifier) -> Result<Vec<UserId>> {
+        let endpoint =
+            Url::parse(&self.endpoints.user()).context("failed to build current-user endpoint")?;
+
+        let payload: UserResponse = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket user API")?
+            .error_for_status()
+            .context("Bitbucket user API returned an error status")?
+            .json()
+            .context("failed to deserialize Bitbucket user response")?;
+
+        payload.into_user_ids(preferred)
+    }
+
+    /// Lists one repo's PRs matching `query`, calling `on_page` after each
+    /// page with the page number and the PRs fetched so far.
+    pub fn list_pull_requests(
+        &self,
+        workspace: &str,
+        repo: &str,
+        query: &PullRequestQuery,
+        on_page: impl Fn(usize, usize),
+    ) -> Result<PullRequestBatch> {
+        let mut endpoint = Url::parse(&self.endpoints.pull_requests(workspace, repo))
+            .context("failed to build Bitbucket pull request endpoint")?;
+
+        let max_results = query.max_results;
+        let pagelen = max_results.map_or(MAX_PAGELEN, |cap| cap.clamp(1, MAX_PAGELEN));
+        endpoint
+            .query_pairs_mut()
+            .append_pair("sort", "-updated_on")
+            .append_pair("pagelen", &pagelen.to_string())
+            .append_pair("fields", PULL_REQUEST_FIELDS)
+            .append_pair("q", &build_query(query));
+
+        // Follow `next` until the listing ends, `max_results` PRs are in, or
+        // `MAX_PULL_REQUEST_PAGES` pages were read.
+        let mut values = Vec::new();
+        let mut next = Some(endpoint);
+        for page in 1..=MAX_PULL_REQUEST_PAGES {
+            let Some(endpoint) = next.take() else {
+                break;
+            };
+
+            let payload: PullRequestListResponse = self
+                .send_get(endpoint)
+                .context("failed to call Bitbucket pull request API")?
+                .error_for_status()
+                .with_context(|| {
+                    format!("Bitbucket pull request API returned an error for {workspace}/{repo}")
+                })?
+                .json()
+                .context("failed to deserialize Bitbucket pull request response")?;
+
+            values.extend(payload.values);
+            on_page(page, values.len());
+            next = payload
+                .next
+                .map(|next| Url::parse(&next))
+                .transpose()
+                .context("Bitbucket returned an invalid next page url")?;
+            if max_results.is_some_and(|cap| values.len() >= cap as usize) {
+                break;
+            }
         }
+
+        let truncated = match max_results {
+            Some(cap)
+                if values.len() > cap as usize
+                    || (next.is_some() && values.len() == cap as usize) =>
+            {
+                values.truncate(cap as usize);
+                Some(Truncation::Cap(cap))
+            }
+            _ if next.is_some() => Some(Truncation::PageLimit(MAX_PULL_REQUEST_PAGES)),
+            _ => None,
+        };
+
+        let pull_requests = values
+            .into_iter()
+            .map(|value| {
+                let relation = value.relation_to(query.user);
+                let mut pr = value.into_pull_request(workspace, repo);
+                pr.relation = relation;
+                pr
+            })
+            .collect();
+
+        Ok(PullRequestBatch {
+            pull_requests,
+            truncated,
+        })
+    }
+
+    /// Lists the user's pull requests across every repo they can see with the
+    /// `/pullrequests/{selected_user}` endpoint, following pagination. Each
+    /// PR's workspace and repo come from its destination repository. A
+    /// `custom_query` is sent as `q` and replaces the status filter.
+    /// `on_page` is called after each page with the page number and the
+    /// PRs fetched so far.
+    pub fn list_my_pull_requests(
+        &self,
+        user: &UserId,
+        status: PrStatus,
+        custom_query: Option<&str>,
+        on_page: impl Fn(usize, usize),
+    ) -> Result<Vec<PullRequest>> {
+        let mut endpoint = Url::parse(&self.endpoints.user_pull_requests(user.value()))
+            .context("failed to build Bitbucket user pull request endpoint")?;
+        {
+            let mut query = endpoint.query_pairs_mut();
+            query
+                .append_pair("sort", "-updated_on")
+                .append_pair("pagelen", &MAX_PAGELEN.to_string())
+                .append_pair("fields", PULL_REQUEST_FIELDS);
+            let states = match status.as_query_state() {
+                Some(state) if custom_query.is_none() => vec![state],
+                _ => vec!["OPEN", "MERGED", "DECLINED"],
+            };
+            if let Some(custom_query) = custom_query {
+                query.append_pair("q", custom_query);
+            }
+            for state in states {
+                query.append_pair("state", state);
+            }
+        }
+
+        let mut pull_requests = Vec::new();
+        let mut next = Some(endpoint);
+        for page in 1..=MAX_AGGREGATE_PAGES {
+            let Some(endpoint) = next.take() else {
+                break;
+            };
+
+            let payload: PullRequestListResponse = self
+                .send_get(endpoint)
+                .context("failed to call Bitbucket pull request API")?
+                .error_for_status()
+                .context("Bitbucket pull request API returned an error for the current user")?
+                .json()
+                .context("failed to deserialize Bitbucket pull request response")?;
+
+            pull_requests.extend(
+                payload
+                    .values
+                    .into_iter()
+                    .filter_map(PullRequestValue::into_pull_request_from_destination),
+            );
+            on_page(page, pull_requests.len());
+            next = payload
+                .next
+                .map(|next| Url::parse(&next))
+                .transpose()
+                .context("Bitbucket returned an invalid next page url")?;
+        }
+
+        Ok(pull_requests)
+    }
+
+    /// Looks up a single pull request by id, returning `None` when the repo
+    /// has no pull request with that id.
+    pub fn get_pull_request(
+        &self,
+        workspace: &str,
+        repo: &str,
+        id: u64,
+    ) -> Result<Option<PullRequest>> {
+        let endpoint = Url::parse(&self.endpoints.pull_request(workspace, repo, id))
+            .context("failed to build Bitbucket pull request endpoint")?;
+
+        let response = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket pull request API")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let value: PullRequestValue = response
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket pull request API returned an error for {workspace}/{repo}")
+            })?
+            .json()
+            .context("failed to deserialize Bitbucket pull request response")?;
+
+        Ok(Some(value.into_pull_request(workspace, repo)))
+    }
+
+    /// Lists every repository slug in `workspace`, following pagination,
+    /// limited to one project when `project_key` is given.
+    pub fn list_repositories(
+        &self,
+        workspace: &str,
+        project_key: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let mut endpoint = Url::parse(&self.endpoints.repositories(workspace))
+            .context("failed to build Bitbucket repositories endpoint")?;
+        endpoint
+            .query_pairs_mut()
+            .append_pair("pagelen", "100")
+            .append_pair("fields", "values.slug,next");
+        if let Some(key) = project_key {
+            endpoint
+                .query_pairs_mut()
+                .append_pair("q", &format!("project.key=\"{key}\""));
+        }
+
+        let mut slugs = Vec::new();
+        let mut next = Some(endpoint);
+        for _ in 0..MAX_REPOSITORY_PAGES {
+            let Some(endpoint) = next.take() else {
+                break;
+            };
+
+            let payload: RepositoryListResponse = self
+                .send_get(endpoint)
+                .context("failed to call Bitbucket repositories API")?
+                .error_for_status()
+                .with_context(|| {
+                    format!("Bitbucket repositories API returned an error for {workspace}")
+                })?
+                .json()
+                .context("failed to deserialize Bitbucket repositories response")?;
+
+            slugs.extend(payload.values.into_iter().map(|value| value.slug));
+            next = payload
+                .next
+                .map(|next| Url::parse(&next))
+                .transpose()
+                .context("Bitbucket returned an invalid next page url")?;
+        }
+
+        Ok(slugs)
+    }
+
+    /// The repo's default reviewers, with the branch restrictions that make
+    /// their approvals required. Default reviewers are only looked up when
+    /// such a restriction exists.
+    pub fn required_reviewers(&self, workspace: &str, repo: &str) -> Result<RequiredReviewers> {
+        let mut endpoint = Url::parse(&self.endpoints.branch_restrictions(workspace, repo))
+            .context("failed to build Bitbucket branch restrictions endpoint")?;
+        endpoint
+            .query_pairs_mut()
+            .append_pair("kind", "require_default_reviewer_approvals_to_merge")
+            .append_pair("pagelen", "100");
+
+        let payload: BranchRestrictionListResponse = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket branch restrictions API")?
+            .error_for_status()
+            .with_context(|| {
+                format!(
+                    "Bitbucket branch restrictions API returned an error for {workspace}/{repo}"
+                )
+            })?
+            .json()
+            .context("failed to deserialize Bitbucket branch restrictions response")?;
+
+        // Branching-model restrictions (e.g. "all release branches") can't
+        // be matched without the model, so only glob patterns count.
+        let rules = payload
+            .values
+            .into_iter()
+            .filter(|value| value.branch_match_kind.as_deref() == Some("glob"))
+            .filter_map(|value| {
+                Some(DefaultReviewerRule {
+                    pattern: value.pattern?,
+                    approvals: value.value.filter(|&approvals| approvals > 0)?,
+                })
+            })
+            .collect::<Vec<_>>();
+        if rules.is_empty() {
+            return Ok(RequiredReviewers::default());
+        }
+        Ok(RequiredReviewers {
+            reviewers: self.default_reviewers(workspace, repo)?,
+            rules,
+        })
+    }
+
+    /// The repo's effective default reviewers.
+    pub fn default_reviewers(&self, workspace: &str, repo: &str) -> Result<Vec<Participant>> {
+        let mut endpoint = Url::parse(&self.endpoints.default_reviewers(workspace, repo))
+            .context("failed to build Bitbucket default reviewers endpoint")?;
+        endpoint.query_pairs_mut().append_pair("pagelen", "100");
+
+        let payload: DefaultReviewerListResponse = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket default reviewers API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket default reviewers API returned an error for {workspace}/{repo}")
+            })?
+            .json()
+            .context("failed to deserialize Bitbucket default reviewers response")?;
+
+        Ok(payload
+            .values
+            .into_iter()
+            .filter_map(|value| value.user)
+            .map(|user| Participant {
+                display_name: user
+                    .display_name
+                    .or_else(|| user.nickname.clone())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                uuid: user.uuid,
+                account_id: user.account_id,
+                nickname: user.nickname,
+                role: "DEFAULT_REVIEWER".to_string(),
+                approved: false,
+            })
+            .collect())
+    }
+
+    /// URL of the most recent build (pipeline or external CI) reported for
+    /// the pull request's source commit, if any.
+    pub fn latest_build_url(&self, workspace: &str, repo: &str, id: u64) -> Result<Option<String>> {
+        let mut endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "statuses"),
+        )
+        .context("failed to build Bitbucket build status endpoint")?;
+        endpoint
+            .query_pairs_mut()
+            .append_pair("sort", "-created_on")
+            .append_pair("pagelen", "1");
+
+        let payload: BuildStatusListResponse = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket build status API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket build status API returned an error for {workspace}/{repo} #{id}")
+            })?
+            .json()
+            .context("failed to deserialize Bitbucket build status response")?;
+
+        Ok(payload
+            .values
+            .into_iter()
+            .find_map(|status| status.url.filter(|url| !url.is_empty())))
+    }
+
+    /// Newest non-deleted comment on the pull request, or `None` when it has
+    /// no comments.
+    pub fn latest_comment(&self, workspace: &str, repo: &str, id: u64) -> Result<Option<Comment>> {
+        let mut endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "comments"),
+        )
+        .context("failed to build Bitbucket comments endpoint")?;
+        endpoint
+            .query_pairs_mut()
+            .append_pair("q", "deleted=false")
+            .append_pair("sort", "-created_on")
+            .append_pair("pagelen", "1");
+
+        let payload: CommentListResponse = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket comments API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket comments API returned an error for {workspace}/{repo} #{id}")
+            })?
+            .json()
+            .context("failed to deserialize Bitbucket comments response")?;
+
+        Ok(payload
+            .values
+            .into_iter()
+            .next()
+            .map(CommentValue::into_comment))
+    }
+
+    /// Reads one page of the PR's commits, newest first: the page `size` is
+    /// the commit count and the first entry the latest commit.
+    pub fn pull_request_commits(
+        &self,
+        workspace: &str,
+        repo: &str,
+        id: u64,
+    ) -> Result<CommitSummary> {
+        let mut endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "commits"),
+        )
+        .context("failed to build Bitbucket commits endpoint")?;
+        endpoint.query_pairs_mut().append_pair("pagelen", "1");
+
+        let payload: CommitListResponse = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket commits API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket commits API returned an error for {workspace}/{repo} #{id}")
+            })?
+            .json()
+            .context("failed to deserialize Bitbucket commits response")?;
+
+        Ok(payload.into_summary())
+    }
+
+    /// Compares `source` with `destination` in the destination repo. Fails
+    /// when either branch is unknown there, e.g. for PRs from forks.
+    pub fn branch_divergence(
+        &self,
+        workspace: &str,
+        repo: &str,
+        source: &str,
+        destination: &str,
+    ) -> Result<Divergence> {
+        Ok(Divergence {
+            ahead: self.count_commits(workspace, repo, source, destination)?,
+            behind: self.count_commits(workspace, repo, destination, source)?,
+        })
+    }
+
+    /// Counts commits reachable from `include` but not from `exclude`,
+    /// following at most `MAX_DIVERGENCE_PAGES` pages.
+    fn count_commits(
+        &self,
+        workspace: &str,
+        repo: &str,
+        include: &str,
+        exclude: &str,
+    ) -> Result<CommitCount> {
+        let mut endpoint = Url::parse(&self.endpoints.commits(workspace, repo))
+            .context("failed to build Bitbucket commits endpoint")?;
+        endpoint
+            .query_pairs_mut()
+            .append_pair("include", include)
+            .append_pair("exclude", exclude)
+            .append_pair("pagelen", &MAX_PAGELEN.to_string())
+            .append_pair("fields", "values.hash,next");
+
+        let mut count = CommitCount::default();
+        let mut next = Some(endpoint);
+        for _ in 0..MAX_DIVERGENCE_PAGES {
+            let Some(endpoint) = next.take() else {
+                break;
+            };
+
+            let payload: CommitHashListResponse = self
+                .send_get(endpoint)
+                .context("failed to call Bitbucket commits API")?
+                .error_for_status()
+                .with_context(|| {
+                    format!(
+                        "Bitbucket commits API returned an error comparing {include} with {exclude} in {workspace}/{repo}"
+                    )
+                })?
+                .json()
+                .context("failed to deserialize Bitbucket commits response")?;
+
+            count.count += payload.values.len() as u64;
+            next = payload
+                .next
+                .map(|next| Url::parse(&next))
+                .transpose()
+                .context("Bitbucket returned an invalid next page url")?;
+        }
+        count.capped = next.is_some();
+        Ok(count)
+    }
+
+    /// Sums the per-file diffstat of a pull request, following pagination.
+    pub fn diffstat(&self, workspace: &str, repo: &str, id: u64) -> Result<DiffStat> {
+        let mut diffstat = DiffStat::default();
+        for file in self.diffstat_values(
+            workspace,
+            repo,
+            id,
+            "values.lines_added,values.lines_removed,next",
+        )? {
+            diffstat.files_changed += 1;
+            diffstat.lines_added += file.lines_added;
+            diffstat.lines_removed += file.lines_removed;
+        }
+        Ok(diffstat)
+    }
+
+    /// Every diffstat entry of a pull request, following pagination, with
+    /// only `fields` requested.
+    fn diffstat_values(
+        &self,
+        workspace: &str,
+        repo: &str,
+        id: u64,
+        fields: &str,
+    ) -> Result<Vec<DiffStatValue>> {
+        let mut endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "diffstat"),
+        )
+        .context("failed to build Bitbucket diffstat endpoint")?;
+        endpoint
+            .query_pairs_mut()
+            .append_pair("pagelen", "500")
+            .append_pair("fields", fields);
+
+        let mut values = Vec::new();
+        let mut next = Some(endpoint);
+        for _ in 0..MAX_DIFFSTAT_PAGES {
+            let Some(endpoint) = next.take() else {
+                break;
+            };
+
+            let payload: DiffStatListResponse = self
+                .send_get(endpoint)
+                .context("failed to call Bitbucket diffstat API")?
+                .error_for_status()
+                .with_context(|| {
+                    format!("Bitbucket diffstat API returned an error for {workspace}/{repo} #{id}")
+                })?
+                .json()
+                .context("failed to deserialize Bitbucket diffstat response")?;
+
+            values.extend(payload.values);
+            next = payload
+                .next
+                .map(|next| Url::parse(&next))
+                .transpose()
+                .context("Bitbucket returned an invalid next page url")?;
+        }
+
+        Ok(values)
+    }
+
+    /// The pull request's diff as unified diff text, limited to one file
+    /// when `path` is given.
+    pub fn diff(&self, workspace: &str, repo: &str, id: u64, path: Option<&str>) -> Result<String> {
+        let mut endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "diff"),
+        )
+        .context("failed to build Bitbucket diff endpoint")?;
+        if let Some(path) = path {
+            endpoint.query_pairs_mut().append_pair("path", path);
+        }
+
+        let response = self
+            .send_get(endpoint)
+            .context("failed to call Bitbucket diff API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket diff API returned an error for {workspace}/{repo} #{id}")
+            })?;
+        Ok(response.body)
+    }
+
+    /// Fetches the diff of one file changed by a pull request. `name` is the
+    /// file's path or, when only one changed file ends with it, a trailing
+    /// part such as the file name. Returns the full path and the diff text.
+    pub fn file_diff(
+        &self,
+        workspace: &str,
+        repo: &str,
+        id: u64,
+        name: &str,
+    ) -> Result<(String, String)> {
+        let paths = self
+            .diffstat_values(workspace, repo, id, "values.old.path,values.new.path,next")?
+            .into_iter()
+            .filter_map(DiffStatValue::into_path)
+            .collect::<Vec<_>>();
+        let path = resolve_changed_file(&paths, name)
+            .with_context(|| format!("cannot show {name} for PR #{id}"))?;
+        let diff = self.diff(workspace, repo, id, Some(&path))?;
+        Ok((path, diff))
+    }
+
+    pub fn approve_pull_request(&self, workspace: &str, repo: &str, id: u64) -> Result<()> {
+        self.send_approval(Method::POST, workspace, repo, id)
+    }
+
+    pub fn unapprove_pull_request(&self, workspace: &str, repo: &str, id: u64) -> Result<()> {
+        self.send_approval(Method::DELETE, workspace, repo, id)
+    }
+
+    pub fn decline_pull_request(&self, workspace: &str, repo: &str, id: u64) -> Result<()> {
+        let endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "decline"),
+        )
+        .context("failed to build Bitbucket decline endpoint")?;
+
+        self.send(Method::POST, endpoint)
+            .context("failed to call Bitbucket decline API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket decline API returned an error for {workspace}/{repo} #{id}")
+            })?;
+        Ok(())
+    }
+
+    fn send_approval(&self, method: Method, workspace: &str, repo: &str, id: u64) -> Result<()> {
+        let endpoint = Url::parse(
+            &self
+                .endpoints
+                .pull_request_resource(workspace, repo, id, "approve"),
+        )
+        .context("failed to build Bitbucket approve endpoint")?;
+
+        self.send(method, endpoint)
+            .context("failed to call Bitbucket approve API")?
+            .error_for_status()
+            .with_context(|| {
+                format!("Bitbucket approve API returned an error for {workspace}/{repo} #{id}")
+            })?;
+        Ok(())
+    }
+
+    fn send_get(&self, endpoint: Url) -> Result<ApiResponse> {
+        self.send(Method::GET, endpoint)
+    }
+
+    fn send(&self, method: Method, endpoint: Url) -> Result<ApiResponse> {
+        self.transport.send(method, &endpoint)
+    }
+}
+
+/// API paths relative to the base URL. `{name}` placeholders are filled by
+/// `Endpoints`; adapting to another API layout only means changing these.
+const USER_PATH: &str = "/user";
+const USER_PULL_REQUESTS_PATH: &str = "/pullrequests/{user}";
+const REPOSITORIES_PATH: &str = "/repositories/{workspace}";
+const PULL_REQUESTS_PATH: &str = "/repositories/{workspace}/{repo}/pullrequests";
+const PULL_REQUEST_PATH: &str = "/repositories/{workspace}/{repo}/pullrequests/{id}";
+const COMMITS_PATH: &str = "/repositories/{workspace}/{repo}/commits";
+const DEFAULT_REVIEWERS_PATH: &str = "/repositories/{workspace}/{repo}/effective-default-reviewers";
+const BRANCH_RESTRICTIONS_PATH: &str = "/repositories/{workspace}/{repo}/branch-restrictions";
+
+/// Builds API URLs from the configured base URL and the path templates
+/// above.
+#[derive(Debug, Clone)]
+pub struct Endpoints {
+    base_url: String,
+}
+
+impl Endpoints {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn user(&self) -> String {
+        self.build(USER_PATH, &[])
+    }
+
+    pub fn user_pull_requests(&self, user: &str) -> String {
+        self.build(USER_PULL_REQUESTS_PATH, &[("user", user)])
     }
 
-    pub fn current_user_uuid(&self) -> Result<String> {
-        let endpoint = Url::parse(&format!("{}/user", self.base_url.trim_end_matches('/')))
-            .context("failed to build current-user endpoint")?;
+    pub fn repositories(&self, workspace: &str) -> String {
+        self.build(REPOSITORIES_PATH, &[("workspace", workspace)])
+    }
 
-        let payload: UserResponse = self
-            .auth_get(endpoint)
-            .send()
-            .context("failed to call Bitbucket user API")?
-            .error_for_status()
-            .context("Bitbucket user API returned an error status")?
-            .json()
-            .context("failed to deserialize Bitbucket user response")?;
+    pub fn pull_requests(&self, workspace: &str, repo: &str) -> String {
+        self.build(
+            PULL_REQUESTS_PATH,
+            &[("workspace", workspace), ("repo", repo)],
+        )
+    }
 
-        Ok(payload.uuid)
+    pub fn pull_request(&self, workspace: &str, repo: &str, id: u64) -> String {
+        self.build(
+            PULL_REQUEST_PATH,
+            &[
+                ("workspace", workspace),
+                ("repo", repo),
+                ("id", &id.to_string()),
+            ],
+        )
     }
 
-    pub fn list_pull_requests_created_by(
+    /// A sub-resource or action of a pull request, e.g. `comments`,
+    /// `diffstat`, `approve`, or `decline`.
+    pub fn pull_request_resource(
         &self,
         workspace: &str,
         repo: &str,
-        author_uuid: &str,
-        status: PrStatus,
-    ) -> Result<Vec<PullRequest>> {
-        let mut endpoint = Url::parse(&format!(
-            "{}/repositories/{}/{}/pullrequests",
-            self.base_url.trim_end_matches('/'),
-            workspace,
-            repo
-        ))
-        .context("failed to build Bitbucket pull request endpoint")?;
+        id: u64,
+        resource: &str,
+    ) -> String {
+        format!("{}/{resource}", self.pull_request(workspace, repo, id))
+    }
 
-        let query = build_query(author_uuid, status);
-        endpoint
-            .query_pairs_mut()
-            .append_pair("sort", "-updated_on")
-            .append_pair("pagelen", "50")
-            .append_pair("q", &query);
+    pub fn commits(&self, workspace: &str, repo: &str) -> String {
+        self.build(COMMITS_PATH, &[("workspace", workspace), ("repo", repo)])
+    }
 
-        let payload: PullRequestListResponse = self
-            .auth_get(endpoint)
-            .send()
-            .context("failed to call Bitbucket pull request API")?
-            .error_for_status()
-            .with_context(|| {
-                format!("Bitbucket pull request API returned an error for {workspace}/{repo}")
-            })?
-            .json()
-            .context("failed to deserialize Bitbucket pull request response")?;
+    pub fn default_reviewers(&self, workspace: &str, repo: &str) -> String {
+        self.build(
+            DEFAULT_REVIEWERS_PATH,
+            &[("workspace", workspace), ("repo", repo)],
+        )
+    }
 
-        Ok(payload
-            .values
-            .into_iter()
-            .map(|value| {
-                let description = value
-                    .description
-                    .or_else(|| value.summary.and_then(|summary| summary.raw))
-                    .unwrap_or_default();
-
-                PullRequest {
-                    workspace: workspace.to_string(),
-                    repo: repo.to_string(),
-                    id: value.id,
-                    title: value.title,
-                    description,
-                    author: value
-                        .author
-                        .display_name
-                        .or(value.author.nickname)
-                        .unwrap_or_else(|| "unknown".to_string()),
-                    state: value.state,
-                    comment_count: value.comment_count,
-                    updated_on: value.updated_on,
-                    url: value.links.html.href,
-                }
-            })
-            .collect())
+    pub fn branch_restrictions(&self, workspace: &str, repo: &str) -> String {
+        self.build(
+            BRANCH_RESTRICTIONS_PATH,
+            &[("workspace", workspace), ("repo", repo)],
+        )
     }
 
-    fn auth_get(&self, endpoint: Url) -> RequestBuilder {
-        self.http
-            .get(endpoint)
-            .basic_auth(&self.email, Some(&self.api_token))
+    fn build(&self, template: &str, params: &[(&str, &str)]) -> String {
+        let path = params
+            .iter()
+            .fold(template.to_string(), |path, (name, value)| {
+                path.replace(&format!("{{{name}}}"), value)
+            });
+        format!("{}{path}", self.base_url)
     }
 }
 
-fn build_query(author_uuid: &str, status: PrStatus) -> String {
-    let mut terms = vec![format!("author.uuid=\"{}\"", author_uuid)];
-    if let Some(state) = status.as_query_state() {
+/// Returns true when `err` was caused by a connection-level failure (DNS,
+/// refused connection, timeout) rather than an HTTP error response.
+pub fn is_connection_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|err| err.is_connect() || err.is_timeout())
+    })
+}
+
+/// HTTP status of the error response behind `err`, if it came from one.
+pub fn http_status(err: &anyhow::Error) -> Option<StatusCode> {
+    err.chain().find_map(|cause| {
+        cause
+            .downcast_ref::<HttpStatusError>()
+            .map(|err| err.0)
+            .or_else(|| cause.downcast_ref::<reqwest::Error>()?.status())
+    })
+}
+
+/// User term for the relation AND'd with either the status filter or, when
+/// given, the raw custom query (which then takes over state filtering).
+fn build_query(query: &PullRequestQuery) -> String {
+    let user = query.user;
+    let author = format!("author.{}=\"{}\"", user.query_field(), user.value());
+    let reviewer = format!("reviewers.{}=\"{}\"", user.query_field(), user.value());
+    let mut terms = vec![match query.relation {
+        // `Other` never comes from the user; fall back to the default query.
+        Relation::Authored | Relation::Other => author,
+        Relation::Reviewing => reviewer,
+        Relation::Both => format!("({author} OR {reviewer})"),
+    }];
+    if let Some(custom_query) = query.custom_query {
+        terms.push(format!("({custom_query})"));
+    } else if let Some(state) = query.status.as_query_state() {
         terms.push(format!("state=\"{}\"", state));
     }
     terms.join(" AND ")
 }
 
+/// Minimal sanity check for a raw `q` expression: non-empty, with balanced
+/// double quotes (ignoring backslash-escaped ones) and parentheses outside
+/// of strings.
+pub fn validate_custom_query(expr: &str) -> Result<()> {
+    if expr.trim().is_empty() {
+        bail!("query is empty");
+    }
+
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut depth = 0i32;
+    for ch in expr.chars() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth < 0 {
+                    bail!("query has an unmatched ')'");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if in_string {
+        bail!("query has an unterminated string (unbalanced quotes)");
+    }
+    if depth != 0 {
+        bail!("query has an unmatched '('");
+    }
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 struct UserResponse {
-    uuid: String,
+    uuid: Option<String>,
+    account_id: Option<String>,
+    nickname: Option<String>,
+}
+
+impl UserResponse {
+    /// The nickname is only used when neither uuid nor account_id is
+    /// available.
+    fn into_user_ids(self, preferred: AuthorIdentifier) -> Result<Vec<UserId>> {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+
+        let uuid = non_empty(self.uuid).map(UserId::Uuid);
+        let account_id = non_empty(self.account_id).map(UserId::AccountId);
+        let ids = match preferred {
+            AuthorIdentifier::Uuid => [uuid, account_id],
+            AuthorIdentifier::AccountId => [account_id, uuid],
+        }
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if !ids.is_empty() {
+            return Ok(ids);
+        }
+        if let Some(nickname) = non_empty(self.nickname) {
+            return Ok(vec![UserId::Nickname(nickname)]);
+        }
+
+        bail!(
+            "Bitbucket user response has no uuid, account_id, or nickname; check the API token scopes"
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffStatListResponse {
+    values: Vec<DiffStatValue>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffStatValue {
+    #[serde(default)]
+    lines_added: u64,
+    #[serde(default)]
+    lines_removed: u64,
+    old: Option<DiffStatPath>,
+    new: Option<DiffStatPath>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiffStatPath {
+    path: String,
+}
+
+impl DiffStatValue {
+    /// The file's path after the change, or before it for deleted files.
+    fn into_path(self) -> Option<String> {
+        self.new.or(self.old).map(|file| file.path)
+    }
+}
+
+/// Picks the changed file `name` refers to: an exact path, or else the one
+/// path ending with `/{name}`.
+fn resolve_changed_file(paths: &[String], name: &str) -> Result<String> {
+    let name = name.trim_start_matches("./");
+    if let Some(path) = paths.iter().find(|path| path.as_str() == name) {
+        return Ok(path.clone());
+    }
+    let suffix = format!("/{name}");
+    let matches = paths
+        .iter()
+        .filter(|path| path.ends_with(&suffix))
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [path] => Ok((*path).clone()),
+        [] => bail!("no changed file matches {name}"),
+        _ => bail!(
+            "{name} matches several changed files: {}",
+            matches
+                .iter()
+                .map(|path| path.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DefaultReviewerListResponse {
+    values: Vec<DefaultReviewerValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefaultReviewerValue {
+    user: Option<PullRequestAuthor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchRestrictionListResponse {
+    values: Vec<BranchRestrictionValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BranchRestrictionValue {
+    value: Option<u32>,
+    branch_match_kind: Option<String>,
+    pattern: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitListResponse {
+    size: Option<u64>,
+    #[serde(default)]
+    values: Vec<CommitValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitValue {
+    message: Option<String>,
+}
+
+impl CommitListResponse {
+    fn into_summary(self) -> CommitSummary {
+        let latest_subject = self
+            .values
+            .into_iter()
+            .next()
+            .and_then(|commit| commit.message)
+            .and_then(|message| {
+                let subject = message.lines().next().unwrap_or_default().trim();
+                (!subject.is_empty()).then(|| subject.to_string())
+            });
+        CommitSummary {
+            count: self.size,
+            latest_subject,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitHashListResponse {
+    #[serde(default)]
+    values: Vec<serde::de::IgnoredAny>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentListResponse {
+    values: Vec<CommentValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentValue {
+    user: Option<PullRequestAuthor>,
+    content: Option<CommentContent>,
+    created_on: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommentContent {
+    raw: Option<String>,
+}
+
+impl CommentValue {
+    fn into_comment(self) -> Comment {
+        Comment {
+            author: self
+                .user
+                .and_then(|user| user.display_name.or(user.nickname))
+                .unwrap_or_else(|| "unknown".to_string()),
+            created_on: self.created_on.unwrap_or_default(),
+            raw: self
+                .content
+                .and_then(|content| content.raw)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildStatusListResponse {
+    values: Vec<BuildStatusValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildStatusValue {
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryListResponse {
+    values: Vec<RepositoryValue>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepositoryValue {
+    slug: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct PullRequestListResponse {
     values: Vec<PullRequestValue>,
+    next: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -147,9 +1604,129 @@ struct PullRequestValue {
     state: String,
     #[serde(default)]
     comment_count: u64,
+    #[serde(default)]
+    created_on: String,
     updated_on: String,
     author: PullRequestAuthor,
     links: PullRequestLinks,
+    #[serde(default)]
+    participants: Vec<ParticipantValue>,
+    source: Option<PullRequestEndpoint>,
+    destination: Option<PullRequestEndpoint>,
+    #[serde(default)]
+    close_source_branch: bool,
+}
+
+impl PullRequestValue {
+    fn relation_to(&self, user: &UserId) -> Relation {
+        let authored = user.matches_ids(
+            self.author.uuid.as_deref(),
+            self.author.account_id.as_deref(),
+            self.author.nickname.as_deref(),
+        );
+        let reviewing = self.participants.iter().any(|participant| {
+            participant.role == "REVIEWER"
+                && user.matches_ids(
+                    participant.user.uuid.as_deref(),
+                    participant.user.account_id.as_deref(),
+                    participant.user.nickname.as_deref(),
+                )
+        });
+        match (authored, reviewing) {
+            (true, true) => Relation::Both,
+            (false, true) => Relation::Reviewing,
+            (true, false) => Relation::Authored,
+            (false, false) => Relation::Other,
+        }
+    }
+
+    /// Converts a PR from a cross-repo listing, which carries its repo in
+    /// `destination.repository.full_name`. Returns `None` if that is missing.
+    fn into_pull_request_from_destination(self) -> Option<PullRequest> {
+        let full_name = self
+            .destination
+            .as_ref()?
+            .repository
+            .as_ref()?
+            .full_name
+            .clone()?;
+        let (workspace, repo) = full_name.split_once('/')?;
+        Some(self.into_pull_request(workspace, repo))
+    }
+
+    fn into_pull_request(self, workspace: &str, repo: &str) -> PullRequest {
+        let description = self
+            .description
+            .or_else(|| self.summary.and_then(|summary| summary.raw))
+            .unwrap_or_default();
+
+        PullRequest {
+            workspace: workspace.to_string(),
+            repo: repo.to_string(),
+            id: self.id,
+            title: self.title,
+            description,
+            author: self
+                .author
+                .display_name
+                .or(self.author.nickname)
+                .unwrap_or_else(|| "unknown".to_string()),
+            author_uuid: self.author.uuid,
+            author_account_id: self.author.account_id,
+            state: self.state,
+            comment_count: self.comment_count,
+            created_on: self.created_on,
+            updated_on: self.updated_on,
+            url: self.links.html.href,
+            source_branch: self
+                .source
+                .and_then(|source| source.branch)
+                .and_then(|branch| branch.name),
+            destination_branch: self
+                .destination
+                .as_ref()
+                .and_then(|destination| destination.branch.as_ref())
+                .and_then(|branch| branch.name.clone()),
+            close_source_branch: self.close_source_branch,
+            build_url: None,
+            diffstat: None,
+            relation: Relation::Authored,
+            issue_keys: Vec::new(),
+            participants: self
+                .participants
+                .into_iter()
+                .map(|participant| Participant {
+                    display_name: participant
+                        .user
+                        .display_name
+                        .or_else(|| participant.user.nickname.clone())
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    uuid: participant.user.uuid,
+                    account_id: participant.user.account_id,
+                    nickname: participant.user.nickname,
+                    role: participant.role,
+                    approved: participant.approved,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ParticipantValue {
+    user: ParticipantUser,
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    approved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParticipantUser {
+    uuid: Option<String>,
+    account_id: Option<String>,
+    nickname: Option<String>,
+    display_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -161,6 +1738,24 @@ struct PullRequestSummary {
 struct PullRequestAuthor {
     display_name: Option<String>,
     nickname: Option<String>,
+    uuid: Option<String>,
+    account_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestEndpoint {
+    repository: Option<EndpointRepository>,
+    branch: Option<EndpointBranch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EndpointRepository {
+    full_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EndpointBranch {
+    name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -172,3 +1767,713 @@ struct PullRequestLinks {
 struct PullRequestHtmlLink {
     href: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ApiResponse, BitbucketClient, Comment, CommentListResponse, CommentValue, CommitCount,
+        CommitListResponse, CommitSummary, DefaultReviewerRule, DiffStatListResponse, Endpoints,
+        MAX_PULL_REQUEST_PAGES, PULL_REQUEST_FIELDS, Participant, PullRequestQuery,
+        PullRequestValue, Relation, RequiredReviewers, TlsOptions, Transport, Truncation, UserId,
+        UserResponse, build_query, glob_matches, resolve_changed_file, validate_custom_query,
+    };
+    use crate::config::{AuthorIdentifier, PrStatus};
+    use chrono::{DateTime, Utc};
+    use reqwest::{Method, StatusCode, Url};
+    use std::cell::RefCell;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn query<'a>(
+        user: &'a UserId,
+        relation: Relation,
+        custom_query: Option<&'a str>,
+    ) -> PullRequestQuery<'a> {
+        PullRequestQuery {
+            user,
+            relation,
+            status: PrStatus::Open,
+            max_results: None,
+            custom_query,
+        }
+    }
+
+    fn parse_user(raw: &str) -> UserResponse {
+        serde_json::from_str(raw).expect("valid user fixture")
+    }
+
+    #[test]
+    fn orders_author_identifiers_by_preference() {
+        let raw = r#"{"uuid": "{abc}", "account_id": "557058:1", "nickname": "me"}"#;
+        let uuid = UserId::Uuid("{abc}".to_string());
+        let account_id = UserId::AccountId("557058:1".to_string());
+        assert_eq!(
+            parse_user(raw)
+                .into_user_ids(AuthorIdentifier::Uuid)
+                .expect("user ids"),
+            vec![uuid.clone(), account_id.clone()]
+        );
+        assert_eq!(
+            parse_user(raw)
+                .into_user_ids(AuthorIdentifier::AccountId)
+                .expect("user ids"),
+            vec![account_id, uuid]
+        );
+        assert_eq!(
+            parse_user(r#"{"nickname": "me"}"#)
+                .into_user_ids(AuthorIdentifier::Uuid)
+                .expect("user ids"),
+            vec![UserId::Nickname("me".to_string())]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_account_id_without_uuid() {
+        let user = parse_user(r#"{"account_id": "557058:1", "nickname": "me"}"#);
+        let user_ids = user
+            .into_user_ids(AuthorIdentifier::Uuid)
+            .expect("user ids");
+        assert_eq!(user_ids, vec![UserId::AccountId("557058:1".to_string())]);
+        assert_eq!(
+            build_query(&query(&user_ids[0], Relation::Authored, None)),
+            "author.account_id=\"557058:1\" AND state=\"OPEN\""
+        );
+    }
+
+    #[test]
+    fn custom_query_replaces_status_term() {
+        let user_id = UserId::Nickname("me".to_string());
+        assert_eq!(
+            build_query(&query(
+                &user_id,
+                Relation::Authored,
+                Some("reviewers.nickname=\"bob\" OR state=\"MERGED\"")
+            )),
+            "author.nickname=\"me\" AND (reviewers.nickname=\"bob\" OR state=\"MERGED\")"
+        );
+    }
+
+    #[test]
+    fn builds_reviewing_and_combined_relation_terms() {
+        let user_id = UserId::Nickname("me".to_string());
+        assert_eq!(
+            build_query(&query(&user_id, Relation::Reviewing, None)),
+            "reviewers.nickname=\"me\" AND state=\"OPEN\""
+        );
+        assert_eq!(
+            build_query(&query(&user_id, Relation::Both, None)),
+            "(author.nickname=\"me\" OR reviewers.nickname=\"me\") AND state=\"OPEN\""
+        );
+    }
+
+    #[test]
+    fn derives_relation_from_author_and_reviewers() {
+        let value: PullRequestValue = serde_json::from_str(
+            r#"{
+                "id": 9,
+                "title": "Review me",
+                "state": "OPEN",
+                "updated_on": "2026-01-01T00:00:00+00:00",
+                "author": {"display_name": "Bob", "uuid": "{bob}"},
+                "links": {"html": {"href": "https://bitbucket.org/team/app/pull-requests/9"}},
+                "participants": [
+                    {"user": {"uuid": "{me}", "display_name": "Me"}, "role": "REVIEWER", "approved": false}
+                ]
+            }"#,
+        )
+        .expect("pull request fixture");
+
+        assert_eq!(
+            value.relation_to(&UserId::Uuid("{me}".to_string())),
+            Relation::Reviewing
+        );
+        assert_eq!(
+            value.relation_to(&UserId::Uuid("{bob}".to_string())),
+            Relation::Authored
+        );
+        assert_eq!(
+            value.relation_to(&UserId::Uuid("{carol}".to_string())),
+            Relation::Other
+        );
+    }
+
+    #[test]
+    fn validates_custom_query_quotes_and_parens() {
+        assert!(validate_custom_query("state=\"OPEN\" AND title ~ \"a \\\" b\"").is_ok());
+        assert!(validate_custom_query("(state=\"OPEN\")").is_ok());
+        assert!(validate_custom_query("state=\"OPEN").is_err());
+        assert!(validate_custom_query("(state=\"OPEN\"").is_err());
+        assert!(validate_custom_query("title ~ \")\"").is_ok());
+        assert!(validate_custom_query("   ").is_err());
+    }
+
+    #[test]
+    fn builds_cloud_endpoint_urls() {
+        let endpoints = Endpoints::new("https://api.bitbucket.org/2.0/");
+        assert_eq!(endpoints.user(), "https://api.bitbucket.org/2.0/user");
+        assert_eq!(
+            endpoints.user_pull_requests("{abc}"),
+            "https://api.bitbucket.org/2.0/pullrequests/{abc}"
+        );
+        assert_eq!(
+            endpoints.repositories("team"),
+            "https://api.bitbucket.org/2.0/repositories/team"
+        );
+        assert_eq!(
+            endpoints.pull_requests("team", "api"),
+            "https://api.bitbucket.org/2.0/repositories/team/api/pullrequests"
+        );
+        assert_eq!(
+            endpoints.pull_request("team", "api", 7),
+            "https://api.bitbucket.org/2.0/repositories/team/api/pullrequests/7"
+        );
+        assert_eq!(
+            endpoints.pull_request_resource("team", "api", 7, "approve"),
+            "https://api.bitbucket.org/2.0/repositories/team/api/pullrequests/7/approve"
+        );
+        assert_eq!(
+            endpoints.default_reviewers("team", "api"),
+            "https://api.bitbucket.org/2.0/repositories/team/api/effective-default-reviewers"
+        );
+        assert_eq!(
+            endpoints.branch_restrictions("team", "api"),
+            "https://api.bitbucket.org/2.0/repositories/team/api/branch-restrictions"
+        );
+    }
+
+    #[test]
+    fn detects_own_approval_from_participants() {
+        let value: PullRequestValue = serde_json::from_str(
+            r#"{
+                "id": 7,
+                "title": "Fix login",
+                "state": "OPEN",
+                "updated_on": "2026-01-01T00:00:00+00:00",
+                "author": {"display_name": "Me"},
+                "links": {"html": {"href": "https://bitbucket.org/team/app/pull-requests/7"}},
+                "participants": [
+                    {"user": {"uuid": "{me}", "display_name": "Me"}, "role": "PARTICIPANT", "approved": false},
+                    {"user": {"uuid": "{bob}", "display_name": "Bob"}, "role": "REVIEWER", "approved": true}
+                ]
+            }"#,
+        )
+        .expect("pull request fixture");
+        let pr = value.into_pull_request("team", "app");
+
+        assert_eq!(pr.approvals(), 1);
+        assert!(pr.is_approved_by(&UserId::Uuid("{bob}".to_string())));
+        assert!(!pr.is_approved_by(&UserId::Uuid("{me}".to_string())));
+    }
+
+    #[test]
+    fn reads_repo_from_destination_in_cross_repo_listing() {
+        let value: PullRequestValue = serde_json::from_str(
+            r#"{
+                "id": 3,
+                "title": "Bump deps",
+                "state": "OPEN",
+                "updated_on": "2026-01-01T00:00:00+00:00",
+                "author": {"display_name": "Me"},
+                "links": {"html": {"href": "https://bitbucket.org/team/web/pull-requests/3"}},
+                "source": {"branch": {"name": "feature/bump-deps"}},
+                "destination": {"repository": {"full_name": "team/web"}}
+            }"#,
+        )
+        .expect("pull request fixture");
+        let pr = value
+            .into_pull_request_from_destination()
+            .expect("destination repo");
+
+        assert_eq!(pr.workspace, "team");
+        assert_eq!(pr.repo, "web");
+        assert_eq!(pr.id, 3);
+        assert_eq!(pr.source_branch.as_deref(), Some("feature/bump-deps"));
+    }
+
+    #[test]
+    fn derives_repo_pull_request_page_from_pr_link() {
+        let pr_linked = |href: &str| {
+            let value: PullRequestValue = serde_json::from_str(&format!(
+                r#"{{
+                    "id": 5,
+                    "title": "Slow review",
+                    "state": "OPEN",
+                    "updated_on": "2026-03-02T10:00:00+00:00",
+                    "author": {{"display_name": "Me"}},
+                    "links": {{"html": {{"href": "{href}"}}}}
+                }}"#
+            ))
+            .expect("pull request fixture");
+            value.into_pull_request("team", "app")
+        };
+
+        assert_eq!(
+            pr_linked("https://bitbucket.org/team/app/pull-requests/5").repo_pull_requests_url(),
+            "https://bitbucket.org/team/app/pull-requests"
+        );
+        assert_eq!(
+            pr_linked("https://git.example.com/projects/TEAM/repos/app/pull-requests/5/overview")
+                .repo_pull_requests_url(),
+            "https://git.example.com/projects/TEAM/repos/app/pull-requests"
+        );
+        assert_eq!(
+            pr_linked("").repo_pull_requests_url(),
+            "https://bitbucket.org/team/app/pull-requests"
+        );
+    }
+
+    #[test]
+    fn counts_days_waiting_for_first_approval() {
+        let pr_with = |state: &str, approved: bool| {
+            let value: PullRequestValue = serde_json::from_str(&format!(
+                r#"{{
+                    "id": 5,
+                    "title": "Slow review",
+                    "state": "{state}",
+                    "created_on": "2026-03-01T10:00:00.123456+00:00",
+                    "updated_on": "2026-03-02T10:00:00+00:00",
+                    "author": {{"display_name": "Me"}},
+                    "links": {{"html": {{"href": "https://bitbucket.org/team/app/pull-requests/5"}}}},
+                    "participants": [
+                        {{"user": {{"uuid": "{{bob}}"}}, "role": "REVIEWER", "approved": {approved}}}
+                    ]
+                }}"#
+            ))
+            .expect("pull request fixture");
+            value.into_pull_request("team", "app")
+        };
+        let now = DateTime::parse_from_rfc3339("2026-03-04T09:00:00+00:00")
+            .expect("timestamp")
+            .with_timezone(&Utc);
+
+        assert_eq!(pr_with("OPEN", false).waiting_for_review_days(now), Some(2));
+        assert_eq!(pr_with("OPEN", true).waiting_for_review_days(now), None);
+        assert_eq!(pr_with("MERGED", false).waiting_for_review_days(now), None);
+    }
+
+    #[test]
+    fn parses_latest_comment_and_empty_page() {
+        let payload: CommentListResponse = serde_json::from_str(
+            r#"{"values": [{"user": {"display_name": "Bob"}, "content": {"raw": "LGTM"}, "created_on": "2026-03-01T10:00:00+00:00"}]}"#,
+        )
+        .expect("comments fixture");
+        let comment = payload
+            .values
+            .into_iter()
+            .next()
+            .map(CommentValue::into_comment);
+        assert_eq!(
+            comment,
+            Some(Comment {
+                author: "Bob".to_string(),
+                created_on: "2026-03-01T10:00:00+00:00".to_string(),
+                raw: "LGTM".to_string(),
+            })
+        );
+
+        let empty: CommentListResponse =
+            serde_json::from_str(r#"{"values": []}"#).expect("empty comments fixture");
+        assert!(empty.values.is_empty());
+    }
+
+    /// Serves commit pages: ten pages when the destination is excluded,
+    /// one short page when the source is.
+    struct CommitPages;
+
+    impl Transport for CommitPages {
+        fn send(&self, _method: Method, endpoint: &Url) -> anyhow::Result<ApiResponse> {
+            let query = endpoint.query().unwrap_or_default();
+            let page = endpoint
+                .query_pairs()
+                .find(|(name, _)| name == "page")
+                .map_or(1, |(_, page)| page.parse::<u32>().expect("page"));
+            let body = if query.contains("exclude=main") {
+                let values = vec![r#"{"hash": "a"}"#; 50].join(",");
+                format!(
+                    r#"{{"values": [{values}], "next": "https://api.bitbucket.org/2.0/repositories/team/api/commits?exclude=main&page={}"}}"#,
+                    page + 1
+                )
+            } else {
+                r#"{"values": [{"hash": "b"}, {"hash": "c"}]}"#.to_string()
+            };
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body,
+            })
+        }
+    }
+
+    /// Serves `pages` pages of two PRs each, linked by `next`.
+    struct PullRequestPages {
+        pages: u64,
+    }
+
+    impl Transport for PullRequestPages {
+        fn send(&self, _method: Method, endpoint: &Url) -> anyhow::Result<ApiResponse> {
+            let page = endpoint
+                .query_pairs()
+                .find(|(name, _)| name == "page")
+                .map_or(1, |(_, page)| page.parse::<u64>().expect("page"));
+            let values = [page * 10 + 1, page * 10 + 2]
+                .map(|id| {
+                    format!(
+                        r#"{{"id": {id}, "title": "PR {id}", "state": "MERGED",
+                            "updated_on": "2026-03-02T10:00:00+00:00",
+                            "author": {{"display_name": "Me"}},
+                            "links": {{"html": {{"href": ""}}}}}}"#
+                    )
+                })
+                .join(",");
+            let next = if page < self.pages {
+                format!(
+                    r#", "next": "https://api.bitbucket.org/2.0/repositories/team/app/pullrequests?page={}""#,
+                    page + 1
+                )
+            } else {
+                String::new()
+            };
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body: format!(r#"{{"values": [{values}]{next}}}"#),
+            })
+        }
+    }
+
+    #[test]
+    fn follows_pull_request_pages_up_to_the_result_cap() {
+        let client = BitbucketClient::with_transport(
+            "https://api.bitbucket.org/2.0",
+            Box::new(PullRequestPages { pages: 3 }),
+        );
+        let user = UserId::Uuid("{me}".to_string());
+
+        let progress = RefCell::new(Vec::new());
+        let batch = client
+            .list_pull_requests(
+                "team",
+                "app",
+                &query(&user, Relation::Authored, None),
+                |page, fetched| progress.borrow_mut().push((page, fetched)),
+            )
+            .expect("all pages");
+        assert_eq!(progress.into_inner(), vec![(1, 2), (2, 4), (3, 6)]);
+        assert_eq!(
+            batch
+                .pull_requests
+                .iter()
+                .map(|pr| pr.id)
+                .collect::<Vec<_>>(),
+            vec![11, 12, 21, 22, 31, 32]
+        );
+        assert_eq!(batch.truncated, None);
+
+        let capped = PullRequestQuery {
+            max_results: Some(3),
+            ..query(&user, Relation::Authored, None)
+        };
+        let batch = client
+            .list_pull_requests("team", "app", &capped, |_, _| {})
+            .expect("capped");
+        assert_eq!(batch.pull_requests.len(), 3);
+        assert_eq!(batch.truncated, Some(Truncation::Cap(3)));
+    }
+
+    #[test]
+    fn reports_hitting_the_page_limit() {
+        let client = BitbucketClient::with_transport(
+            "https://api.bitbucket.org/2.0",
+            Box::new(PullRequestPages { pages: 100 }),
+        );
+        let user = UserId::Uuid("{me}".to_string());
+
+        let batch = client
+            .list_pull_requests(
+                "team",
+                "app",
+                &query(&user, Relation::Authored, None),
+                |_, _| {},
+            )
+            .expect("pages");
+        assert_eq!(batch.pull_requests.len(), 2 * MAX_PULL_REQUEST_PAGES);
+        assert_eq!(
+            batch.truncated,
+            Some(Truncation::PageLimit(MAX_PULL_REQUEST_PAGES))
+        );
+        assert_eq!(
+            batch.truncated.map(|truncation| truncation.to_string()),
+            Some("truncated after 20 pages".to_string())
+        );
+    }
+
+    #[test]
+    fn counts_divergence_and_caps_long_histories() {
+        let client =
+            BitbucketClient::with_transport("https://api.bitbucket.org/2.0", Box::new(CommitPages));
+        let divergence = client
+            .branch_divergence("team", "api", "feature/retries", "main")
+            .expect("divergence");
+        assert_eq!(
+            divergence.ahead,
+            CommitCount {
+                count: 200,
+                capped: true
+            }
+        );
+        assert_eq!(divergence.ahead.to_string(), "200+");
+        assert_eq!(divergence.behind.to_string(), "2");
+    }
+
+    #[test]
+    fn summarizes_commits_page() {
+        let payload: CommitListResponse = serde_json::from_str(
+            r#"{"size": 3, "pagelen": 1, "values": [{"message": "Fix parser\n\nLonger body"}]}"#,
+        )
+        .expect("commits fixture");
+        assert_eq!(
+            payload.into_summary(),
+            CommitSummary {
+                count: Some(3),
+                latest_subject: Some("Fix parser".to_string()),
+            }
+        );
+
+        let empty: CommitListResponse =
+            serde_json::from_str(r#"{"pagelen": 1}"#).expect("empty commits fixture");
+        assert_eq!(empty.into_summary(), CommitSummary::default());
+    }
+
+    #[test]
+    fn lists_required_reviewers_without_approval() {
+        let reviewer = |uuid: &str, name: &str, approved: bool| Participant {
+            uuid: Some(uuid.to_string()),
+            account_id: None,
+            nickname: None,
+            display_name: name.to_string(),
+            role: "REVIEWER".to_string(),
+            approved,
+        };
+        let value: PullRequestValue = serde_json::from_str(
+            r#"{
+                "id": 8,
+                "title": "Gate",
+                "state": "OPEN",
+                "updated_on": "2026-03-02T10:00:00+00:00",
+                "author": {"display_name": "Me", "uuid": "{me}"},
+                "links": {"html": {"href": "https://bitbucket.org/team/app/pull-requests/8"}},
+                "destination": {"branch": {"name": "release/2.0"}}
+            }"#,
+        )
+        .expect("pull request fixture");
+        let mut pr = value.into_pull_request("team", "app");
+        pr.participants = vec![
+            reviewer("{bob}", "Bob", true),
+            reviewer("{cy}", "Cy", false),
+        ];
+
+        let rule = |pattern: &str, approvals: u32| DefaultReviewerRule {
+            pattern: pattern.to_string(),
+            approvals,
+        };
+        let mut required = RequiredReviewers {
+            reviewers: vec![
+                reviewer("{bob}", "Bob", false),
+                reviewer("{cy}", "Cy", false),
+                reviewer("{dee}", "Dee", false),
+                reviewer("{me}", "Someone else's name", false),
+            ],
+            rules: vec![rule("main", 3), rule("release/*", 2)],
+        };
+        assert_eq!(pr.pending_required_reviewers(&required), vec!["Cy", "Dee"]);
+
+        // Bob's approval is enough once only one is needed.
+        required.rules = vec![rule("release/*", 1)];
+        assert!(pr.pending_required_reviewers(&required).is_empty());
+
+        // Default reviewers alone don't block merging.
+        required.rules.clear();
+        assert!(pr.pending_required_reviewers(&required).is_empty());
+        assert!(
+            pr.pending_required_reviewers(&RequiredReviewers::default())
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn matches_branch_restriction_globs() {
+        assert!(glob_matches("main", "main"));
+        assert!(!glob_matches("main", "main2"));
+        assert!(glob_matches("release/*", "release/2.0"));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("feature/*/ready", "feature/x/ready"));
+        assert!(!glob_matches("feature/*/ready", "feature/x/wip"));
+        assert!(!glob_matches("a*a", "a"));
+    }
+
+    /// Serves one glob and one branching-model restriction, and one default
+    /// reviewer.
+    struct RestrictedRepo;
+
+    impl Transport for RestrictedRepo {
+        fn send(&self, _method: Method, endpoint: &Url) -> anyhow::Result<ApiResponse> {
+            let body = if endpoint.path().ends_with("/branch-restrictions") {
+                assert!(
+                    endpoint
+                        .query()
+                        .unwrap_or_default()
+                        .contains("kind=require_default_reviewer_approvals_to_merge")
+                );
+                r#"{"values": [
+                    {"kind": "require_default_reviewer_approvals_to_merge", "value": 1,
+                     "branch_match_kind": "glob", "pattern": "main"},
+                    {"kind": "require_default_reviewer_approvals_to_merge", "value": 2,
+                     "branch_match_kind": "branching_model", "branch_type": "release"}
+                ]}"#
+            } else {
+                r#"{"values": [{"user": {"display_name": "Bob", "uuid": "{bob}"}}]}"#
+            };
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body: body.to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn reads_required_reviewers_from_branch_restrictions() {
+        let client = BitbucketClient::with_transport(
+            "https://api.bitbucket.org/2.0",
+            Box::new(RestrictedRepo),
+        );
+        let required = client.required_reviewers("team", "api").expect("required");
+        assert_eq!(
+            required.rules,
+            vec![DefaultReviewerRule {
+                pattern: "main".to_string(),
+                approvals: 1,
+            }]
+        );
+        assert_eq!(required.reviewers.len(), 1);
+        assert_eq!(required.approvals_needed("main"), 1);
+        assert_eq!(required.approvals_needed("develop"), 0);
+    }
+
+    #[test]
+    fn narrowed_pull_request_listing_still_deserializes() {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().expect("addr"));
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().expect("accept");
+            let mut reader = BufReader::new(stream.try_clone().expect("clone stream"));
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).expect("request line");
+            let mut header = String::new();
+            while reader.read_line(&mut header).expect("header") > 2 {
+                header.clear();
+            }
+            // Only the fields named in PULL_REQUEST_FIELDS.
+            let body = r#"{"values": [{
+                "id": 7, "title": "Narrow", "description": "Body", "summary": {"raw": "Body"},
+                "state": "OPEN", "comment_count": 3,
+                "created_on": "2026-03-01T10:00:00+00:00", "updated_on": "2026-03-02T10:00:00+00:00",
+                "author": {"display_name": "Me", "nickname": "me", "uuid": "{me}", "account_id": "1"},
+                "links": {"html": {"href": "https://bitbucket.org/team/app/pull-requests/7"}},
+                "participants": [{"role": "REVIEWER", "approved": true,
+                    "user": {"display_name": "Bob", "nickname": "bob", "uuid": "{bob}", "account_id": "2"}}],
+                "source": {"branch": {"name": "feature/narrow"}},
+                "destination": {"branch": {"name": "main"}, "repository": {"full_name": "team/app"}},
+                "close_source_branch": true
+            }]}"#;
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .expect("write response");
+            request_line
+        });
+
+        let client = BitbucketClient::new(
+            base_url,
+            "me@example.com".to_string(),
+            "token".to_string(),
+            &TlsOptions::default(),
+        )
+        .expect("client");
+        let user = UserId::Uuid("{me}".to_string());
+        let batch = client
+            .list_pull_requests(
+                "team",
+                "app",
+                &query(&user, Relation::Authored, None),
+                |_, _| {},
+            )
+            .expect("narrowed listing");
+
+        let request_line = server.join().expect("mock server");
+        let fields = Url::parse(&format!(
+            "http://x{}",
+            request_line.split(' ').nth(1).unwrap_or("")
+        ))
+        .expect("request url")
+        .query_pairs()
+        .find(|(key, _)| key == "fields")
+        .map(|(_, value)| value.into_owned());
+        assert_eq!(fields.as_deref(), Some(PULL_REQUEST_FIELDS));
+
+        let pr = &batch.pull_requests[0];
+        assert_eq!((pr.id, pr.comment_count), (7, 3));
+        assert_eq!(pr.source_branch.as_deref(), Some("feature/narrow"));
+        assert!(pr.close_source_branch);
+        assert_eq!(pr.approvals(), 1);
+        assert_eq!(pr.relation, Relation::Authored);
+    }
+
+    #[test]
+    fn rejects_user_without_identifiers() {
+        let user = parse_user(r#"{"display_name": "Someone", "uuid": ""}"#);
+        let err = user
+            .into_user_ids(AuthorIdentifier::Uuid)
+            .expect_err("missing identifiers");
+        assert!(err.to_string().contains("no uuid, account_id, or nickname"));
+    }
+
+    #[test]
+    fn resolves_changed_files_by_path_or_suffix() {
+        let page: DiffStatListResponse = serde_json::from_str(
+            r#"{"values": [
+                {"old": {"path": "src/lib.rs"}, "new": {"path": "src/lib.rs"}},
+                {"old": null, "new": {"path": "src/api/mod.rs"}},
+                {"old": {"path": "src/cli/mod.rs"}, "new": null}
+            ]}"#,
+        )
+        .expect("diffstat fixture");
+        let paths = page
+            .values
+            .into_iter()
+            .filter_map(|value| value.into_path())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["src/lib.rs", "src/api/mod.rs", "src/cli/mod.rs"]);
+
+        assert_eq!(
+            resolve_changed_file(&paths, "./src/lib.rs").expect("exact"),
+            "src/lib.rs"
+        );
+        assert_eq!(
+            resolve_changed_file(&paths, "lib.rs").expect("suffix"),
+            "src/lib.rs"
+        );
+        assert_eq!(
+            resolve_changed_file(&paths, "cli/mod.rs").expect("deleted file"),
+            "src/cli/mod.rs"
+        );
+        let ambiguous = resolve_changed_file(&paths, "mod.rs").expect_err("ambiguous");
+        assert!(
+            ambiguous
+                .to_string()
+                .contains("src/api/mod.rs, src/cli/mod.rs")
+        );
+        let missing = resolve_changed_file(&paths, "main.rs").expect_err("missing");
+        assert_eq!(missing.to_string(), "no changed file matches main.rs");
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::{Context, Result, bail};
+use std::process::{Command, Stdio};
+
+/// Opens `url` with `browser_command` when configured, otherwise with the
+/// system default browser. Returns the launched command line, if any, so
+/// callers can log it.
+pub fn open(url: &str, browser_command: Option<&str>) -> Result<Option<String>> {
+    let Some(browser_command) = browser_command.filter(|command| !command.trim().is_empty()) else {
+        webbrowser::open(url).with_context(|| format!("failed to open {url}"))?;
+        return Ok(None);
+    };
+
+    let argv = command_argv(browser_command, url)?;
+    let mut child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("failed to launch browser command '{}'", argv[0]))?;
+    // Reap the browser process in the background so it doesn't linger as a zombie.
+    std::thread::spawn(move || child.wait());
+
+    Ok(Some(argv.join(" ")))
+}
+
+/// Splits `command` on whitespace and substitutes `%u` with `url`, appending
+/// the URL as the last argument when there is no placeholder.
+fn command_argv(command: &str, url: &str) -> Result<Vec<String>> {
+    let mut has_placeholder = false;
+    let mut argv = command
+        .split_whitespace()
+        .map(|arg| {
+            if arg.contains("%u") {
+                has_placeholder = true;
+                arg.replace("%u", url)
+            } else {
+                arg.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    if argv.is_empty() {
+        bail!("browser_command is empty");
+    }
+    if !has_placeholder {
+        argv.push(url.to_string());
+    }
+    Ok(argv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::command_argv;
+
+    #[test]
+    fn substitutes_url_placeholder_or_appends_url() {
+        let url = "https://bitbucket.org/team/api/pull-requests/7";
+        assert_eq!(
+            command_argv("firefox --new-window", url).expect("argv"),
+            vec!["firefox", "--new-window", url]
+        );
+        assert_eq!(
+            command_argv("open -a Safari %u --args", url).expect("argv"),
+            vec!["open", "-a", "Safari", url, "--args"]
+        );
+        assert!(command_argv("   ", url).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use crate::bitbucket::{ApiResponse, BitbucketClient, Transport};
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where API responses are written to (`--record`) or served from
+/// (`--replay`) for this session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiCapture {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// One recorded response. The body is kept verbatim so parsing problems
+/// reproduce exactly; request headers, and so credentials, are not stored.
+#[derive(Debug, Serialize, Deserialize)]
+struct Recording {
+    method: String,
+    url: String,
+    status: u16,
+    body: String,
+}
+
+/// Routes `client` through the capture directory, if one is set.
+pub fn apply(client: BitbucketClient, capture: Option<&ApiCapture>) -> BitbucketClient {
+    match capture {
+        None => client,
+        Some(ApiCapture::Record(dir)) => client.wrap_transport(|inner| {
+            Box::new(RecordingTransport {
+                inner,
+                dir: dir.clone(),
+            })
+        }),
+        Some(ApiCapture::Replay(dir)) => {
+            client.wrap_transport(|_| Box::new(ReplayTransport { dir: dir.clone() }))
+        }
+    }
+}
+
+/// Passes requests on and writes each response to `dir`.
+struct RecordingTransport {
+    inner: Box<dyn Transport>,
+    dir: PathBuf,
+}
+
+impl Transport for RecordingTransport {
+    fn send(&self, method: Method, endpoint: &Url) -> Result<ApiResponse> {
+        let response = self.inner.send(method.clone(), endpoint)?;
+        let recording = Recording {
+            method: method.to_string(),
+            url: endpoint.to_string(),
+            status: response.status.as_u16(),
+            body: response.body.clone(),
+        };
+        let path = recording_path(&self.dir, &method, endpoint);
+        let raw =
+            serde_json::to_string_pretty(&recording).context("failed to serialize response")?;
+        fs::write(&path, raw)
+            .with_context(|| format!("failed to record response to {}", path.display()))?;
+        Ok(response)
+    }
+}
+
+/// Answers requests from the files a recording session wrote, without
+/// touching the network.
+struct ReplayTransport {
+    dir: PathBuf,
+}
+
+impl Transport for ReplayTransport {
+    fn send(&self, method: Method, endpoint: &Url) -> Result<ApiResponse> {
+        let path = recording_path(&self.dir, &method, endpoint);
+        let raw = fs::read_to_string(&path).map_err(|_| {
+            anyhow!(
+                "no recorded response for {method} {endpoint} in {}",
+                self.dir.display()
+            )
+        })?;
+        let recording: Recording = serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse recorded response {}", path.display()))?;
+        Ok(ApiResponse {
+            status: StatusCode::from_u16(recording.status)
+                .with_context(|| format!("invalid status in {}", path.display()))?,
+            body: recording.body,
+        })
+    }
+}
+
+/// File for a request, named by method and a hash of the URL path and query
+/// so recordings replay against any base URL host.
+fn recording_path(dir: &Path, method: &Method, endpoint: &Url) -> PathBuf {
+    let target = match endpoint.query() {
+        Some(query) => format!("{}?{query}", endpoint.path()),
+        None => endpoint.path().to_string(),
+    };
+    dir.join(format!("{method}-{:016x}.json", fnv1a(&target)))
+}
+
+/// FNV-1a, which unlike the std hasher is stable across Rust releases.
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiCapture, apply};
+    use crate::bitbucket::{ApiResponse, BitbucketClient, Transport, UserId};
+    use crate::config::AuthorIdentifier;
+    use anyhow::Result;
+    use reqwest::{Method, StatusCode, Url};
+    use std::fs;
+
+    struct CannedUser;
+
+    impl Transport for CannedUser {
+        fn send(&self, _method: Method, _endpoint: &Url) -> Result<ApiResponse> {
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body: r#"{"uuid": "{abc}", "nickname": "me"}"#.to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn replays_recorded_responses() {
+        let dir = std::env::temp_dir().join(format!("myprs-capture-{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("capture dir");
+
+        let recording = apply(
+            BitbucketClient::with_transport("https://api.bitbucket.org/2.0", Box::new(CannedUser)),
+            Some(&ApiCapture::Record(dir.clone())),
+        );
+        let expected = vec![UserId::Uuid("{abc}".to_string())];
+        assert_eq!(
+            recording
+                .current_user_ids(AuthorIdentifier::Uuid)
+                .expect("recorded"),
+            expected
+        );
+
+        let replaying = apply(
+            BitbucketClient::new(
+                "https://bitbucket.example.com/2.0".to_string(),
+                String::new(),
+                String::new(),
+                &Default::default(),
+            )
+            .expect("client"),
+            Some(&ApiCapture::Replay(dir.clone())),
+        );
+        assert_eq!(
+            replaying
+                .current_user_ids(AuthorIdentifier::Uuid)
+                .expect("replayed"),
+            expected
+        );
+        let err = replaying
+            .latest_comment("team", "api", 1)
+            .expect_err("nothing recorded");
+        assert!(format!("{err:#}").contains("no recorded response for GET"));
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
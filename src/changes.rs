@@ -0,0 +1,244 @@
+use crate::bitbucket::PullRequest;
+use crate::config::Config;
+use crate::headless;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// What is remembered about each PR between `--changes-since-last-run`
+/// invocations; enough to report a PR that has since disappeared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    pub workspace: String,
+    pub repo: String,
+    pub id: u64,
+    pub title: String,
+    pub author: String,
+    pub state: String,
+    pub updated_on: String,
+    pub url: String,
+}
+
+impl From<&PullRequest> for SnapshotEntry {
+    fn from(pr: &PullRequest) -> Self {
+        Self {
+            workspace: pr.workspace.clone(),
+            repo: pr.repo.clone(),
+            id: pr.id,
+            title: pr.title.clone(),
+            author: pr.author.clone(),
+            state: pr.state.clone(),
+            updated_on: pr.updated_on.clone(),
+            url: pr.url.clone(),
+        }
+    }
+}
+
+impl SnapshotEntry {
+    fn key(&self) -> String {
+        format!("{}/{}/{}", self.workspace, self.repo, self.id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Removed,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub change: ChangeKind,
+    #[serde(flatten)]
+    pub pull_request: SnapshotEntry,
+}
+
+/// Stored snapshots, one per query (see `query_key`), so runs with a
+/// different status filter or repo set do not report each other's PRs.
+type Snapshots = BTreeMap<String, Vec<SnapshotEntry>>;
+
+/// Fetches the PR list, prints what was added, updated (new `updated_on`
+/// or state), or removed since the stored snapshot for the same query as
+/// JSON, then replaces that snapshot. On the first run of a query every PR
+/// is reported as added.
+pub fn run(config: &Config) -> Result<()> {
+    let path = snapshot_path()?;
+    let mut snapshots = match fs::read_to_string(&path) {
+        Ok(raw) => parse_snapshots(&raw)
+            .with_context(|| format!("failed to parse snapshot at {}", path.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Snapshots::new(),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("failed to read snapshot at {}", path.display()));
+        }
+    };
+
+    let key = query_key(config);
+    let previous = snapshots.remove(&key).unwrap_or_default();
+    let current = headless::fetch_pull_requests(config)?
+        .iter()
+        .map(SnapshotEntry::from)
+        .collect::<Vec<_>>();
+    let changes = diff(&previous, &current);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&changes).context("failed to serialize changes")?
+    );
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create snapshot directory {}", parent.display()))?;
+    }
+    snapshots.insert(key, current);
+    let snapshot = serde_json::to_string(&snapshots).context("failed to serialize snapshot")?;
+    // Write a sibling file and rename it over the snapshot so an interrupted
+    // run never leaves a truncated file behind.
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, snapshot)
+        .with_context(|| format!("failed to write snapshot at {}", temp.display()))?;
+    fs::rename(&temp, &path)
+        .with_context(|| format!("failed to replace snapshot at {}", path.display()))
+}
+
+/// Identifies the query a snapshot was taken for: provider, status filter,
+/// and the watched repos in a stable order.
+fn query_key(config: &Config) -> String {
+    let mut repos = config
+        .repos()
+        .iter()
+        .map(|repo| repo.to_string().to_lowercase())
+        .collect::<Vec<_>>();
+    repos.sort();
+    repos.dedup();
+    format!(
+        "{} {} {}",
+        config.provider(),
+        config.launch_status(),
+        repos.join(",")
+    )
+}
+
+/// Reads the snapshot file. A snapshot from before snapshots were keyed by
+/// query cannot be matched to one, so it is dropped.
+fn parse_snapshots(raw: &str) -> serde_json::Result<Snapshots> {
+    serde_json::from_str::<Snapshots>(raw).or_else(|err| {
+        serde_json::from_str::<Vec<SnapshotEntry>>(raw)
+            .map(|_| Snapshots::new())
+            .map_err(|_| err)
+    })
+}
+
+fn snapshot_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("cannot determine home directory")?;
+    Ok(home.join(".config").join("myprs").join("snapshot.json"))
+}
+
+/// Changes from `previous` to `current`, in `current` order followed by
+/// removed PRs in key order.
+pub fn diff(previous: &[SnapshotEntry], current: &[SnapshotEntry]) -> Vec<Change> {
+    let mut previous_by_key = previous
+        .iter()
+        .map(|entry| (entry.key(), entry))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut changes = Vec::new();
+    for entry in current {
+        let change = match previous_by_key.remove(&entry.key()) {
+            None => ChangeKind::Added,
+            Some(old) if old.updated_on != entry.updated_on || old.state != entry.state => {
+                ChangeKind::Updated
+            }
+            Some(_) => continue,
+        };
+        changes.push(Change {
+            change,
+            pull_request: entry.clone(),
+        });
+    }
+    changes.extend(previous_by_key.into_values().map(|entry| Change {
+        change: ChangeKind::Removed,
+        pull_request: entry.clone(),
+    }));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChangeKind, SnapshotEntry, diff, parse_snapshots, query_key};
+    use crate::config::{Config, PrStatus, RepoRef};
+
+    fn entry(id: u64, updated_on: &str) -> SnapshotEntry {
+        SnapshotEntry {
+            workspace: "team".to_string(),
+            repo: "api".to_string(),
+            id,
+            title: format!("PR {id}"),
+            author: "Me".to_string(),
+            state: "OPEN".to_string(),
+            updated_on: updated_on.to_string(),
+            url: String::new(),
+        }
+    }
+
+    #[test]
+    fn reports_added_updated_and_removed_pull_requests() {
+        let previous = [
+            entry(1, "2026-03-01"),
+            entry(2, "2026-03-01"),
+            entry(3, "2026-03-01"),
+        ];
+        let current = [
+            entry(4, "2026-03-02"),
+            entry(2, "2026-03-02"),
+            entry(3, "2026-03-01"),
+        ];
+
+        let changes = diff(&previous, &current);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| (change.change, change.pull_request.id))
+                .collect::<Vec<_>>(),
+            vec![
+                (ChangeKind::Added, 4),
+                (ChangeKind::Updated, 2),
+                (ChangeKind::Removed, 1)
+            ]
+        );
+
+        let json = serde_json::to_value(&changes[0]).expect("json");
+        assert_eq!(json["change"], "added");
+        assert_eq!(json["id"], 4);
+    }
+
+    #[test]
+    fn keys_snapshots_by_status_and_repo_set() {
+        let mut config = Config::default();
+        config.repos = vec![
+            RepoRef::parse("team/web").expect("repo"),
+            RepoRef::parse("Team/API").expect("repo"),
+        ];
+        let open = query_key(&config);
+
+        config.repos.reverse();
+        assert_eq!(query_key(&config), open);
+
+        config.set_session_status(PrStatus::Merged);
+        assert_ne!(query_key(&config), open);
+
+        config.set_session_status(PrStatus::Open);
+        config.repos.pop();
+        assert_ne!(query_key(&config), open);
+    }
+
+    #[test]
+    fn drops_unkeyed_snapshots() {
+        let legacy = serde_json::to_string(&[entry(1, "2026-03-01")]).expect("json");
+        assert!(parse_snapshots(&legacy).expect("legacy").is_empty());
+        assert!(parse_snapshots("{\"open\": 1}").is_err());
+    }
+}
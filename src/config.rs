@@ -1,19 +1,56 @@
+use crate::bitbucket::TlsOptions;
+use crate::capture::ApiCapture;
+use crate::credentials::{self, CredentialLayer, CredentialSource, ResolvedCredentials};
+use crate::debug_log;
+use crate::git_credentials;
+use crate::issues;
+use crate::keymap::Keymap;
+use crate::keyring;
 use anyhow::{Context, Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::str::FromStr;
 
 const DEFAULT_BITBUCKET_BASE_URL: &str = "https://api.bitbucket.org/2.0";
+/// Shown whenever a session runs with certificate checks off.
+pub const INSECURE_TLS_WARNING: &str =
+    "WARNING: TLS certificate verification is DISABLED (danger_accept_invalid_certs = true).";
+const DEFAULT_GITHUB_BASE_URL: &str = "https://api.github.com";
 const DEFAULT_AUTO_REFRESH_SECONDS: u64 = 120;
+const DEFAULT_STALE_AFTER_DAYS: u32 = 90;
+const DEFAULT_BRANCH: &str = "main";
+const DEFAULT_STANDUP_TEMPLATE: &str = "#{id} {title} ({repo})";
+const DEFAULT_IDLE_BACKOFF_AFTER_SECONDS: u64 = 5 * 60;
+const DEFAULT_IDLE_BACKOFF_MAX_SECONDS: u64 = 30 * 60;
+const DEFAULT_MIN_TERMINAL_WIDTH: u16 = 40;
+const DEFAULT_MIN_TERMINAL_HEIGHT: u16 = 12;
+const LOCAL_REPO_FILE: &str = ".myprs";
+const SHARED_CONFIG_PATH: &str = "/etc/myprs/config.toml";
+/// Lists that a shared config and the user's config add up instead of the
+/// user's replacing the shared one.
+const UNION_KEYS: [&str; 3] = ["repos", "workspaces", "projects"];
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoRef {
     pub workspace: String,
     pub repo: String,
 }
 
+/// Bitbucket slugs are case-insensitive, so `Team/App` and `team/app` are
+/// the same repo. The casing as written is kept for display.
+impl PartialEq for RepoRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.workspace.eq_ignore_ascii_case(&other.workspace)
+            && self.repo.eq_ignore_ascii_case(&other.repo)
+    }
+}
+
+impl Eq for RepoRef {}
+
 impl RepoRef {
     pub fn new(workspace: String, repo: String) -> Self {
         Self { workspace, repo }
@@ -30,6 +67,21 @@ impl RepoRef {
 
         Ok(Self::new(workspace.to_string(), repo.to_string()))
     }
+
+    /// Derives a repo from a git remote URL, accepting SSH (`git@host:w/r.git`,
+    /// `ssh://git@host/w/r.git`) and HTTPS (`https://user@host/w/r.git`) forms.
+    pub fn from_git_remote(url: &str) -> Result<Self> {
+        let url = url.trim();
+        let path = match url.split_once("://") {
+            Some((_, rest)) => rest.split_once('/').map(|(_, path)| path),
+            None => url.split_once(':').map(|(_, path)| path),
+        }
+        .ok_or_else(|| anyhow!("unrecognized git remote url '{url}'"))?;
+
+        let path = path.trim_matches('/');
+        let path = path.strip_suffix(".git").unwrap_or(path);
+        Self::parse(path).with_context(|| format!("unrecognized git remote url '{url}'"))
+    }
 }
 
 impl fmt::Display for RepoRef {
@@ -38,6 +90,215 @@ impl fmt::Display for RepoRef {
     }
 }
 
+/// How pull requests are fetched on refresh: one request per watched repo,
+/// or the user's cross-repo listing filtered down to the watched repos.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchStrategy {
+    #[default]
+    PerRepo,
+    Aggregate,
+}
+
+/// Host the PR list is fetched from. With GitHub only listing the user's
+/// own PRs is supported; commands that need the Bitbucket API say so.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Provider {
+    #[default]
+    Bitbucket,
+    Github,
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Bitbucket => "bitbucket",
+            Self::Github => "github",
+        };
+        write!(f, "{value}")
+    }
+}
+
+impl Provider {
+    /// The host's name as written in messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bitbucket => "Bitbucket",
+            Self::Github => "GitHub",
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bitbucket" => Ok(Self::Bitbucket),
+            "github" => Ok(Self::Github),
+            _ => Err(anyhow!(
+                "invalid provider '{s}'. expected: bitbucket|github"
+            )),
+        }
+    }
+}
+
+/// Identifier preferred when filtering pull requests by author. Some
+/// workspaces only match one of the two, so the other is tried as well when
+/// the preferred one finds nothing.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorIdentifier {
+    #[default]
+    Uuid,
+    AccountId,
+}
+
+/// Vertical spacing of the PR list: `Compact` packs repo groups together,
+/// `Comfortable` leaves a blank line between them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ListSpacing {
+    #[default]
+    Compact,
+    Comfortable,
+}
+
+impl fmt::Display for ListSpacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Compact => "compact",
+            Self::Comfortable => "comfortable",
+        };
+        write!(f, "{value}")
+    }
+}
+
+impl FromStr for ListSpacing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "compact" => Ok(Self::Compact),
+            "comfortable" => Ok(Self::Comfortable),
+            _ => Err(anyhow!(
+                "invalid density '{s}'. expected: compact|comfortable"
+            )),
+        }
+    }
+}
+
+/// What `/search` matches against: titles only, or titles and descriptions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchScope {
+    Title,
+    #[default]
+    All,
+}
+
+impl fmt::Display for SearchScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Title => "title",
+            Self::All => "all",
+        };
+        write!(f, "{value}")
+    }
+}
+
+impl FromStr for SearchScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "title" => Ok(Self::Title),
+            "all" => Ok(Self::All),
+            _ => Err(anyhow!("invalid search scope '{s}'. expected: title|all")),
+        }
+    }
+}
+
+/// Size bucket of a PR by its changed (added plus removed) lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeCategory {
+    Xs,
+    S,
+    M,
+    L,
+    Xl,
+}
+
+impl fmt::Display for SizeCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Xs => "XS",
+            Self::S => "S",
+            Self::M => "M",
+            Self::L => "L",
+            Self::Xl => "XL",
+        };
+        write!(f, "{value}")
+    }
+}
+
+impl FromStr for SizeCategory {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "xs" => Ok(Self::Xs),
+            "s" => Ok(Self::S),
+            "m" => Ok(Self::M),
+            "l" => Ok(Self::L),
+            "xl" => Ok(Self::Xl),
+            _ => Err(anyhow!("invalid size '{s}'. expected: xs|s|m|l|xl")),
+        }
+    }
+}
+
+/// Most changed lines a PR may have to count as each size; anything above
+/// `l` is XL.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SizeThresholds {
+    pub xs: u64,
+    pub s: u64,
+    pub m: u64,
+    pub l: u64,
+}
+
+impl Default for SizeThresholds {
+    fn default() -> Self {
+        Self {
+            xs: 10,
+            s: 50,
+            m: 250,
+            l: 1000,
+        }
+    }
+}
+
+impl SizeThresholds {
+    pub fn category(&self, changed_lines: u64) -> SizeCategory {
+        match changed_lines {
+            lines if lines <= self.xs => SizeCategory::Xs,
+            lines if lines <= self.s => SizeCategory::S,
+            lines if lines <= self.m => SizeCategory::M,
+            lines if lines <= self.l => SizeCategory::L,
+            _ => SizeCategory::Xl,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(self.xs < self.s && self.s < self.m && self.m < self.l) {
+            bail!("size_thresholds must increase from xs to l");
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, clap::ValueEnum, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum PrStatus {
@@ -94,9 +355,92 @@ pub struct Config {
     pub bitbucket_base_url: String,
     pub bitbucket_email: Option<String>,
     pub bitbucket_api_token: Option<String>,
+    pub use_git_credentials: bool,
+    pub provider: Provider,
+    pub github_base_url: String,
+    /// Personal access token for `provider = "github"`; `GITHUB_TOKEN`
+    /// takes precedence.
+    pub github_token: Option<String>,
     pub repos: Vec<RepoRef>,
+    pub workspaces: Vec<String>,
+    /// Watched Bitbucket projects as `workspace/PROJECT_KEY`.
+    pub projects: Vec<String>,
     pub default_status: PrStatus,
     pub auto_refresh_seconds: u64,
+    /// Without a keystroke for this long, the auto-refresh interval doubles
+    /// (again for each further period), up to `idle_backoff_max_seconds`.
+    /// `0` disables the backoff.
+    pub idle_backoff_after_seconds: u64,
+    pub idle_backoff_max_seconds: u64,
+    pub max_results_per_repo: Option<u32>,
+    pub stale_after_days: u32,
+    pub live_search: bool,
+    pub default_search: Option<String>,
+    pub search_scope: SearchScope,
+    /// Branch `/mainline` keeps PRs targeting; `master` always counts too.
+    pub default_branch: String,
+    pub fetch_strategy: FetchStrategy,
+    pub author_identifier: AuthorIdentifier,
+    pub offer_dead_repo_removal: bool,
+    pub browser_command: Option<String>,
+    pub issue_key_pattern: String,
+    /// Issue tracker link with a `{key}` placeholder, e.g.
+    /// `https://jira.example.com/browse/{key}`.
+    pub issue_url_template: Option<String>,
+    /// Line copied by `/standup`, with `{id}`, `{title}`, `{repo}`
+    /// (`workspace/repo`), and `{url}` placeholders.
+    pub standup_template: String,
+    pub size_thresholds: SizeThresholds,
+    pub persist_pins: bool,
+    pub list_spacing: ListSpacing,
+    pub group_separators: bool,
+    pub set_terminal_title: bool,
+    /// Below this size the TUI shows a "terminal too small" notice instead
+    /// of its panels.
+    pub min_terminal_width: u16,
+    pub min_terminal_height: u16,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pinned_prs: Vec<String>,
+    pub ca_cert_path: Option<String>,
+    pub danger_accept_invalid_certs: bool,
+    pub read_only: bool,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, Vec<String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub repo_colors: BTreeMap<String, String>,
+    /// Version of the last run, used to announce new features once.
+    pub last_seen_version: Option<String>,
+    #[serde(skip)]
+    session_repos: Vec<RepoRef>,
+    /// Set by `--only`: the single repo watched this session, in place of
+    /// the configured repos, workspaces, and projects.
+    #[serde(skip)]
+    only_repo: Option<RepoRef>,
+    #[serde(skip)]
+    api_capture: Option<ApiCapture>,
+    #[serde(skip)]
+    session_status: Option<PrStatus>,
+    #[serde(skip)]
+    session_provider: Option<Provider>,
+    #[serde(skip)]
+    session_read_only: bool,
+    /// Credentials from the CLI, env, and git credential helper for this
+    /// session; the config fields form the lowest-precedence layer.
+    #[serde(skip)]
+    credential_layers: Vec<CredentialLayer>,
+    #[serde(skip)]
+    env_templates: Vec<(&'static str, String, String)>,
+    /// The shared config this one was merged over, so `save` writes back
+    /// only the user's own settings.
+    #[serde(skip)]
+    shared: Option<toml::Table>,
+    /// Set when `bitbucket_api_token` is the keyring marker, so `save`
+    /// writes the marker back and keeps the keyring entry up to date.
+    #[serde(skip)]
+    token_in_keyring: bool,
+    /// The token as last read from or written to the keyring.
+    #[serde(skip)]
+    keyring_token: Option<String>,
 }
 
 impl Default for Config {
@@ -105,27 +449,282 @@ impl Default for Config {
             bitbucket_base_url: DEFAULT_BITBUCKET_BASE_URL.to_string(),
             bitbucket_email: None,
             bitbucket_api_token: None,
+            use_git_credentials: false,
+            provider: Provider::Bitbucket,
+            github_base_url: DEFAULT_GITHUB_BASE_URL.to_string(),
+            github_token: None,
             repos: Vec::new(),
+            workspaces: Vec::new(),
+            projects: Vec::new(),
             default_status: PrStatus::Open,
             auto_refresh_seconds: DEFAULT_AUTO_REFRESH_SECONDS,
+            idle_backoff_after_seconds: DEFAULT_IDLE_BACKOFF_AFTER_SECONDS,
+            idle_backoff_max_seconds: DEFAULT_IDLE_BACKOFF_MAX_SECONDS,
+            max_results_per_repo: None,
+            stale_after_days: DEFAULT_STALE_AFTER_DAYS,
+            live_search: false,
+            default_search: None,
+            search_scope: SearchScope::All,
+            default_branch: DEFAULT_BRANCH.to_string(),
+            fetch_strategy: FetchStrategy::PerRepo,
+            author_identifier: AuthorIdentifier::Uuid,
+            offer_dead_repo_removal: true,
+            browser_command: None,
+            issue_key_pattern: issues::DEFAULT_ISSUE_KEY_PATTERN.to_string(),
+            issue_url_template: None,
+            standup_template: DEFAULT_STANDUP_TEMPLATE.to_string(),
+            size_thresholds: SizeThresholds::default(),
+            persist_pins: false,
+            list_spacing: ListSpacing::Compact,
+            group_separators: false,
+            set_terminal_title: false,
+            min_terminal_width: DEFAULT_MIN_TERMINAL_WIDTH,
+            min_terminal_height: DEFAULT_MIN_TERMINAL_HEIGHT,
+            pinned_prs: Vec::new(),
+            ca_cert_path: None,
+            danger_accept_invalid_certs: false,
+            read_only: false,
+            keys: BTreeMap::new(),
+            repo_colors: BTreeMap::new(),
+            last_seen_version: None,
+            session_repos: Vec::new(),
+            only_repo: None,
+            api_capture: None,
+            session_status: None,
+            session_provider: None,
+            session_read_only: false,
+            credential_layers: Vec::new(),
+            env_templates: Vec::new(),
+            shared: None,
+            token_in_keyring: false,
+            keyring_token: None,
         }
     }
 }
 
 impl Config {
+    /// Loads the user's config, merged over the shared config from
+    /// `MYPRS_SHARED_CONFIG` or `/etc/myprs/config.toml` when there is one.
     pub fn load() -> Result<Self> {
         let path = Self::config_path()?;
-        if !path.exists() {
+        let shared = match Self::shared_config_path() {
+            Some(shared_path) => Some(read_table(&shared_path)?),
+            None => None,
+        };
+        if !path.exists() && shared.is_none() {
             return Ok(Self::default());
         }
 
-        let raw = fs::read_to_string(&path)
-            .with_context(|| format!("failed to read config at {}", path.display()))?;
-        let parsed = toml::from_str(&raw)
+        let user = if path.exists() {
+            read_table(&path)?
+        } else {
+            toml::Table::new()
+        };
+        let merged = match &shared {
+            Some(shared) => Self::merge(shared.clone(), user),
+            None => user,
+        };
+        let mut parsed: Self = toml::Value::Table(merged)
+            .try_into()
             .with_context(|| format!("failed to parse config at {}", path.display()))?;
+        if shared.is_some() {
+            for repo in std::mem::take(&mut parsed.repos) {
+                parsed.add_repo(repo);
+            }
+        }
+        parsed.shared = shared;
+        parsed
+            .expand_env_vars(|name| std::env::var(name).ok())
+            .with_context(|| format!("failed to expand config at {}", path.display()))?;
+        parsed.resolve_keyring_token(keyring::lookup);
+        Keymap::resolve(&parsed.keys)
+            .with_context(|| format!("invalid [keys] in config at {}", path.display()))?;
+        issues::compile(&parsed.issue_key_pattern)
+            .with_context(|| format!("invalid config at {}", path.display()))?;
+        parsed
+            .size_thresholds
+            .validate()
+            .with_context(|| format!("invalid config at {}", path.display()))?;
         Ok(parsed)
     }
 
+    /// Resolves `${VAR}` references in the string settings that commonly hold
+    /// secrets. The original templates are remembered so `save` writes them
+    /// back instead of the resolved values.
+    fn expand_env_vars(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<()> {
+        let mut expand = |field: &'static str, value: &mut Option<String>| -> Result<()> {
+            let Some(raw) = value.as_deref() else {
+                return Ok(());
+            };
+            let expanded = expand_env_refs(raw, &lookup).with_context(|| format!("in {field}"))?;
+            if expanded != raw {
+                self.env_templates
+                    .push((field, raw.to_string(), expanded.clone()));
+                *value = Some(expanded);
+            }
+            Ok(())
+        };
+
+        expand("bitbucket_email", &mut self.bitbucket_email)?;
+        expand("bitbucket_api_token", &mut self.bitbucket_api_token)?;
+        expand("github_token", &mut self.github_token)?;
+        let mut base_url = Some(self.bitbucket_base_url.clone());
+        expand("bitbucket_base_url", &mut base_url)?;
+        self.bitbucket_base_url = base_url.unwrap_or_default();
+        Ok(())
+    }
+
+    /// Replaces the keyring marker in `bitbucket_api_token` with the token
+    /// stored for `bitbucket_email`. When the keyring cannot provide it, the
+    /// token is left unset, as if it had never been configured.
+    fn resolve_keyring_token(&mut self, lookup: impl Fn(&str) -> Result<String>) {
+        if self.bitbucket_api_token.as_deref() != Some(keyring::MARKER) {
+            return;
+        }
+        self.token_in_keyring = true;
+        self.bitbucket_api_token = None;
+
+        let Some(email) = self.bitbucket_email.as_deref() else {
+            debug_log::record("WARN", "keyring token needs bitbucket_email to look up");
+            return;
+        };
+        match lookup(email) {
+            Ok(token) => {
+                self.bitbucket_api_token = Some(token.clone());
+                self.keyring_token = Some(token);
+            }
+            Err(err) => debug_log::record(
+                "WARN",
+                &format!("keyring token unavailable, continuing without one: {err:#}"),
+            ),
+        }
+    }
+
+    /// Moves the API token in use, wherever it came from (`--api-token`,
+    /// the environment, the git credential helper, or a plaintext
+    /// `bitbucket_api_token`), into the OS keyring; the caller saves the
+    /// config, which then only holds the marker. Call it after
+    /// `apply_env_and_cli` so overrides are the token that gets stored.
+    pub fn move_token_to_keyring(&mut self) -> Result<()> {
+        self.move_token_to(keyring::store)
+    }
+
+    fn move_token_to(&mut self, store: impl FnOnce(&str, &str) -> Result<()>) -> Result<()> {
+        let resolved = self.resolved_credentials();
+        let (Some((email, _)), Some((token, source))) = (resolved.email, resolved.api_token) else {
+            bail!("an email and an API token are needed to store the token in the keyring")
+        };
+        let (email, token) = (email.to_string(), token.to_string());
+        if source == CredentialSource::Config {
+            if self.token_in_keyring {
+                bail!("the API token is already stored in the keyring")
+            }
+            if self
+                .env_templates
+                .iter()
+                .any(|(field, _, _)| *field == "bitbucket_api_token")
+            {
+                bail!("bitbucket_api_token references an environment variable; nothing to move")
+            }
+        }
+        // The keyring entry is looked up by the configured email on startup.
+        match self.bitbucket_email.as_deref() {
+            Some(configured) if configured != email => bail!(
+                "the token is for {email} but config.toml has bitbucket_email = \"{configured}\"; use the same email"
+            ),
+            Some(_) => {}
+            None => self.bitbucket_email = Some(email.clone()),
+        }
+        store(&email, &token)?;
+        self.bitbucket_api_token = Some(token.clone());
+        self.keyring_token = Some(token);
+        self.token_in_keyring = true;
+        Ok(())
+    }
+
+    /// Copy of the config with expanded values swapped back to their `${VAR}`
+    /// templates, unless the value was changed since loading, and a keyring
+    /// token swapped back to the marker.
+    fn with_env_templates(&self) -> Self {
+        let mut config = self.clone();
+        for (field, raw, expanded) in &self.env_templates {
+            let value = match *field {
+                "bitbucket_email" => config.bitbucket_email.as_mut(),
+                "bitbucket_api_token" => config.bitbucket_api_token.as_mut(),
+                "github_token" => config.github_token.as_mut(),
+                "bitbucket_base_url" => Some(&mut config.bitbucket_base_url),
+                _ => None,
+            };
+            if let Some(value) = value
+                && value == expanded
+            {
+                *value = raw.clone();
+            }
+        }
+        if self.token_in_keyring {
+            config.bitbucket_api_token = Some(keyring::MARKER.to_string());
+        }
+        config
+    }
+
+    /// Layers `overlay` over `base`, key by key: the overlay's value wins,
+    /// tables such as `[keys]` merge entry by entry, and the `repos`,
+    /// `workspaces`, and `projects` lists are unioned, base entries first.
+    pub fn merge(mut base: toml::Table, overlay: toml::Table) -> toml::Table {
+        for (key, value) in overlay {
+            let merged = match (base.remove(&key), value) {
+                (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                    toml::Value::Table(Self::merge(base, overlay))
+                }
+                (Some(toml::Value::Array(mut base)), toml::Value::Array(overlay))
+                    if UNION_KEYS.contains(&key.as_str()) =>
+                {
+                    for entry in overlay {
+                        if !base.contains(&entry) {
+                            base.push(entry);
+                        }
+                    }
+                    toml::Value::Array(base)
+                }
+                (_, value) => value,
+            };
+            base.insert(key, merged);
+        }
+        base
+    }
+
+    /// Drops what the user's config inherits from `shared` unchanged: equal
+    /// values, and shared entries of the unioned lists.
+    fn strip_shared(table: &mut toml::Table, shared: &toml::Table) {
+        for (key, shared_value) in shared {
+            let Some(value) = table.get_mut(key) else {
+                continue;
+            };
+            match (value, shared_value) {
+                (toml::Value::Table(table), toml::Value::Table(shared)) => {
+                    Self::strip_shared(table, shared);
+                }
+                (toml::Value::Array(entries), toml::Value::Array(shared))
+                    if UNION_KEYS.contains(&key.as_str()) =>
+                {
+                    entries.retain(|entry| !shared.contains(entry));
+                }
+                (value, shared_value) => {
+                    if value == shared_value {
+                        table.remove(key);
+                    }
+                }
+            }
+        }
+    }
+
+    fn shared_config_path() -> Option<PathBuf> {
+        match read_env("MYPRS_SHARED_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => Some(PathBuf::from(SHARED_CONFIG_PATH)).filter(|path| path.exists()),
+        }
+    }
+
     pub fn save(&self) -> Result<()> {
         let path = Self::config_path()?;
         if let Some(parent) = path.parent() {
@@ -134,12 +733,33 @@ impl Config {
             })?;
         }
 
-        let toml = toml::to_string_pretty(self).context("failed to serialize config")?;
+        // A token replaced this session (e.g. after a 401) goes to the keyring
+        // too rather than into the file.
+        if self.token_in_keyring
+            && let (Some(email), Some(token)) = (&self.bitbucket_email, &self.bitbucket_api_token)
+            && self.keyring_token.as_ref() != Some(token)
+        {
+            keyring::store(email, token)?;
+        }
+
+        let toml = self.to_toml()?;
         fs::write(&path, toml)
             .with_context(|| format!("failed to write config at {}", path.display()))?;
         Ok(())
     }
 
+    /// The config as written to disk: env templates restored and, over a
+    /// shared config, only the user's own settings.
+    fn to_toml(&self) -> Result<String> {
+        let config = self.with_env_templates();
+        let Some(shared) = &self.shared else {
+            return toml::to_string_pretty(&config).context("failed to serialize config");
+        };
+        let mut table = toml::Table::try_from(&config).context("failed to serialize config")?;
+        Self::strip_shared(&mut table, shared);
+        toml::to_string_pretty(&table).context("failed to serialize config")
+    }
+
     pub fn config_path() -> Result<PathBuf> {
         let home = dirs::home_dir().context("cannot determine home directory")?;
         Ok(home.join(".config").join("myprs").join("config.toml"))
@@ -156,6 +776,13 @@ impl Config {
     ) -> Result<()> {
         let mut changed = false;
 
+        self.push_credential_layer(
+            CredentialSource::Env,
+            read_env("BITBUCKET_EMAIL"),
+            read_env("BITBUCKET_API_TOKEN"),
+        );
+        self.push_credential_layer(CredentialSource::Cli, email.clone(), api_token.clone());
+
         if let Some(value) = read_env("BITBUCKET_EMAIL") {
             self.bitbucket_email = Some(value);
             changed = true;
@@ -171,6 +798,10 @@ impl Config {
             changed = true;
         }
 
+        if let Some(value) = read_env("MYPRS_STATUS_ONCE") {
+            self.set_session_status(PrStatus::from_str(&value)?);
+        }
+
         if let Some(value) = read_env("BITBUCKET_BASE_URL") {
             self.bitbucket_base_url = value;
             changed = true;
@@ -208,11 +839,11 @@ impl Config {
             changed |= self.set_status(value);
         }
 
-        if let Some(value) = base_url {
-            if self.bitbucket_base_url != value {
-                self.bitbucket_base_url = value;
-                changed = true;
-            }
+        if let Some(value) = base_url
+            && self.bitbucket_base_url != value
+        {
+            self.bitbucket_base_url = value;
+            changed = true;
         }
 
         if let Some(value) = auto_refresh_seconds {
@@ -230,15 +861,154 @@ impl Config {
         Ok(())
     }
 
+    /// When `use_git_credentials` is set, asks `git credential fill` for the
+    /// Bitbucket host. The result is kept for this session only and never
+    /// saved; on failure the configured credentials are used unchanged.
+    pub fn apply_git_credentials(&mut self) {
+        if !self.use_git_credentials {
+            return;
+        }
+
+        let host = self.git_credential_host();
+        match git_credentials::fill(&host) {
+            Ok(credential) => self.push_credential_layer(
+                CredentialSource::GitCredentials,
+                Some(credential.username),
+                Some(credential.password),
+            ),
+            Err(err) => debug_log::record(
+                "WARN",
+                &format!("git credentials unavailable, using configured credentials: {err:#}"),
+            ),
+        }
+    }
+
+    fn git_credential_host(&self) -> String {
+        let host = reqwest::Url::parse(&self.bitbucket_base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_else(|| "bitbucket.org".to_string());
+        // Cloud serves the API from api.bitbucket.org but git from bitbucket.org.
+        host.strip_prefix("api.")
+            .map(str::to_string)
+            .unwrap_or(host)
+    }
+
+    fn push_credential_layer(
+        &mut self,
+        source: CredentialSource,
+        email: Option<String>,
+        api_token: Option<String>,
+    ) {
+        if email.is_some() || api_token.is_some() {
+            self.credential_layers.push(CredentialLayer {
+                source,
+                email,
+                api_token,
+            });
+        }
+    }
+
+    /// Email and token by precedence: CLI > env > git credential helper >
+    /// config, each with the source it came from.
+    pub fn resolved_credentials(&self) -> ResolvedCredentials<'_> {
+        credentials::resolve(
+            self.credential_layers
+                .iter()
+                .map(CredentialLayer::view)
+                .chain([(
+                    CredentialSource::Config,
+                    self.bitbucket_email.as_deref(),
+                    self.bitbucket_api_token.as_deref(),
+                )]),
+        )
+    }
+
     pub fn credentials(&self) -> Option<(&str, &str)> {
-        match (&self.bitbucket_email, &self.bitbucket_api_token) {
-            (Some(email), Some(token)) => Some((email.as_str(), token.as_str())),
-            _ => None,
+        self.resolved_credentials().pair()
+    }
+
+    /// Replaces the API token, e.g. after a mid-session 401, where the one
+    /// in use came from. Returns that source when the new token is only
+    /// kept for this session (a CLI, env, or git credential token, or a
+    /// config token expanded from an env template), and `None` when `save`
+    /// persists it to the config or the keyring.
+    pub fn set_api_token(&mut self, token: String) -> Option<CredentialSource> {
+        let source = self
+            .resolved_credentials()
+            .api_token
+            .map_or(CredentialSource::Config, |(_, source)| source);
+        let from_template = self.env_templates.iter().any(|(field, _, expanded)| {
+            *field == "bitbucket_api_token"
+                && self.bitbucket_api_token.as_deref() == Some(expanded.as_str())
+        });
+        match source {
+            CredentialSource::Config if from_template => {
+                self.push_credential_layer(CredentialSource::Env, None, Some(token));
+                Some(CredentialSource::Env)
+            }
+            CredentialSource::Config => {
+                self.bitbucket_api_token = Some(token);
+                None
+            }
+            source => {
+                for layer in &mut self.credential_layers {
+                    if layer.source == source {
+                        layer.api_token = Some(token.clone());
+                    }
+                }
+                Some(source)
+            }
+        }
+    }
+
+    pub fn tls_options(&self) -> TlsOptions {
+        TlsOptions {
+            ca_cert_path: self.ca_cert_path.clone(),
+            accept_invalid_certs: self.danger_accept_invalid_certs,
+        }
+    }
+
+    pub fn repos(&self) -> Vec<RepoRef> {
+        if let Some(repo) = &self.only_repo {
+            return vec![repo.clone()];
+        }
+        let mut repos = self.repos.clone();
+        for repo in &self.session_repos {
+            if !repos.contains(repo) {
+                repos.push(repo.clone());
+            }
+        }
+        repos
+    }
+
+    /// Adds a repo for this session only; it is never written by `save`.
+    pub fn add_session_repo(&mut self, repo_ref: RepoRef) -> bool {
+        if self.repos.contains(&repo_ref) || self.session_repos.contains(&repo_ref) {
+            return false;
         }
+        self.session_repos.push(repo_ref);
+        true
+    }
+
+    /// Watches only `repo_ref` for this session, leaving the saved repo
+    /// list, workspaces, and projects untouched.
+    pub fn set_only_repo(&mut self, repo_ref: RepoRef) {
+        self.only_repo = Some(repo_ref);
     }
 
-    pub fn repos(&self) -> &[RepoRef] {
-        &self.repos
+    pub fn only_repo(&self) -> Option<&RepoRef> {
+        self.only_repo.as_ref()
+    }
+
+    /// Records API responses to, or replays them from, a directory for this
+    /// session (`--record` / `--replay`).
+    pub fn set_api_capture(&mut self, capture: ApiCapture) {
+        self.api_capture = Some(capture);
+    }
+
+    pub fn api_capture(&self) -> Option<&ApiCapture> {
+        self.api_capture.as_ref()
     }
 
     pub fn add_repo(&mut self, repo_ref: RepoRef) -> bool {
@@ -255,8 +1025,123 @@ impl Config {
         before != self.repos.len()
     }
 
-    pub fn status(&self) -> PrStatus {
-        self.default_status
+    pub fn workspaces(&self) -> &[String] {
+        if self.only_repo.is_some() {
+            return &[];
+        }
+        &self.workspaces
+    }
+
+    /// Watches every repo in `workspace`; the repo list is expanded at refresh
+    /// time and never persisted, so new repos are picked up automatically.
+    pub fn add_workspace(&mut self, workspace: &str) -> Result<bool> {
+        let workspace = workspace.trim();
+        if workspace.is_empty() || workspace.contains('/') {
+            bail!("workspace must be a single workspace slug")
+        }
+        if self.workspaces.iter().any(|known| known == workspace) {
+            return Ok(false);
+        }
+        self.workspaces.push(workspace.to_string());
+        Ok(true)
+    }
+
+    pub fn remove_workspace(&mut self, workspace: &str) -> bool {
+        let before = self.workspaces.len();
+        self.workspaces.retain(|known| known != workspace.trim());
+        before != self.workspaces.len()
+    }
+
+    pub fn projects(&self) -> &[String] {
+        if self.only_repo.is_some() {
+            return &[];
+        }
+        &self.projects
+    }
+
+    /// Watches every repo in a project, given as `workspace/PROJECT_KEY`.
+    /// Like workspaces, projects are expanded to repos at refresh time.
+    pub fn add_project(&mut self, project: &str) -> Result<bool> {
+        let project = project.trim();
+        let valid = project.split_once('/').is_some_and(|(workspace, key)| {
+            !workspace.is_empty() && !key.is_empty() && !key.contains('/')
+        });
+        if !valid {
+            bail!("project must be in the form workspace/PROJECT_KEY")
+        }
+        if self.projects.iter().any(|known| known == project) {
+            return Ok(false);
+        }
+        self.projects.push(project.to_string());
+        Ok(true)
+    }
+
+    pub fn remove_project(&mut self, project: &str) -> bool {
+        let before = self.projects.len();
+        self.projects.retain(|known| known != project.trim());
+        before != self.projects.len()
+    }
+
+    /// Whether any repo, workspace, or project is configured to query.
+    /// GitHub needs none, since its search covers every repo.
+    pub fn has_repo_sources(&self) -> bool {
+        self.provider() == Provider::Github
+            || !self.repos().is_empty()
+            || !self.workspaces().is_empty()
+            || !self.projects().is_empty()
+    }
+
+    /// Sorts configured repos alphabetically and drops duplicates, returning
+    /// whether the stored list changed.
+    pub fn sort_repos(&mut self) -> bool {
+        let before = self.repos.clone();
+        self.repos.sort_by_cached_key(|repo| {
+            (
+                repo.workspace.to_ascii_lowercase(),
+                repo.repo.to_ascii_lowercase(),
+            )
+        });
+        self.repos.dedup();
+        before != self.repos
+    }
+
+    /// Status filter to start with: a one-off session status when set,
+    /// otherwise the persisted default.
+    pub fn launch_status(&self) -> PrStatus {
+        self.session_status.unwrap_or(self.default_status)
+    }
+
+    /// Overrides the launch status for this session without touching the
+    /// persisted `default_status`.
+    pub fn set_session_status(&mut self, status: PrStatus) {
+        self.session_status = Some(status);
+    }
+
+    /// Provider to fetch from: `--provider` when given, otherwise the
+    /// configured one.
+    pub fn provider(&self) -> Provider {
+        self.session_provider.unwrap_or(self.provider)
+    }
+
+    /// Overrides the provider for this session without touching the
+    /// persisted `provider`.
+    pub fn set_session_provider(&mut self, provider: Provider) {
+        self.session_provider = Some(provider);
+    }
+
+    pub fn github_token(&self) -> Option<String> {
+        read_env("GITHUB_TOKEN").or_else(|| self.github_token.clone())
+    }
+
+    /// True when writes to Bitbucket are disabled, either by `read_only` in
+    /// the config or by `--read-only` for this session.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || self.session_read_only
+    }
+
+    /// Disables writes for this session without persisting `read_only`.
+    pub fn set_session_read_only(&mut self) {
+        self.session_read_only = true;
     }
 
     pub fn set_status(&mut self, status: PrStatus) -> bool {
@@ -281,6 +1166,87 @@ impl Config {
         self.auto_refresh_seconds = seconds;
         Ok(true)
     }
+
+    pub fn max_results_per_repo(&self) -> Option<u32> {
+        self.max_results_per_repo
+    }
+
+    pub fn set_max_results_per_repo(&mut self, limit: Option<u32>) -> Result<bool> {
+        if limit == Some(0) {
+            bail!("max results per repo must be >= 1")
+        }
+        if self.max_results_per_repo == limit {
+            return Ok(false);
+        }
+        self.max_results_per_repo = limit;
+        Ok(true)
+    }
+}
+
+/// Detects repos for the checkout in `dir`: a `.myprs` file listing one
+/// `workspace/repo` per line wins, otherwise the `origin` git remote is used.
+pub fn detect_local_repos(dir: &Path) -> Result<Vec<RepoRef>> {
+    let local_file = dir.join(LOCAL_REPO_FILE);
+    if local_file.exists() {
+        let raw = fs::read_to_string(&local_file)
+            .with_context(|| format!("failed to read {}", local_file.display()))?;
+        return parse_local_repo_file(&raw)
+            .with_context(|| format!("failed to parse {}", local_file.display()));
+    }
+
+    let output = Command::new("git")
+        .args(["config", "--get", "remote.origin.url"])
+        .current_dir(dir)
+        .output()
+        .context("failed to run git to detect the local repo")?;
+    if !output.status.success() {
+        bail!(
+            "no {LOCAL_REPO_FILE} file or git remote 'origin' found in {}",
+            dir.display()
+        )
+    }
+
+    let url = String::from_utf8_lossy(&output.stdout);
+    Ok(vec![RepoRef::from_git_remote(&url)?])
+}
+
+fn parse_local_repo_file(raw: &str) -> Result<Vec<RepoRef>> {
+    raw.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(RepoRef::parse)
+        .collect()
+}
+
+/// Replaces each `${NAME}` in `value` using `lookup`; text without `${` is
+/// returned unchanged.
+fn expand_env_refs(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow!("unterminated '${{' in config value"))?;
+        let name = &after[..end];
+        if name.is_empty() {
+            bail!("empty '${{}}' reference in config value")
+        }
+        let resolved = lookup(name).ok_or_else(|| {
+            anyhow!("environment variable {name} referenced in config is not set")
+        })?;
+        expanded.push_str(&resolved);
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config at {}", path.display()))?;
+    toml::from_str(&raw).with_context(|| format!("failed to parse config at {}", path.display()))
 }
 
 fn read_env(key: &str) -> Option<String> {
@@ -311,7 +1277,12 @@ fn parse_auto_refresh_seconds(key: &str, value: &str) -> Result<u64> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Config, PrStatus, RepoRef};
+    use super::{
+        Config, PrStatus, RepoRef, SizeCategory, SizeThresholds, expand_env_refs,
+        parse_local_repo_file,
+    };
+    use crate::credentials::CredentialSource;
+    use anyhow::anyhow;
 
     #[test]
     fn parses_repo_ref() {
@@ -327,6 +1298,312 @@ mod tests {
         assert!(RepoRef::parse("/").is_err());
     }
 
+    #[test]
+    fn parses_git_remote_urls() {
+        let expected = RepoRef::new("team".to_string(), "project".to_string());
+        for url in [
+            "git@bitbucket.org:team/project.git",
+            "ssh://git@bitbucket.org/team/project.git",
+            "https://someone@bitbucket.org/team/project.git",
+            "https://bitbucket.org/team/project/",
+        ] {
+            assert_eq!(
+                RepoRef::from_git_remote(url).expect("remote parse"),
+                expected,
+                "{url}"
+            );
+        }
+        assert!(RepoRef::from_git_remote("not a remote").is_err());
+    }
+
+    #[test]
+    fn parses_local_repo_file() {
+        let repos = parse_local_repo_file("# local repos\nteam/app\n\nteam/lib\n")
+            .expect("local file parse");
+        assert_eq!(
+            repos,
+            vec![
+                RepoRef::new("team".to_string(), "app".to_string()),
+                RepoRef::new("team".to_string(), "lib".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn session_repos_are_not_serialized() {
+        let mut config = Config::default();
+        config.add_session_repo(RepoRef::new("team".to_string(), "app".to_string()));
+        assert_eq!(config.repos().len(), 1);
+
+        let raw = toml::to_string_pretty(&config).expect("serialize config");
+        assert!(!raw.contains("team"));
+    }
+
+    #[test]
+    fn only_repo_replaces_configured_sources_for_the_session() {
+        let mut config = Config {
+            workspaces: vec!["team".to_string()],
+            ..Config::default()
+        };
+        config.add_repo(RepoRef::parse("team/api").expect("repo parse"));
+        config.set_only_repo(RepoRef::parse("team/app").expect("repo parse"));
+
+        assert_eq!(
+            config.repos(),
+            vec![RepoRef::parse("team/app").expect("repo")]
+        );
+        assert!(config.workspaces().is_empty());
+        let raw = toml::to_string_pretty(&config).expect("serialize config");
+        assert!(raw.contains("\"api\""));
+        assert!(!raw.contains("\"app\""));
+    }
+
+    #[test]
+    fn treats_repos_differing_only_by_case_as_the_same() {
+        let mut config = Config::default();
+        assert!(config.add_repo(RepoRef::parse("Team/App").expect("repo parse")));
+        assert!(!config.add_repo(RepoRef::parse("team/app").expect("repo parse")));
+        assert_eq!(config.repos()[0].to_string(), "Team/App");
+
+        assert!(config.remove_repo(&RepoRef::parse("TEAM/APP").expect("repo parse")));
+        assert!(config.repos().is_empty());
+
+        config
+            .repos
+            .push(RepoRef::parse("team/web").expect("repo parse"));
+        config
+            .repos
+            .push(RepoRef::parse("Core/API").expect("repo parse"));
+        config
+            .repos
+            .push(RepoRef::parse("Team/Web").expect("repo parse"));
+        assert!(config.sort_repos());
+        let sorted = config
+            .repos()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, vec!["Core/API", "team/web"]);
+    }
+
+    #[test]
+    fn sorts_and_dedups_repos() {
+        let mut config = Config::default();
+        for repo in ["team/web", "core/api", "team/app", "core/api"] {
+            config.repos.push(RepoRef::parse(repo).expect("repo parse"));
+        }
+
+        assert!(config.sort_repos());
+        let sorted = config
+            .repos()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(sorted, vec!["core/api", "team/app", "team/web"]);
+        assert!(!config.sort_repos());
+    }
+
+    #[test]
+    fn round_trips_repo_colors_table() {
+        let mut config = Config::default();
+        config
+            .repo_colors
+            .insert("team/app".to_string(), "cyan".to_string());
+        config.add_repo(RepoRef::new("team".to_string(), "app".to_string()));
+
+        let raw = toml::to_string_pretty(&config).expect("serialize config");
+        let parsed: Config = toml::from_str(&raw).expect("parse config");
+        assert_eq!(
+            parsed.repo_colors.get("team/app").map(String::as_str),
+            Some("cyan")
+        );
+    }
+
+    #[test]
+    fn session_credentials_override_config_until_token_is_replaced() {
+        let mut config = Config {
+            bitbucket_email: Some("me@example.com".to_string()),
+            bitbucket_api_token: Some("from-config".to_string()),
+            ..Config::default()
+        };
+        config.push_credential_layer(CredentialSource::Env, None, Some("from-env".to_string()));
+
+        let resolved = config.resolved_credentials();
+        assert_eq!(
+            resolved.email,
+            Some(("me@example.com", CredentialSource::Config))
+        );
+        assert_eq!(
+            resolved.api_token,
+            Some(("from-env", CredentialSource::Env))
+        );
+
+        assert_eq!(
+            config.set_api_token("rotated".to_string()),
+            Some(CredentialSource::Env)
+        );
+        assert_eq!(config.credentials(), Some(("me@example.com", "rotated")));
+        // The session-only token never reaches the file.
+        assert_eq!(config.bitbucket_api_token.as_deref(), Some("from-config"));
+
+        let mut config = Config {
+            bitbucket_email: Some("me@example.com".to_string()),
+            bitbucket_api_token: Some("from-config".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(config.set_api_token("rotated".to_string()), None);
+        assert_eq!(config.bitbucket_api_token.as_deref(), Some("rotated"));
+    }
+
+    #[test]
+    fn session_status_does_not_change_persisted_default() {
+        let mut config = Config::default();
+        config.set_session_status(PrStatus::Merged);
+
+        assert_eq!(config.launch_status(), PrStatus::Merged);
+        assert_eq!(config.default_status, PrStatus::Open);
+
+        let raw = toml::to_string_pretty(&config).expect("serialize config");
+        let parsed: Config = toml::from_str(&raw).expect("parse config");
+        assert_eq!(parsed.default_status, PrStatus::Open);
+        assert_eq!(parsed.launch_status(), PrStatus::Open);
+    }
+
+    #[test]
+    fn adds_and_removes_projects() {
+        let mut config = Config::default();
+        assert!(!config.has_repo_sources());
+        assert!(config.add_project("team/CORE").expect("add project"));
+        assert!(
+            !config
+                .add_project(" team/CORE ")
+                .expect("duplicate project")
+        );
+        assert!(config.add_project("team").is_err());
+        assert!(config.add_project("team/CORE/x").is_err());
+        assert!(config.has_repo_sources());
+        assert!(config.remove_project("team/CORE"));
+        assert!(config.projects().is_empty());
+    }
+
+    #[test]
+    fn adds_and_removes_workspaces() {
+        let mut config = Config::default();
+        assert!(config.add_workspace("team").expect("add workspace"));
+        assert!(!config.add_workspace(" team ").expect("duplicate workspace"));
+        assert!(config.add_workspace("team/app").is_err());
+        assert_eq!(config.workspaces(), ["team".to_string()]);
+        assert!(config.remove_workspace("team"));
+        assert!(config.workspaces().is_empty());
+    }
+
+    #[test]
+    fn derives_git_credential_host_from_base_url() {
+        let mut config = Config::default();
+        assert_eq!(config.git_credential_host(), "bitbucket.org");
+        config.bitbucket_base_url = "https://git.example.com/rest/api".to_string();
+        assert_eq!(config.git_credential_host(), "git.example.com");
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "MYPRS_TEST_TOKEN" => Some("s3cr3t".to_string()),
+            "MYPRS_TEST_USER" => Some("me".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_env_references() {
+        assert_eq!(
+            expand_env_refs("${MYPRS_TEST_TOKEN}", lookup).expect("expand"),
+            "s3cr3t"
+        );
+        assert_eq!(
+            expand_env_refs("${MYPRS_TEST_USER}@example.com", lookup).expect("expand"),
+            "me@example.com"
+        );
+        assert!(expand_env_refs("${MYPRS_TEST_MISSING}", lookup).is_err());
+        assert!(expand_env_refs("${MYPRS_TEST_TOKEN", lookup).is_err());
+    }
+
+    #[test]
+    fn leaves_literal_values_untouched() {
+        assert_eq!(
+            expand_env_refs("plain-token$1", lookup).expect("expand"),
+            "plain-token$1"
+        );
+    }
+
+    #[test]
+    fn keeps_keyring_tokens_out_of_the_saved_config() {
+        let mut config = Config {
+            bitbucket_api_token: Some("s3cr3t".to_string()),
+            bitbucket_email: Some("me@example.com".to_string()),
+            ..Config::default()
+        };
+        let mut stored = None;
+        config
+            .move_token_to(|email, token| {
+                stored = Some((email.to_string(), token.to_string()));
+                Ok(())
+            })
+            .expect("move token");
+        assert_eq!(
+            stored,
+            Some(("me@example.com".to_string(), "s3cr3t".to_string()))
+        );
+        let raw = config.to_toml().expect("serialize");
+        assert!(raw.contains(r#"bitbucket_api_token = "keyring""#), "{raw}");
+        assert!(!raw.contains("s3cr3t"), "{raw}");
+
+        let mut loaded: Config = toml::from_str(&raw).expect("parse");
+        loaded.resolve_keyring_token(|email| {
+            assert_eq!(email, "me@example.com");
+            Ok("s3cr3t".to_string())
+        });
+        assert_eq!(loaded.credentials(), Some(("me@example.com", "s3cr3t")));
+
+        let mut overridden = Config {
+            bitbucket_email: Some("me@example.com".to_string()),
+            ..Config::default()
+        };
+        overridden.push_credential_layer(CredentialSource::Env, None, Some("from-env".to_string()));
+        overridden
+            .move_token_to(|_, token| {
+                assert_eq!(token, "from-env");
+                Ok(())
+            })
+            .expect("move env token");
+        let raw_overridden = overridden.to_toml().expect("serialize");
+        assert!(!raw_overridden.contains("from-env"), "{raw_overridden}");
+
+        let mut unavailable: Config = toml::from_str(&raw).expect("parse");
+        unavailable.resolve_keyring_token(|_| Err(anyhow!("secret-tool not found")));
+        assert_eq!(unavailable.credentials(), None);
+        assert!(
+            unavailable
+                .to_toml()
+                .expect("serialize")
+                .contains("keyring")
+        );
+    }
+
+    #[test]
+    fn saves_env_templates_instead_of_secrets() {
+        let mut config = Config {
+            bitbucket_api_token: Some("${MYPRS_TEST_TOKEN}".to_string()),
+            bitbucket_email: Some("me@example.com".to_string()),
+            ..Config::default()
+        };
+        config.expand_env_vars(lookup).expect("expand config");
+        assert_eq!(config.credentials(), Some(("me@example.com", "s3cr3t")));
+
+        let raw = toml::to_string_pretty(&config.with_env_templates()).expect("serialize");
+        assert!(raw.contains("${MYPRS_TEST_TOKEN}"), "{raw}");
+        assert!(!raw.contains("s3cr3t"), "{raw}");
+    }
+
     #[test]
     fn parses_status_values() {
         assert_eq!(
@@ -355,4 +1632,99 @@ mod tests {
         let mut config = Config::default();
         assert!(config.set_auto_refresh_seconds(0).is_err());
     }
+
+    #[test]
+    fn sets_and_clears_max_results_per_repo() {
+        let mut config = Config::default();
+        assert!(config.set_max_results_per_repo(Some(0)).is_err());
+        assert!(
+            config
+                .set_max_results_per_repo(Some(20))
+                .expect("set limit")
+        );
+        assert_eq!(config.max_results_per_repo(), Some(20));
+        assert!(config.set_max_results_per_repo(None).expect("clear limit"));
+        assert_eq!(config.max_results_per_repo(), None);
+    }
+
+    #[test]
+    fn buckets_changed_lines_into_size_categories() {
+        let thresholds = SizeThresholds::default();
+        assert_eq!(thresholds.category(0), SizeCategory::Xs);
+        assert_eq!(thresholds.category(10), SizeCategory::Xs);
+        assert_eq!(thresholds.category(11), SizeCategory::S);
+        assert_eq!(thresholds.category(250), SizeCategory::M);
+        assert_eq!(thresholds.category(1001), SizeCategory::Xl);
+        assert_eq!(
+            "XL".parse::<SizeCategory>().expect("size"),
+            SizeCategory::Xl
+        );
+        assert!("huge".parse::<SizeCategory>().is_err());
+
+        let config: Config = toml::from_str("[size_thresholds]\nxs = 50").expect("config");
+        assert!(config.size_thresholds.validate().is_err());
+    }
+
+    #[test]
+    fn merges_user_config_over_shared_config() {
+        let shared: toml::Table = toml::from_str(
+            r#"
+            auto_refresh_seconds = 60
+            workspaces = ["team"]
+            live_search = true
+
+            [[repos]]
+            workspace = "team"
+            repo = "api"
+
+            [repo_colors]
+            "team/api" = "cyan"
+            "team/web" = "green"
+            "#,
+        )
+        .expect("shared");
+        let user: toml::Table = toml::from_str(
+            r#"
+            auto_refresh_seconds = 30
+            workspaces = ["team", "me"]
+
+            [[repos]]
+            workspace = "team"
+            repo = "web"
+
+            [repo_colors]
+            "team/web" = "red"
+            "#,
+        )
+        .expect("user");
+
+        let merged = Config::merge(shared.clone(), user);
+        let mut config: Config = toml::Value::Table(merged).try_into().expect("config");
+        assert_eq!(config.auto_refresh_seconds, 30);
+        assert!(config.live_search);
+        assert_eq!(config.workspaces, vec!["team", "me"]);
+        assert_eq!(
+            config
+                .repos
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["team/api", "team/web"]
+        );
+        assert_eq!(config.repo_colors["team/api"], "cyan");
+        assert_eq!(config.repo_colors["team/web"], "red");
+
+        config.shared = Some(shared);
+        config.stale_after_days = 30;
+        let saved: toml::Table = toml::from_str(&config.to_toml().expect("toml")).expect("saved");
+        assert_eq!(saved["auto_refresh_seconds"].as_integer(), Some(30));
+        assert_eq!(saved["stale_after_days"].as_integer(), Some(30));
+        assert!(!saved.contains_key("live_search"));
+        assert_eq!(saved["workspaces"].as_array().map(Vec::len), Some(1));
+        assert_eq!(saved["repos"].as_array().map(Vec::len), Some(1));
+        assert_eq!(
+            saved["repo_colors"].as_table().map(toml::Table::len),
+            Some(1)
+        );
+    }
 }
@@ -0,0 +1,130 @@
+use std::fmt;
+
+/// Where a credential value came from, in precedence order: earlier
+/// variants win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CredentialSource {
+    Cli,
+    Env,
+    GitCredentials,
+    Config,
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Self::Cli => "cli",
+            Self::Env => "env",
+            Self::GitCredentials => "git credential helper",
+            Self::Config => "config",
+        };
+        write!(f, "{value}")
+    }
+}
+
+/// The email and token one source provided; either may be missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialLayer {
+    pub source: CredentialSource,
+    pub email: Option<String>,
+    pub api_token: Option<String>,
+}
+
+impl CredentialLayer {
+    pub fn view(&self) -> LayerView<'_> {
+        (
+            self.source,
+            self.email.as_deref(),
+            self.api_token.as_deref(),
+        )
+    }
+}
+
+/// A borrowed layer: source, email, token.
+pub type LayerView<'a> = (CredentialSource, Option<&'a str>, Option<&'a str>);
+
+/// The email and token to use, each with the source it was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResolvedCredentials<'a> {
+    pub email: Option<(&'a str, CredentialSource)>,
+    pub api_token: Option<(&'a str, CredentialSource)>,
+}
+
+impl<'a> ResolvedCredentials<'a> {
+    pub fn pair(&self) -> Option<(&'a str, &'a str)> {
+        Some((self.email?.0, self.api_token?.0))
+    }
+}
+
+/// Picks the email and the token independently from the highest-precedence
+/// layer that has them, so e.g. an env token can pair with a config email.
+pub fn resolve<'a>(layers: impl IntoIterator<Item = LayerView<'a>>) -> ResolvedCredentials<'a> {
+    let mut layers = layers.into_iter().collect::<Vec<_>>();
+    layers.sort_by_key(|(source, _, _)| *source);
+
+    ResolvedCredentials {
+        email: layers
+            .iter()
+            .find_map(|(source, email, _)| email.map(|email| (email, *source))),
+        api_token: layers
+            .iter()
+            .find_map(|(source, _, token)| token.map(|token| (token, *source))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CredentialLayer, CredentialSource, resolve};
+
+    fn layer(
+        source: CredentialSource,
+        email: Option<&str>,
+        token: Option<&str>,
+    ) -> CredentialLayer {
+        CredentialLayer {
+            source,
+            email: email.map(str::to_string),
+            api_token: token.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn follows_cli_env_git_config_precedence() {
+        let config = layer(CredentialSource::Config, Some("config@x"), Some("config"));
+        let git = layer(CredentialSource::GitCredentials, Some("git@x"), Some("git"));
+        let env = layer(CredentialSource::Env, Some("env@x"), Some("env"));
+        let cli = layer(CredentialSource::Cli, Some("cli@x"), Some("cli"));
+
+        let cases = [
+            (vec![&config], "config"),
+            (vec![&config, &git], "git"),
+            (vec![&config, &git, &env], "env"),
+            (vec![&config, &git, &env, &cli], "cli"),
+            (vec![&cli, &config], "cli"),
+            (vec![&env, &config], "env"),
+            (vec![&git, &env], "env"),
+        ];
+        for (layers, expected) in cases {
+            let resolved = resolve(layers.into_iter().map(CredentialLayer::view));
+            assert_eq!(resolved.api_token.map(|(token, _)| token), Some(expected));
+        }
+
+        let resolved = resolve([env.view(), config.view()]);
+        assert_eq!(resolved.api_token, Some(("env", CredentialSource::Env)));
+        assert_eq!(resolved.pair(), Some(("env@x", "env")));
+    }
+
+    #[test]
+    fn combines_partial_sources_per_field() {
+        let config = layer(CredentialSource::Config, Some("me@x"), None);
+        let env = layer(CredentialSource::Env, None, Some("token"));
+
+        let resolved = resolve([config.view(), env.view()]);
+        assert_eq!(resolved.email, Some(("me@x", CredentialSource::Config)));
+        assert_eq!(resolved.api_token, Some(("token", CredentialSource::Env)));
+        assert_eq!(resolved.pair(), Some(("me@x", "token")));
+
+        assert_eq!(resolve([config.view()]).pair(), None);
+        assert_eq!(resolve([]).pair(), None);
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Developer-facing trace written to a file when `--log-file` or `RUST_LOG`
+/// is set. Secrets registered with `redact` never reach the file.
+struct DebugLog {
+    file: File,
+    secrets: Vec<String>,
+}
+
+static DEBUG_LOG: OnceLock<Mutex<DebugLog>> = OnceLock::new();
+
+pub fn default_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("cannot determine home directory")?;
+    Ok(home.join(".config").join("myprs").join("myprs.log"))
+}
+
+pub fn init(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create log directory {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open log file {}", path.display()))?;
+
+    let _ = DEBUG_LOG.set(Mutex::new(DebugLog {
+        file,
+        secrets: Vec::new(),
+    }));
+    record(
+        "INFO",
+        &format!("myprs {} started", env!("CARGO_PKG_VERSION")),
+    );
+    Ok(())
+}
+
+/// Registers a value (e.g. the API token) that must be masked in every line.
+pub fn redact(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    if let Some(log) = DEBUG_LOG.get()
+        && let Ok(mut log) = log.lock()
+        && !log.secrets.iter().any(|known| known == secret)
+    {
+        log.secrets.push(secret.to_string());
+    }
+}
+
+pub fn record(level: &str, message: &str) {
+    let Some(log) = DEBUG_LOG.get() else {
+        return;
+    };
+    let Ok(mut log) = log.lock() else {
+        return;
+    };
+
+    let message = redact_secrets(message, &log.secrets);
+    let line = format!(
+        "{} {level:<5} {message}\n",
+        Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z")
+    );
+    let _ = log.file.write_all(line.as_bytes());
+}
+
+fn redact_secrets(message: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(message.to_string(), |message, secret| {
+        message.replace(secret.as_str(), REDACTED)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::redact_secrets;
+
+    #[test]
+    fn masks_registered_secrets() {
+        let secrets = vec!["s3cr3t".to_string()];
+        assert_eq!(
+            redact_secrets("GET https://x?token=s3cr3t failed: s3cr3t", &secrets),
+            "GET https://x?token=[REDACTED] failed: [REDACTED]"
+        );
+        assert_eq!(redact_secrets("nothing here", &secrets), "nothing here");
+    }
+}
@@ -0,0 +1,72 @@
+use crate::bitbucket::{http_status, is_connection_error};
+use reqwest::StatusCode;
+use std::fmt;
+
+/// Process exit codes for scripting, documented in the README.
+pub const CONFIG: u8 = 1;
+pub const AUTH: u8 = 2;
+pub const NETWORK: u8 = 3;
+pub const NO_RESULTS: u8 = 4;
+/// Any other failure, such as an API error status or an unreadable response.
+pub const API: u8 = 5;
+
+/// Marks an error as a configuration problem (bad or missing settings).
+/// Attach it with `.context(ConfigError)`.
+#[derive(Debug)]
+pub struct ConfigError;
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "configuration error")
+    }
+}
+
+/// A headless lookup that found nothing to report.
+#[derive(Debug)]
+pub struct NoResults(pub String);
+
+impl fmt::Display for NoResults {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for NoResults {}
+
+/// Maps a top-level error to its exit code. Only errors marked with
+/// [`ConfigError`] count as configuration errors; anything else that is not
+/// otherwise classified is an API/runtime failure.
+pub fn code_for(err: &anyhow::Error) -> u8 {
+    if err.downcast_ref::<NoResults>().is_some() {
+        return NO_RESULTS;
+    }
+    if err.downcast_ref::<ConfigError>().is_some() {
+        return CONFIG;
+    }
+    if matches!(
+        http_status(err),
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+    ) {
+        return AUTH;
+    }
+    if is_connection_error(err) {
+        return NETWORK;
+    }
+    API
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{API, CONFIG, ConfigError, NO_RESULTS, NoResults, code_for};
+    use anyhow::anyhow;
+
+    #[test]
+    fn maps_typed_errors_to_exit_codes() {
+        assert_eq!(code_for(&anyhow!("bad repo").context(ConfigError)), CONFIG);
+        assert_eq!(
+            code_for(&anyhow::Error::new(NoResults("no PRs".to_string()))),
+            NO_RESULTS
+        );
+        assert_eq!(code_for(&anyhow!("something else")), API);
+    }
+}
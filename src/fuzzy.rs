@@ -0,0 +1,48 @@
+/// Scores `candidate` against a fuzzy `query`: every query character must
+/// appear in order, ignoring case. Runs of consecutive characters and
+/// matches at the start of a word score higher, skipped characters lower.
+/// `None` when the query does not match.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate = candidate.chars().collect::<Vec<_>>();
+    let mut score = 0i64;
+    let mut position = 0usize;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.chars().filter(|ch| !ch.is_whitespace()) {
+        let offset = candidate[position..]
+            .iter()
+            .position(|ch| ch.eq_ignore_ascii_case(&wanted))?;
+        let index = position + offset;
+
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || matches!(candidate[index - 1], ' ' | '/' | '-' | '_') {
+            score += 3;
+        }
+        score -= offset as i64;
+
+        previous = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::score;
+
+    #[test]
+    fn ranks_word_starts_and_runs_above_scattered_matches() {
+        assert_eq!(score("xyz", "/refresh"), None);
+        assert_eq!(score("", "/refresh"), Some(0));
+        assert!(score("ref", "/refresh").is_some());
+        assert!(score("MA", "/mark-all-read").is_some());
+
+        let refresh = score("ref", "/refresh now").expect("refresh");
+        let scattered = score("ref", "/retry-failed").expect("retry-failed");
+        assert!(refresh > scattered);
+        assert!(score("ar", "/all-read") > score("ar", "/xaxxr"));
+    }
+}
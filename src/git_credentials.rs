@@ -0,0 +1,79 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Username/password pair returned by `git credential fill`.
+pub struct GitCredential {
+    pub username: String,
+    pub password: String,
+}
+
+/// Asks git's configured credential helper for `host` credentials. Prompting
+/// is disabled so a missing entry fails instead of blocking the terminal.
+pub fn fill(host: &str) -> Result<GitCredential> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to run git credential fill")?;
+
+    child
+        .stdin
+        .take()
+        .context("failed to open git credential stdin")?
+        .write_all(format!("protocol=https\nhost={host}\n\n").as_bytes())
+        .context("failed to write git credential request")?;
+
+    let output = child
+        .wait_with_output()
+        .context("failed to read git credential output")?;
+    if !output.status.success() {
+        bail!("git credential helper has no entry for {host}")
+    }
+
+    parse_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_output(raw: &str) -> Result<GitCredential> {
+    let mut username = None;
+    let mut password = None;
+    for line in raw.lines() {
+        match line.split_once('=') {
+            Some(("username", value)) => username = Some(value.to_string()),
+            Some(("password", value)) => password = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    match (username, password) {
+        (Some(username), Some(password)) if !username.is_empty() && !password.is_empty() => {
+            Ok(GitCredential { username, password })
+        }
+        _ => Err(anyhow!(
+            "git credential helper returned no username/password"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_output;
+
+    #[test]
+    fn parses_credential_fill_output() {
+        let credential = parse_output(
+            "protocol=https\nhost=bitbucket.org\nusername=me@example.com\npassword=a=b\n",
+        )
+        .expect("credential parse");
+        assert_eq!(credential.username, "me@example.com");
+        assert_eq!(credential.password, "a=b");
+    }
+
+    #[test]
+    fn rejects_output_without_password() {
+        assert!(parse_output("protocol=https\nhost=bitbucket.org\nusername=me\n").is_err());
+    }
+}
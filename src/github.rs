@@ -0,0 +1,278 @@
+use crate::bitbucket::{
+    ApiResponse, PullRequest, Relation, TlsOptions, Transport, UserId, http_client,
+};
+use crate::config::PrStatus;
+use crate::debug_log;
+use crate::provider::PrProvider;
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use reqwest::header::{ACCEPT, USER_AGENT};
+use reqwest::{Method, Url};
+use serde::Deserialize;
+
+const SEARCH_PAGE_SIZE: usize = 100;
+/// The search API stops at 1000 results, i.e. ten full pages.
+const MAX_SEARCH_PAGES: usize = 10;
+
+/// Sends GitHub API requests with a personal access token.
+struct TokenTransport {
+    http: Client,
+    token: String,
+}
+
+impl Transport for TokenTransport {
+    fn send(&self, method: Method, endpoint: &Url) -> Result<ApiResponse> {
+        let result = self
+            .http
+            .request(method.clone(), endpoint.clone())
+            .bearer_auth(&self.token)
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, concat!("myprs/", env!("CARGO_PKG_VERSION")))
+            .send();
+        match &result {
+            Ok(response) => debug_log::record(
+                "DEBUG",
+                &format!("{method} {endpoint} -> {}", response.status()),
+            ),
+            Err(err) => debug_log::record("ERROR", &format!("{method} {endpoint} failed: {err}")),
+        }
+        let response = result?;
+        Ok(ApiResponse {
+            status: response.status(),
+            body: response.text()?,
+        })
+    }
+}
+
+/// Lists the current user's pull requests on GitHub (or GitHub Enterprise)
+/// through the issue search API.
+pub struct GithubClient {
+    transport: Box<dyn Transport>,
+    base_url: String,
+}
+
+impl GithubClient {
+    pub fn new(base_url: String, token: String, tls: &TlsOptions) -> Result<Self> {
+        let transport = TokenTransport {
+            http: http_client(tls)?,
+            token,
+        };
+        Ok(Self::with_transport(&base_url, Box::new(transport)))
+    }
+
+    pub fn with_transport(base_url: &str, transport: Box<dyn Transport>) -> Self {
+        Self {
+            transport,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl PrProvider for GithubClient {
+    /// The login of the token's owner.
+    fn current_user(&self) -> Result<UserId> {
+        let endpoint = Url::parse(&format!("{}/user", self.base_url))
+            .context("failed to build GitHub user endpoint")?;
+        let user: GithubUser = self
+            .transport
+            .send(Method::GET, &endpoint)
+            .context("failed to call GitHub user API")?
+            .error_for_status()
+            .context("GitHub user API returned an error status")?
+            .json()
+            .context("failed to deserialize GitHub user response")?;
+        Ok(UserId::Nickname(user.login))
+    }
+
+    /// Searches PRs opened by `user` in any repo the token can see, most
+    /// recently updated first.
+    fn list_pull_requests_created_by(
+        &self,
+        user: &UserId,
+        status: PrStatus,
+    ) -> Result<Vec<PullRequest>> {
+        let mut query = format!("is:pr author:{}", user.value());
+        match status {
+            PrStatus::Open => query.push_str(" is:open"),
+            PrStatus::Merged => query.push_str(" is:merged"),
+            PrStatus::Declined => query.push_str(" is:closed is:unmerged"),
+            PrStatus::All => {}
+        }
+
+        let mut pull_requests = Vec::new();
+        for page in 1..=MAX_SEARCH_PAGES {
+            let mut endpoint = Url::parse(&format!("{}/search/issues", self.base_url))
+                .context("failed to build GitHub search endpoint")?;
+            endpoint
+                .query_pairs_mut()
+                .append_pair("q", &query)
+                .append_pair("sort", "updated")
+                .append_pair("order", "desc")
+                .append_pair("per_page", &SEARCH_PAGE_SIZE.to_string())
+                .append_pair("page", &page.to_string());
+
+            let payload: SearchResponse = self
+                .transport
+                .send(Method::GET, &endpoint)
+                .context("failed to call GitHub search API")?
+                .error_for_status()
+                .context("GitHub search API returned an error status")?
+                .json()
+                .context("failed to deserialize GitHub search response")?;
+
+            let full_page = payload.items.len() == SEARCH_PAGE_SIZE;
+            pull_requests.extend(
+                payload
+                    .items
+                    .into_iter()
+                    .filter_map(SearchItem::into_pull_request),
+            );
+            if !full_page {
+                break;
+            }
+        }
+        Ok(pull_requests)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    items: Vec<SearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchItem {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    user: Option<GithubUser>,
+    state: String,
+    #[serde(default)]
+    comments: u64,
+    created_at: String,
+    updated_at: String,
+    html_url: String,
+    /// `{base}/repos/{owner}/{repo}`.
+    repository_url: String,
+    pull_request: Option<SearchPullRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPullRequest {
+    merged_at: Option<String>,
+}
+
+impl SearchItem {
+    /// Maps a search hit onto the shared PR model: the owner stands in for
+    /// the workspace, and closed PRs become MERGED or DECLINED as on
+    /// Bitbucket. Issues, which the search can also return, are skipped.
+    fn into_pull_request(self) -> Option<PullRequest> {
+        let merged = self.pull_request?.merged_at.is_some();
+        let (owner, repo) = self
+            .repository_url
+            .rsplit_once("/repos/")
+            .and_then(|(_, full_name)| full_name.split_once('/'))?;
+        let state = match self.state.as_str() {
+            "open" => "OPEN",
+            _ if merged => "MERGED",
+            _ => "DECLINED",
+        };
+
+        Some(PullRequest {
+            workspace: owner.to_string(),
+            repo: repo.to_string(),
+            id: self.number,
+            title: self.title,
+            description: self.body.unwrap_or_default(),
+            author: self
+                .user
+                .map_or_else(|| "unknown".to_string(), |user| user.login),
+            author_uuid: None,
+            author_account_id: None,
+            state: state.to_string(),
+            comment_count: self.comments,
+            created_on: self.created_at,
+            updated_on: self.updated_at,
+            url: self.html_url,
+            source_branch: None,
+            destination_branch: None,
+            close_source_branch: false,
+            build_url: None,
+            diffstat: None,
+            relation: Relation::Authored,
+            participants: Vec::new(),
+            issue_keys: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GithubClient;
+    use crate::bitbucket::{ApiResponse, Transport, UserId};
+    use crate::config::PrStatus;
+    use crate::provider::PrProvider;
+    use anyhow::Result;
+    use reqwest::{Method, StatusCode, Url};
+
+    /// Answers `/user` and one page of search results.
+    struct CannedGithub;
+
+    impl Transport for CannedGithub {
+        fn send(&self, _method: Method, endpoint: &Url) -> Result<ApiResponse> {
+            let body = if endpoint.path() == "/user" {
+                r#"{"login": "octocat"}"#.to_string()
+            } else {
+                let query = endpoint
+                    .query_pairs()
+                    .find(|(name, _)| name == "q")
+                    .map(|(_, query)| query.into_owned())
+                    .unwrap_or_default();
+                assert_eq!(query, "is:pr author:octocat is:merged");
+                r#"{"total_count": 2, "items": [
+                    {"number": 7, "title": "Add retries", "body": null,
+                     "user": {"login": "octocat"}, "state": "closed", "comments": 3,
+                     "created_at": "2026-03-01T10:00:00Z", "updated_at": "2026-03-02T10:00:00Z",
+                     "html_url": "https://github.com/acme/api/pull/7",
+                     "repository_url": "https://api.github.com/repos/acme/api",
+                     "pull_request": {"merged_at": "2026-03-02T10:00:00Z"}},
+                    {"number": 8, "title": "Not a PR", "state": "open",
+                     "created_at": "2026-03-01T10:00:00Z", "updated_at": "2026-03-02T10:00:00Z",
+                     "html_url": "https://github.com/acme/api/issues/8",
+                     "repository_url": "https://api.github.com/repos/acme/api"}
+                ]}"#
+                .to_string()
+            };
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body,
+            })
+        }
+    }
+
+    #[test]
+    fn lists_search_results_as_pull_requests() {
+        let client =
+            GithubClient::with_transport("https://api.github.com/", Box::new(CannedGithub));
+        let user = client.current_user().expect("user");
+        assert_eq!(user, UserId::Nickname("octocat".to_string()));
+
+        let pull_requests = client
+            .list_pull_requests_created_by(&user, PrStatus::Merged)
+            .expect("search");
+        assert_eq!(pull_requests.len(), 1);
+        let pr = &pull_requests[0];
+        assert_eq!(
+            (pr.workspace.as_str(), pr.repo.as_str(), pr.id),
+            ("acme", "api", 7)
+        );
+        assert_eq!(pr.state, "MERGED");
+        assert_eq!(pr.author, "octocat");
+        assert_eq!(pr.comment_count, 3);
+    }
+}
@@ -0,0 +1,397 @@
+use crate::bitbucket::{BitbucketClient, PullRequest, PullRequestQuery, Relation, UserId};
+use crate::browser;
+use crate::capture;
+use crate::config::{Config, FetchStrategy, Provider, RepoRef};
+use crate::exit_status::{ConfigError, NoResults};
+use crate::github::GithubClient;
+use crate::provider;
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+
+/// Resolves PR `id` in `repo_args` (or every configured repo when empty) and
+/// opens it in the browser without starting the TUI.
+pub fn open_pull_request(config: &Config, repo_args: &[String], id: u64) -> Result<()> {
+    let repos = if repo_args.is_empty() {
+        config.repos()
+    } else {
+        repo_args
+            .iter()
+            .map(|repo| RepoRef::parse(repo))
+            .collect::<Result<Vec<_>>>()
+            .context(ConfigError)?
+    };
+    if repos.is_empty() {
+        return Err(
+            anyhow!("no repos to search; pass --repo <workspace>/<repo>").context(ConfigError),
+        );
+    }
+    if config.provider() != Provider::Bitbucket {
+        return Err(
+            anyhow!("--open is not supported for {}", config.provider().name())
+                .context(ConfigError),
+        );
+    }
+
+    let client = client_from_config(config)?;
+    let mut matches = Vec::new();
+    for repo in &repos {
+        if let Some(pr) = client.get_pull_request(&repo.workspace, &repo.repo, id)? {
+            matches.push(pr);
+        }
+    }
+
+    let pr = match matches.len() {
+        0 => {
+            return Err(NoResults(format!("PR #{id} not found in {}", join_repos(&repos))).into());
+        }
+        1 => matches.remove(0),
+        _ => {
+            let found = matches
+                .iter()
+                .map(|pr| format!("{}/{}", pr.workspace, pr.repo))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(anyhow!(
+                "PR #{id} exists in several repos ({found}); pick one with --repo"
+            )
+            .context(ConfigError));
+        }
+    };
+
+    browser::open(&pr.url, config.browser_command.as_deref())?;
+    println!(
+        "Opened {}/{} PR #{} {}",
+        pr.workspace, pr.repo, pr.id, pr.title
+    );
+    Ok(())
+}
+
+/// Fetches the current user's PRs for every configured repo and watched
+/// workspace with the launch status, outside the TUI. Unlike the TUI, any
+/// failing repo fails the whole fetch.
+pub fn fetch_pull_requests(config: &Config) -> Result<Vec<PullRequest>> {
+    let mut pull_requests = Vec::new();
+    fetch_batches(config, |batch| {
+        pull_requests.extend(batch);
+        Ok(())
+    })?;
+    sort_for_list(&mut pull_requests);
+    Ok(pull_requests)
+}
+
+/// Like `fetch_pull_requests`, but hands each repo's PRs (or, with the
+/// aggregate strategy, the whole list) to `on_batch` as soon as they arrive,
+/// sorted within the batch. Empty batches are skipped.
+pub fn fetch_batches(
+    config: &Config,
+    mut on_batch: impl FnMut(Vec<PullRequest>) -> Result<()>,
+) -> Result<()> {
+    if config.provider() != Provider::Bitbucket {
+        // One listing across repos, narrowed to the configured ones if any.
+        let provider = provider::from_config(config)?;
+        let user = provider.current_user()?;
+        let mut pull_requests =
+            provider.list_pull_requests_created_by(&user, config.launch_status())?;
+        let repos = config.repos();
+        if !repos.is_empty() {
+            pull_requests = retain_watched(pull_requests, &repos, config.max_results_per_repo());
+        }
+        sort_for_list(&mut pull_requests);
+        if !pull_requests.is_empty() {
+            on_batch(pull_requests)?;
+        }
+        return Ok(());
+    }
+
+    let client = client_from_config(config)?;
+    let user_ids = client.current_user_ids(config.author_identifier)?;
+
+    let mut repos = config.repos();
+    let sources = config
+        .workspaces()
+        .iter()
+        .map(|workspace| (workspace.as_str(), None))
+        .chain(
+            config
+                .projects()
+                .iter()
+                .filter_map(|project| project.split_once('/'))
+                .map(|(workspace, key)| (workspace, Some(key))),
+        );
+    for (workspace, project_key) in sources {
+        for slug in client.list_repositories(workspace, project_key)? {
+            let repo = RepoRef::new(workspace.to_string(), slug);
+            if !repos.contains(&repo) {
+                repos.push(repo);
+            }
+        }
+    }
+    if repos.is_empty() {
+        return Err(
+            anyhow!("no repos configured. Add repos with --repo <workspace>/<repo>")
+                .context(ConfigError),
+        );
+    }
+
+    // Nothing has been handed over while a fallback identifier is still
+    // worth trying, since that only happens after finding no PRs at all.
+    for (attempt, user_id) in user_ids.iter().enumerate() {
+        let found = fetch_authored(config, &client, user_id, &repos, &mut on_batch)?;
+        if found > 0 {
+            if attempt > 0 {
+                eprintln!(
+                    "No PRs matched author.{}; found them with author.{}",
+                    user_ids[0].query_field(),
+                    user_id.query_field()
+                );
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Passes the PRs in `repos` authored by `user_id`, with the launch status,
+/// to `on_batch`. Returns how many there were.
+fn fetch_authored(
+    config: &Config,
+    client: &BitbucketClient,
+    user_id: &UserId,
+    repos: &[RepoRef],
+    on_batch: &mut impl FnMut(Vec<PullRequest>) -> Result<()>,
+) -> Result<usize> {
+    let status = config.launch_status();
+    let max_results = config.max_results_per_repo();
+    let mut emit = |mut pull_requests: Vec<PullRequest>| -> Result<usize> {
+        let count = pull_requests.len();
+        if count > 0 {
+            sort_for_list(&mut pull_requests);
+            on_batch(pull_requests)?;
+        }
+        Ok(count)
+    };
+    match config.fetch_strategy {
+        FetchStrategy::PerRepo => {
+            let query = PullRequestQuery {
+                user: user_id,
+                relation: Relation::Authored,
+                status,
+                max_results,
+                custom_query: None,
+            };
+            // Long listings report their progress when someone is watching.
+            let show_progress = io::stderr().is_terminal();
+            let mut found = 0;
+            for repo in repos {
+                let batch = client
+                    .list_pull_requests(&repo.workspace, &repo.repo, &query, |page, fetched| {
+                        if show_progress && page > 1 {
+                            eprintln!("{repo}: fetched {fetched} PR(s), page {page}...");
+                        }
+                    })
+                    .with_context(|| format!("failed loading {repo}"))?;
+                if let Some(truncation) = batch.truncated {
+                    eprintln!("Results for {repo} {truncation}.");
+                }
+                found += emit(batch.pull_requests)?;
+            }
+            Ok(found)
+        }
+        FetchStrategy::Aggregate => emit(retain_watched(
+            client.list_my_pull_requests(user_id, status, None, |_, _| {})?,
+            repos,
+            max_results,
+        )),
+    }
+}
+
+/// List order: by repo, most recently updated first. Ties on `updated_on`
+/// fall back to the newest id so the order is the same on every fetch.
+pub fn sort_for_list(pull_requests: &mut [PullRequest]) {
+    pull_requests.sort_by(|left, right| {
+        left.workspace
+            .cmp(&right.workspace)
+            .then(left.repo.cmp(&right.repo))
+            .then_with(|| right.updated_on.cmp(&left.updated_on))
+            .then(right.id.cmp(&left.id))
+    });
+}
+
+/// Output of the PR list without the TUI, chosen with `--format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Ndjson,
+}
+
+/// Prints the PR list as a plain tab-separated table, for when stdout is
+/// piped and the TUI would only emit escape sequences.
+pub fn print_table(config: &Config) -> Result<()> {
+    for line in table_lines(&fetch_pull_requests(config)?) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// Prints one JSON object per PR and line, flushing after each repo so
+/// consumers can start before every repo has been fetched.
+pub fn print_ndjson(config: &Config) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    fetch_batches(config, |batch| {
+        for pr in &batch {
+            writeln!(stdout, "{}", ndjson_line(pr)?).context("failed to write to stdout")?;
+        }
+        stdout.flush().context("failed to flush stdout")
+    })
+}
+
+fn ndjson_line(pr: &PullRequest) -> Result<String> {
+    serde_json::to_string(pr).context("failed to serialize pull request")
+}
+
+fn table_lines(pull_requests: &[PullRequest]) -> Vec<String> {
+    pull_requests
+        .iter()
+        .map(|pr| {
+            format!(
+                "{}/{}\t#{}\t{}\t{}\t{}\t{}",
+                pr.workspace, pr.repo, pr.id, pr.state, pr.author, pr.title, pr.url
+            )
+        })
+        .collect()
+}
+
+/// Keeps the PRs that belong to one of `repos`, at most `max_results` per
+/// repo. Expects PRs in most-recently-updated order.
+pub fn retain_watched(
+    pull_requests: Vec<PullRequest>,
+    repos: &[RepoRef],
+    max_results: Option<u32>,
+) -> Vec<PullRequest> {
+    let mut per_repo: HashMap<(String, String), u32> = HashMap::new();
+    pull_requests
+        .into_iter()
+        .filter(|pr| {
+            repos.iter().any(|repo| {
+                repo.workspace.eq_ignore_ascii_case(&pr.workspace)
+                    && repo.repo.eq_ignore_ascii_case(&pr.repo)
+            })
+        })
+        .filter(|pr| {
+            let count = per_repo
+                .entry((
+                    pr.workspace.to_ascii_lowercase(),
+                    pr.repo.to_ascii_lowercase(),
+                ))
+                .or_default();
+            *count += 1;
+            max_results.is_none_or(|cap| *count <= cap)
+        })
+        .collect()
+}
+
+pub fn client_from_config(config: &Config) -> Result<BitbucketClient> {
+    if config.provider() != Provider::Bitbucket {
+        return Err(anyhow!(
+            "Bitbucket requests are not supported for {}",
+            config.provider().name()
+        )
+        .context(ConfigError));
+    }
+    let (email, api_token) = config.credentials().ok_or_else(|| {
+        anyhow!("missing credentials. Set BITBUCKET_EMAIL and BITBUCKET_API_TOKEN")
+            .context(ConfigError)
+    })?;
+
+    let client = BitbucketClient::new(
+        config.bitbucket_base_url.clone(),
+        email.to_string(),
+        api_token.to_string(),
+        &config.tls_options(),
+    )?;
+    Ok(capture::apply(client, config.api_capture()))
+}
+
+pub fn github_client_from_config(config: &Config) -> Result<GithubClient> {
+    let token = config.github_token().ok_or_else(|| {
+        anyhow!("missing GitHub token. Set GITHUB_TOKEN or github_token").context(ConfigError)
+    })?;
+    GithubClient::new(config.github_base_url.clone(), token, &config.tls_options())
+}
+
+fn join_repos(repos: &[RepoRef]) -> String {
+    repos
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ndjson_line, retain_watched, sort_for_list, table_lines};
+    use crate::bitbucket::PullRequest;
+    use crate::config::RepoRef;
+
+    fn pull_request(repo: &str, id: u64) -> PullRequest {
+        PullRequest::fixture(repo, id)
+    }
+
+    #[test]
+    fn serializes_each_pull_request_as_one_json_line() {
+        let line = ndjson_line(&pull_request("api", 7)).expect("ndjson");
+        assert!(!line.contains('\n'));
+        let json = serde_json::from_str::<serde_json::Value>(&line).expect("json");
+        assert_eq!(json["repo"], "api");
+        assert_eq!(json["id"], 7);
+        assert_eq!(json["relation"], "authored");
+    }
+
+    #[test]
+    fn prints_one_tab_separated_row_per_pull_request() {
+        let mut pr = pull_request("api", 7);
+        pr.url = "https://bitbucket.org/team/api/pull-requests/7".to_string();
+        assert_eq!(
+            table_lines(&[pr]),
+            vec!["team/api\t#7\tOPEN\tMe\tPR 7\thttps://bitbucket.org/team/api/pull-requests/7"]
+        );
+    }
+
+    #[test]
+    fn aggregate_listing_keeps_only_watched_repos() {
+        let repos = vec![RepoRef::parse("team/api").expect("repo")];
+        let watched = retain_watched(
+            vec![
+                pull_request("api", 3),
+                pull_request("web", 2),
+                pull_request("api", 1),
+            ],
+            &repos,
+            None,
+        );
+        assert_eq!(
+            watched.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![3, 1]
+        );
+
+        let capped = retain_watched(watched, &repos, Some(1));
+        assert_eq!(capped.iter().map(|pr| pr.id).collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn sorts_ties_on_updated_on_by_id() {
+        let mut pull_requests = vec![
+            pull_request("web", 9),
+            pull_request("api", 4),
+            pull_request("api", 8),
+            pull_request("api", 5),
+        ];
+        pull_requests[3].updated_on = "2026-03-02T00:00:00+00:00".to_string();
+        sort_for_list(&mut pull_requests);
+        assert_eq!(
+            pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![5, 8, 4, 9]
+        );
+    }
+}
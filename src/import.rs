@@ -0,0 +1,132 @@
+use crate::bitbucket::http_client;
+use crate::config::{Config, RepoRef};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+
+/// Shared onboarding file: a repo list and optionally the base URL. Any
+/// other keys, credentials included, are ignored.
+#[derive(Debug, Default, Deserialize)]
+struct Template {
+    #[serde(default)]
+    repos: Vec<TemplateRepo>,
+    bitbucket_base_url: Option<String>,
+}
+
+/// Repos may be written as `"workspace/repo"` or as config-style tables.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TemplateRepo {
+    Slug(String),
+    Table(RepoRef),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub base_url: Option<String>,
+    /// A base URL a downloaded template tried to set. Only local templates
+    /// may change where credentials are sent.
+    pub ignored_base_url: Option<String>,
+}
+
+/// Reads a TOML or JSON template from a local path or an http(s) URL and
+/// merges it into `config`, which the caller saves. Downloads honour the
+/// configured TLS options, and a downloaded template's base URL is ignored.
+pub fn import(config: &mut Config, source: &str) -> Result<ImportReport> {
+    let remote = source.starts_with("http://") || source.starts_with("https://");
+    let raw = if remote {
+        http_client(&config.tls_options())?
+            .get(source)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .with_context(|| format!("failed to download {source}"))?
+    } else {
+        fs::read_to_string(source).with_context(|| format!("failed to read {source}"))?
+    };
+    let mut template = parse_template(&raw).with_context(|| format!("failed to parse {source}"))?;
+    let ignored_base_url = if remote {
+        template.bitbucket_base_url.take()
+    } else {
+        None
+    };
+    Ok(ImportReport {
+        ignored_base_url,
+        ..merge(config, template)?
+    })
+}
+
+fn parse_template(raw: &str) -> Result<Template> {
+    if raw.trim_start().starts_with('{') {
+        serde_json::from_str(raw).context("invalid JSON template")
+    } else {
+        toml::from_str(raw).context("invalid TOML template")
+    }
+}
+
+/// Adds the template's repos, skipping ones already configured, and adopts
+/// its base URL only while the config still points at the default.
+fn merge(config: &mut Config, template: Template) -> Result<ImportReport> {
+    let mut report = ImportReport {
+        imported: 0,
+        skipped: 0,
+        base_url: None,
+        ignored_base_url: None,
+    };
+    for repo in template.repos {
+        let repo = match repo {
+            TemplateRepo::Slug(slug) => RepoRef::parse(&slug)?,
+            TemplateRepo::Table(repo) => repo,
+        };
+        if config.add_repo(repo) {
+            report.imported += 1;
+        } else {
+            report.skipped += 1;
+        }
+    }
+
+    if let Some(base_url) = template.bitbucket_base_url
+        && config.bitbucket_base_url == Config::default().bitbucket_base_url
+        && base_url != config.bitbucket_base_url
+    {
+        config.bitbucket_base_url = base_url.clone();
+        report.base_url = Some(base_url);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImportReport, merge, parse_template};
+    use crate::config::{Config, RepoRef};
+
+    #[test]
+    fn merges_toml_and_json_templates_without_secrets() {
+        let mut config = Config::default();
+        config.add_repo(RepoRef::parse("team/api").expect("repo"));
+
+        let toml = r#"
+            bitbucket_api_token = "leaked"
+            bitbucket_base_url = "https://bitbucket.example.com/2.0"
+            repos = ["team/api", "team/web"]
+        "#;
+        let report = merge(&mut config, parse_template(toml).expect("toml")).expect("merge");
+        assert_eq!(
+            report,
+            ImportReport {
+                imported: 1,
+                skipped: 1,
+                base_url: Some("https://bitbucket.example.com/2.0".to_string()),
+                ignored_base_url: None,
+            }
+        );
+        assert!(config.bitbucket_api_token.is_none());
+
+        let json = r#"{"repos": [{"workspace": "team", "repo": "ops"}, "team/web"]}"#;
+        let report = merge(&mut config, parse_template(json).expect("json")).expect("merge");
+        assert_eq!((report.imported, report.skipped), (1, 1));
+        assert_eq!(config.repos.len(), 3);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::bitbucket::PullRequest;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Matches Jira-style keys such as `ABC-123`.
+pub const DEFAULT_ISSUE_KEY_PATTERN: &str = r"\b[A-Z][A-Z0-9]+-\d+\b";
+
+pub fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).with_context(|| format!("invalid issue_key_pattern '{pattern}'"))
+}
+
+/// Issue keys mentioned in the PR title, then its description, without
+/// duplicates and in order of first mention.
+pub fn issue_keys(pattern: &Regex, pr: &PullRequest) -> Vec<String> {
+    let mut keys = Vec::new();
+    for text in [&pr.title, &pr.description] {
+        for found in pattern.find_iter(text) {
+            let key = found.as_str().to_string();
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    keys
+}
+
+/// Fills the `{key}` placeholder of `issue_url_template`.
+pub fn issue_url(template: &str, key: &str) -> String {
+    template.replace("{key}", key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_ISSUE_KEY_PATTERN, compile, issue_keys, issue_url};
+    use crate::bitbucket::PullRequest;
+
+    #[test]
+    fn extracts_unique_keys_from_title_and_description() {
+        let pr = PullRequest {
+            title: "OPS-12: retry uploads".to_string(),
+            description: "Follow-up to OPS-12 and APP2-7; not utf-8 or x-1.".to_string(),
+            ..PullRequest::fixture("api", 1)
+        };
+
+        let pattern = compile(DEFAULT_ISSUE_KEY_PATTERN).expect("pattern");
+        assert_eq!(issue_keys(&pattern, &pr), vec!["OPS-12", "APP2-7"]);
+        let ops_only = compile(r"OPS-\d+").expect("pattern");
+        assert_eq!(issue_keys(&ops_only, &pr), vec!["OPS-12"]);
+        assert!(compile("(").is_err());
+
+        assert_eq!(
+            issue_url("https://jira.example.com/browse/{key}", "OPS-12"),
+            "https://jira.example.com/browse/OPS-12"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+
+/// TUI actions that can be rebound in the `[keys]` config table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Refresh,
+    Open,
+    Up,
+    Down,
+    Complete,
+    CopyId,
+    Pipeline,
+    Focus,
+    Collapse,
+    OpenRepo,
+    Palette,
+    Detail,
+}
+
+impl Action {
+    const ALL: [Action; 13] = [
+        Action::Quit,
+        Action::Refresh,
+        Action::Open,
+        Action::Up,
+        Action::Down,
+        Action::Complete,
+        Action::CopyId,
+        Action::Pipeline,
+        Action::Focus,
+        Action::Collapse,
+        Action::OpenRepo,
+        Action::Palette,
+        Action::Detail,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Quit => "quit",
+            Self::Refresh => "refresh",
+            Self::Open => "open",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::Complete => "complete",
+            Self::CopyId => "copy_id",
+            Self::Pipeline => "pipeline",
+            Self::Focus => "focus",
+            Self::Collapse => "collapse",
+            Self::OpenRepo => "open_repo",
+            Self::Palette => "palette",
+            Self::Detail => "detail",
+        }
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Quit => &["esc", "ctrl+c"],
+            Self::Refresh => &["f5", "r"],
+            Self::Open => &["enter"],
+            Self::Up => &["up"],
+            Self::Down => &["down"],
+            Self::Complete => &["tab"],
+            Self::CopyId => &["Y"],
+            Self::Pipeline => &["b"],
+            Self::Focus => &["f"],
+            Self::Collapse => &["z"],
+            Self::OpenRepo => &["o"],
+            Self::Palette => &["ctrl+p"],
+            Self::Detail => &["d"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeySpec {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+    /// Parses `ctrl+c`, `alt+j`, `f5`, `pgdn`, `Y`, and similar. Modifier
+    /// names are case-insensitive; a single character keeps its case.
+    fn parse(spec: &str) -> Result<Self> {
+        let mut parts = spec.trim().split('+').collect::<Vec<_>>();
+        let key = parts
+            .pop()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| anyhow!("empty key '{spec}'"))?;
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier '{modifier}' in key '{spec}'"),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(ch), None) => KeyCode::Char(ch),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pgup" | "pageup" => KeyCode::PageUp,
+                "pgdn" | "pagedown" => KeyCode::PageDown,
+                "backspace" => KeyCode::Backspace,
+                name => match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => bail!("unknown key '{spec}'"),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    fn matches(&self, key: &KeyEvent) -> bool {
+        if self.code != key.code {
+            return false;
+        }
+        // Shift is implied by a character's case, so only Ctrl/Alt must agree.
+        if matches!(self.code, KeyCode::Char(_)) {
+            let relevant = KeyModifiers::CONTROL | KeyModifiers::ALT;
+            self.modifiers & relevant == key.modifiers & relevant
+        } else {
+            self.modifiers == key.modifiers
+        }
+    }
+
+    /// Plain character keys double as text input, so they only act as
+    /// bindings while the command input is empty.
+    fn types_text(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+/// Resolved bindings: the defaults, with each action listed in `[keys]`
+/// replaced by its configured keys.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, KeySpec)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::resolve(&BTreeMap::new()).expect("default key bindings are valid")
+    }
+}
+
+impl Keymap {
+    pub fn resolve(overrides: &BTreeMap<String, Vec<String>>) -> Result<Self> {
+        for name in overrides.keys() {
+            if !Action::ALL.iter().any(|action| action.name() == name) {
+                let known = Action::ALL.map(Action::name).join(", ");
+                bail!("unknown key action '{name}'. expected one of: {known}");
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            match overrides.get(action.name()) {
+                Some(keys) => {
+                    for key in keys {
+                        bindings.push((action, KeySpec::parse(key)?));
+                    }
+                }
+                None => {
+                    for key in action.default_keys() {
+                        bindings.push((action, KeySpec::parse(key)?));
+                    }
+                }
+            }
+        }
+        Ok(Self { bindings })
+    }
+
+    /// Action bound to `key`, if any. `input_empty` says whether the command
+    /// input is empty, which plain character bindings require.
+    pub fn action(&self, key: &KeyEvent, input_empty: bool) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, spec)| spec.matches(key) && (input_empty || !spec.types_text()))
+            .map(|(action, _)| *action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Keymap};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn resolves_defaults_and_overrides() {
+        let default = Keymap::default();
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(default.action(&ctrl_c, false), Some(Action::Quit));
+        let r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE);
+        assert_eq!(default.action(&r, true), Some(Action::Refresh));
+        assert_eq!(default.action(&r, false), None);
+
+        let overrides = BTreeMap::from([(
+            "down".to_string(),
+            vec!["j".to_string(), "ctrl+n".to_string()],
+        )]);
+        let keymap = Keymap::resolve(&overrides).expect("keymap");
+        let j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(&j, true), Some(Action::Down));
+        let ctrl_n = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(&ctrl_n, false), Some(Action::Down));
+        let arrow = KeyEvent::new(KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(keymap.action(&arrow, true), None);
+        let shifted = KeyEvent::new(KeyCode::Char('Y'), KeyModifiers::SHIFT);
+        assert_eq!(keymap.action(&shifted, true), Some(Action::CopyId));
+    }
+
+    #[test]
+    fn rejects_unknown_actions_and_keys() {
+        let unknown_action = BTreeMap::from([("jump".to_string(), vec!["j".to_string()])]);
+        assert!(Keymap::resolve(&unknown_action).is_err());
+
+        let unknown_key = BTreeMap::from([("quit".to_string(), vec!["hyper+q".to_string()])]);
+        assert!(Keymap::resolve(&unknown_key).is_err());
+    }
+}
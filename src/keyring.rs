@@ -0,0 +1,139 @@
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// What `bitbucket_api_token` is set to when the token lives in the keyring.
+pub const MARKER: &str = "keyring";
+const SERVICE: &str = "myprs";
+
+/// Reads the token stored for `account` (the Bitbucket email) from the OS
+/// keyring: the login keychain on macOS, the Secret Service (`secret-tool`)
+/// elsewhere.
+pub fn lookup(account: &str) -> Result<String> {
+    let output = if cfg!(target_os = "macos") {
+        run(
+            "security",
+            &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+            None,
+        )?
+    } else if cfg!(unix) {
+        run(
+            "secret-tool",
+            &["lookup", "service", SERVICE, "account", account],
+            None,
+        )?
+    } else {
+        bail!("no keyring backend is available on this platform")
+    };
+    if !output.status.success() {
+        bail!("the keyring has no token for {account}")
+    }
+    parse_secret(&String::from_utf8_lossy(&output.stdout))
+        .with_context(|| format!("the keyring has an empty token for {account}"))
+}
+
+/// Stores `token` for `account`, replacing any earlier entry.
+pub fn store(account: &str, token: &str) -> Result<()> {
+    let output = if cfg!(target_os = "macos") {
+        // `security -i` reads the command from stdin, so the token never
+        // shows up in the process list the way `-w <token>` would.
+        let command = format!(
+            "add-generic-password -U -s {} -a {} -w {}\n",
+            quote(SERVICE)?,
+            quote(account)?,
+            quote(token)?
+        );
+        let output = run("security", &["-i"], Some(&command))?;
+        // Interactive mode exits 0 even when the command fails, so read the
+        // entry back to confirm it was stored.
+        if output.status.success() && lookup(account).ok().as_deref() != Some(token) {
+            bail!(
+                "failed to store the token in the keyring: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        output
+    } else if cfg!(unix) {
+        // secret-tool reads the secret from stdin, keeping it out of `ps`.
+        run(
+            "secret-tool",
+            &[
+                "store",
+                "--label=myprs Bitbucket API token",
+                "service",
+                SERVICE,
+                "account",
+                account,
+            ],
+            Some(token),
+        )?
+    } else {
+        bail!("no keyring backend is available on this platform")
+    };
+    if !output.status.success() {
+        bail!(
+            "failed to store the token in the keyring: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+    }
+    Ok(())
+}
+
+fn run(program: &str, args: &[&str], stdin: Option<&str>) -> Result<Output> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("no keyring backend available ({program} not found)"))?;
+
+    let mut input = child
+        .stdin
+        .take()
+        .with_context(|| format!("failed to open {program} stdin"))?;
+    if let Some(value) = stdin {
+        input
+            .write_all(value.as_bytes())
+            .with_context(|| format!("failed to write to {program}"))?;
+    }
+    drop(input);
+
+    child
+        .wait_with_output()
+        .with_context(|| format!("failed to read {program} output"))
+}
+
+/// Quotes an argument for `security -i`, which splits its input line on
+/// whitespace and honours double quotes and backslash escapes.
+fn quote(value: &str) -> Result<String> {
+    if value.contains(['\n', '\r']) {
+        bail!("keyring values cannot contain line breaks")
+    }
+    Ok(format!(
+        "\"{}\"",
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+fn parse_secret(raw: &str) -> Option<String> {
+    let secret = raw.trim_end_matches(['\r', '\n']);
+    (!secret.is_empty()).then(|| secret.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_secret, quote};
+
+    #[test]
+    fn trims_the_trailing_newline_only() {
+        assert_eq!(parse_secret(" s3cr3t \n").as_deref(), Some(" s3cr3t "));
+        assert_eq!(parse_secret("\n"), None);
+    }
+
+    #[test]
+    fn quotes_interactive_security_arguments() {
+        assert_eq!(quote(r#"a "b" \c"#).expect("quote"), r#""a \"b\" \\c""#);
+        assert!(quote("two\nlines").is_err());
+    }
+}
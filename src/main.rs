@@ -1,10 +1,34 @@
 mod bitbucket;
+mod browser;
+mod capture;
+mod changes;
+mod clipboard;
 mod config;
+mod credentials;
+mod debug_log;
+mod exit_status;
+mod fuzzy;
+mod git_credentials;
+mod github;
+mod headless;
+mod import;
+mod issues;
+mod keymap;
+mod keyring;
+mod markdown;
+mod provider;
+mod serve;
+mod summary;
 mod tui;
+mod whats_new;
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 use config::{Config, PrStatus};
+use exit_status::{ConfigError, NoResults};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
 #[derive(Debug, Parser)]
 #[command(
@@ -21,24 +45,274 @@ struct Cli {
     api_token: Option<String>,
     #[arg(long)]
     status: Option<PrStatus>,
+    #[arg(long, help = "Fetch PRs from bitbucket or github for this session")]
+    provider: Option<config::Provider>,
     #[arg(long = "base-url")]
     base_url: Option<String>,
     #[arg(long = "auto-refresh-seconds")]
     auto_refresh_seconds: Option<u64>,
+    #[arg(
+        long,
+        value_name = "WORKSPACE/REPO",
+        help = "Watch only this repo for the session, ignoring the saved repo list"
+    )]
+    only: Option<String>,
+    #[arg(
+        long,
+        help = "Include the repo of the current checkout (.myprs file or git remote) for this session"
+    )]
+    local: bool,
+    #[arg(
+        long,
+        value_name = "ID",
+        help = "Open PR <ID> in the browser and exit without starting the TUI"
+    )]
+    open: Option<u64>,
+    #[arg(
+        long = "log-file",
+        value_name = "PATH",
+        num_args = 0..=1,
+        help = "Write a debug trace to PATH (default ~/.config/myprs/myprs.log); also enabled by RUST_LOG"
+    )]
+    log_file: Option<Option<PathBuf>>,
+    #[arg(
+        long = "no-altscreen",
+        help = "Draw the TUI inline instead of switching to the alternate screen"
+    )]
+    no_altscreen: bool,
+    #[arg(
+        long = "force-tui",
+        help = "Start the TUI even when stdout is not a terminal"
+    )]
+    force_tui: bool,
+    #[arg(
+        long,
+        help = "Log how long each refresh takes and which repo was slowest"
+    )]
+    timing: bool,
+    #[arg(
+        long = "read-only",
+        help = "Disable commands that approve or decline PRs for this session"
+    )]
+    read_only: bool,
+    #[arg(
+        long,
+        help = "Print the number of PRs matching the launch status and exit"
+    )]
+    count: bool,
+    #[arg(
+        long = "fail-empty",
+        requires = "count",
+        help = "With --count, exit with status 4 when no PRs match"
+    )]
+    fail_empty: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Print the PR list without starting the TUI: a tab-separated table or one JSON object per line"
+    )]
+    format: Option<headless::OutputFormat>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "replay",
+        help = "Write every raw API response to DIR, for attaching to bug reports"
+    )]
+    record: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Serve API responses from a --record directory instead of the network"
+    )]
+    replay: Option<PathBuf>,
+    #[arg(
+        long = "changes-since-last-run",
+        help = "Print PRs added, updated, or removed since the previous run as JSON and exit"
+    )]
+    changes_since_last_run: bool,
+    #[arg(
+        long,
+        value_name = "PATH_OR_URL",
+        help = "Merge repos (and base URL) from a shared TOML/JSON template into the config and exit"
+    )]
+    import: Option<String>,
+    #[arg(
+        long = "token-to-keyring",
+        help = "Move the API token from config.toml into the OS keyring and exit"
+    )]
+    token_to_keyring: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let mut config = Config::load()?;
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Serve the current PR list as JSON over HTTP instead of starting the TUI
+    Serve {
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(exit_status::code_for(&err))
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let mut cli = Cli::parse();
+    let log_path = match cli.log_file.take() {
+        Some(Some(path)) => Some(path),
+        Some(None) => Some(debug_log::default_path()?),
+        None if std::env::var_os("RUST_LOG").is_some_and(|value| !value.is_empty()) => {
+            Some(debug_log::default_path()?)
+        }
+        None => None,
+    };
+    if let Some(path) = log_path {
+        debug_log::init(&path).context(ConfigError)?;
+    }
+
+    let mut config = Config::load().context(ConfigError)?;
+
+    if let Some(source) = cli.import {
+        let report = import::import(&mut config, &source)?;
+        config.save()?;
+        println!(
+            "Imported {} repo(s), skipped {} already configured.",
+            report.imported, report.skipped
+        );
+        if let Some(base_url) = report.base_url {
+            println!("Base URL set to {base_url}.");
+        }
+        if let Some(base_url) = report.ignored_base_url {
+            println!(
+                "Ignored bitbucket_base_url = \"{base_url}\" from the downloaded template; set it yourself if you trust it."
+            );
+        }
+        return Ok(());
+    }
+
+    // In headless open mode `--repo` narrows the lookup instead of being saved.
+    let open_repos = if cli.open.is_some() {
+        std::mem::take(&mut cli.repos)
+    } else {
+        Vec::new()
+    };
+
+    config
+        .apply_env_and_cli(
+            cli.repos,
+            cli.email,
+            cli.api_token,
+            cli.status,
+            cli.base_url,
+            cli.auto_refresh_seconds,
+        )
+        .context(ConfigError)?;
+
+    if cli.read_only {
+        config.set_session_read_only();
+    }
+
+    if let Some(provider) = cli.provider {
+        config.set_session_provider(provider);
+    }
+
+    if let Some(dir) = cli.record {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create record directory {}", dir.display()))
+            .context(ConfigError)?;
+        config.set_api_capture(capture::ApiCapture::Record(dir));
+    } else if let Some(dir) = cli.replay {
+        if !dir.is_dir() {
+            return Err(
+                anyhow::anyhow!("replay directory {} does not exist", dir.display())
+                    .context(ConfigError),
+            );
+        }
+        config.set_api_capture(capture::ApiCapture::Replay(dir));
+    }
+
+    if let Some(repo) = &cli.only {
+        config.set_only_repo(config::RepoRef::parse(repo).context(ConfigError)?);
+    }
+
+    if cli.local {
+        let cwd = std::env::current_dir()?;
+        for repo in config::detect_local_repos(&cwd).context(ConfigError)? {
+            config.add_session_repo(repo);
+        }
+    }
+
+    config.apply_git_credentials();
+    if let Some((_, api_token)) = config.credentials() {
+        debug_log::redact(api_token);
+    }
+
+    if cli.token_to_keyring {
+        config.move_token_to_keyring()?;
+        config.save()?;
+        println!(
+            "Moved the API token to the OS keyring; config.toml now has bitbucket_api_token = \"{}\".",
+            keyring::MARKER
+        );
+        return Ok(());
+    }
+    if let Some(token) = config.github_token() {
+        debug_log::redact(&token);
+    }
+
+    // The TUI logs this itself; headless output would otherwise hide it.
+    let starts_tui = cli.open.is_none()
+        && !cli.changes_since_last_run
+        && !cli.count
+        && cli.command.is_none()
+        && cli.format.is_none()
+        && (cli.force_tui || std::io::stdout().is_terminal());
+    if config.danger_accept_invalid_certs && !starts_tui {
+        eprintln!("{}", config::INSECURE_TLS_WARNING);
+    }
+
+    if let Some(id) = cli.open {
+        return headless::open_pull_request(&config, &open_repos, id);
+    }
+
+    if cli.changes_since_last_run {
+        return changes::run(&config);
+    }
+
+    if cli.count {
+        let pull_requests = headless::fetch_pull_requests(&config)?;
+        println!("{}", pull_requests.len());
+        if pull_requests.is_empty() && cli.fail_empty {
+            return Err(NoResults("no pull requests match".to_string()).into());
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Serve { port }) = cli.command {
+        return serve::run(&config, port);
+    }
+
+    match cli.format {
+        Some(headless::OutputFormat::Table) => return headless::print_table(&config),
+        Some(headless::OutputFormat::Ndjson) => return headless::print_ndjson(&config),
+        None => {}
+    }
 
-    config.apply_env_and_cli(
-        cli.repos,
-        cli.email,
-        cli.api_token,
-        cli.status,
-        cli.base_url,
-        cli.auto_refresh_seconds,
-    )?;
+    // Piped output gets a plain table instead of TUI escape sequences.
+    if !cli.force_tui && !std::io::stdout().is_terminal() {
+        eprintln!(
+            "stdout is not a terminal; printing a plain PR table (use --force-tui to start the TUI, or --count / --changes-since-last-run)"
+        );
+        return headless::print_table(&config);
+    }
 
-    tui::run_app(config)
+    tui::run_app(config, !cli.no_altscreen, cli.timing)
 }
@@ -0,0 +1,190 @@
+/// Flattens Bitbucket markdown into a single plain-text line of at most
+/// `max_chars` characters: drops code fences, heading/quote/list markers,
+/// emphasis, and inline code ticks, keeps link and image text, and collapses
+/// whitespace. Longer text is cut with an ellipsis.
+pub fn one_line(raw: &str, max_chars: usize) -> String {
+    let text = raw
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("```"))
+        .map(strip_line_marker)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = strip_inline(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let mut cut = text
+        .chars()
+        .take(max_chars.saturating_sub(1))
+        .collect::<String>();
+    cut.truncate(cut.trim_end().len());
+    cut.push('\u{2026}');
+    cut
+}
+
+fn strip_line_marker(line: &str) -> &str {
+    let line = line
+        .trim_start_matches('#')
+        .trim_start_matches('>')
+        .trim_start();
+    for marker in ["- [ ] ", "- [x] ", "- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return rest;
+        }
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0
+        && let Some(rest) = line[digits..].strip_prefix(". ")
+    {
+        return rest;
+    }
+    line
+}
+
+/// Replaces `[text](url)` and `![alt](url)` with their text and drops
+/// paired emphasis and code markers.
+fn strip_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        let (before, tail) = rest.split_at(start);
+        out.push_str(before.strip_suffix('!').unwrap_or(before));
+        let link = tail[1..].split_once("](").and_then(|(label, after)| {
+            let close = after.find(')')?;
+            (!label.contains('[')).then(|| (label, &after[close + 1..]))
+        });
+        match link {
+            Some((label, after)) => {
+                out.push_str(label);
+                rest = after;
+            }
+            None => {
+                if before.ends_with('!') {
+                    out.push('!');
+                }
+                out.push('[');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    strip_code_and_emphasis(&out)
+}
+
+/// Drops the ticks around code spans, keeping their content as written, and
+/// strips emphasis markers elsewhere. An unpaired tick stays.
+fn strip_code_and_emphasis(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        let Some(len) = rest[start + 1..].find('`') else {
+            break;
+        };
+        out.push_str(&strip_emphasis(&rest[..start]));
+        out.push_str(&rest[start + 1..start + 1 + len]);
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(&strip_emphasis(rest));
+    out
+}
+
+fn strip_emphasis(text: &str) -> String {
+    ["**", "__", "*"]
+        .into_iter()
+        .fold(text.to_string(), |text, marker| strip_pairs(&text, marker))
+}
+
+/// Removes `marker` pairs that open before a word and close after one, so
+/// `2 * 3` and `snake_case` stay intact. `__` only counts around more than
+/// one word, which keeps dunder names like `__init__`.
+fn strip_pairs(text: &str, marker: &str) -> String {
+    let opens = |before: Option<char>, after: Option<char>| {
+        before.is_none_or(|c| c.is_whitespace() || "([{\"'".contains(c))
+            && after.is_some_and(|c| !c.is_whitespace() && !marker.starts_with(c))
+    };
+    let closes = |before: Option<char>, after: Option<char>| {
+        before.is_some_and(|c| !c.is_whitespace())
+            && after.is_none_or(|c| c.is_whitespace() || ".,;:!?)]}\"'".contains(c))
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut index = 0;
+    while let Some(found) = text[index..].find(marker) {
+        let open = index + found;
+        let inner = open + marker.len();
+        out.push_str(&text[index..open]);
+        let close = opens(
+            text[..open].chars().next_back(),
+            text[inner..].chars().next(),
+        )
+        .then(|| {
+            text[inner..]
+                .match_indices(marker)
+                .map(|(at, _)| inner + at)
+                .find(|&at| {
+                    closes(
+                        text[..at].chars().next_back(),
+                        text[at + marker.len()..].chars().next(),
+                    )
+                })
+        })
+        .flatten()
+        .filter(|&at| marker != "__" || text[inner..at].contains(char::is_whitespace));
+        match close {
+            Some(close) => {
+                out.push_str(&text[inner..close]);
+                index = close + marker.len();
+            }
+            None => {
+                out.push_str(marker);
+                index = inner;
+            }
+        }
+    }
+    out.push_str(&text[index..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::one_line;
+
+    #[test]
+    fn flattens_markdown_to_one_line() {
+        let raw = "## Looks good\n\n> quoted\n- **Nice** fix, see [docs](https://x.y/z)\n```\nlet a = 1;\n```\n1. run `cargo test`";
+        assert_eq!(
+            one_line(raw, 200),
+            "Looks good quoted Nice fix, see docs let a = 1; run cargo test"
+        );
+        assert_eq!(
+            one_line("![shot](a.png) [wip] done!", 200),
+            "shot [wip] done!"
+        );
+    }
+
+    #[test]
+    fn strips_only_paired_markers() {
+        assert_eq!(
+            one_line(
+                "call `__init__` or __init__, not *a* b*c but __these words__ and `x*y`, 2 * 3",
+                200
+            ),
+            "call __init__ or __init__, not a b*c but these words and x*y, 2 * 3"
+        );
+        assert_eq!(
+            one_line("a stray ` tick and **open", 200),
+            "a stray ` tick and **open"
+        );
+    }
+
+    #[test]
+    fn truncates_with_ellipsis() {
+        assert_eq!(one_line("one two three", 8), "one two\u{2026}");
+        assert_eq!(one_line("short", 8), "short");
+    }
+}
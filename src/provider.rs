@@ -0,0 +1,43 @@
+use crate::bitbucket::{BitbucketClient, PullRequest, UserId};
+use crate::config::{AuthorIdentifier, Config, PrStatus, Provider};
+use crate::headless;
+use anyhow::{Context, Result};
+
+/// A PR host that can list the current user's pull requests across repos.
+/// `Send` so the TUI can refresh from a worker thread.
+pub trait PrProvider: Send {
+    /// The signed-in user, as the host identifies PR authors.
+    fn current_user(&self) -> Result<UserId>;
+
+    /// PRs opened by `user` with `status`, most recently updated first.
+    fn list_pull_requests_created_by(
+        &self,
+        user: &UserId,
+        status: PrStatus,
+    ) -> Result<Vec<PullRequest>>;
+}
+
+impl PrProvider for BitbucketClient {
+    fn current_user(&self) -> Result<UserId> {
+        self.current_user_ids(AuthorIdentifier::default())?
+            .into_iter()
+            .next()
+            .context("Bitbucket returned no identifier for the current user")
+    }
+
+    fn list_pull_requests_created_by(
+        &self,
+        user: &UserId,
+        status: PrStatus,
+    ) -> Result<Vec<PullRequest>> {
+        self.list_my_pull_requests(user, status, None, |_, _| {})
+    }
+}
+
+/// Client for the provider selected by `provider` or `--provider`.
+pub fn from_config(config: &Config) -> Result<Box<dyn PrProvider>> {
+    match config.provider() {
+        Provider::Bitbucket => Ok(Box::new(headless::client_from_config(config)?)),
+        Provider::Github => Ok(Box::new(headless::github_client_from_config(config)?)),
+    }
+}
@@ -0,0 +1,137 @@
+use crate::config::Config;
+use crate::headless;
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+/// Serves the current PR list as JSON on `GET /` (or `/pulls`) until killed.
+/// Fetched lists are reused for `auto_refresh_seconds` so frequent polling by
+/// a dashboard doesn't turn into one Bitbucket round trip per request.
+pub fn run(config: &Config, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("failed to listen on 127.0.0.1:{port}"))?;
+    println!("Serving PRs as JSON on http://127.0.0.1:{port}/pulls");
+
+    let ttl = Duration::from_secs(config.auto_refresh_seconds());
+    let mut cache: Option<(Instant, String)> = None;
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(err) = handle_connection(stream, config, ttl, &mut cache) {
+            eprintln!("request failed: {err:#}");
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct PullRequestsBody<'a> {
+    fetched_at: String,
+    pull_requests: &'a [crate::bitbucket::PullRequest],
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    config: &Config,
+    ttl: Duration,
+    cache: &mut Option<(Instant, String)>,
+) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; the body of a GET is ignored.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = match route(&request_line) {
+        Route::PullRequests => match cached_pull_requests(config, ttl, cache) {
+            Ok(body) => ("200 OK", body),
+            Err(err) => ("502 Bad Gateway", error_body(&format!("{err:#}"))),
+        },
+        Route::Health => ("200 OK", "{\"status\":\"ok\"}".to_string()),
+        Route::MethodNotAllowed => (
+            "405 Method Not Allowed",
+            error_body("only GET is supported"),
+        ),
+        Route::NotFound => ("404 Not Found", error_body("not found; try GET /pulls")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn cached_pull_requests(
+    config: &Config,
+    ttl: Duration,
+    cache: &mut Option<(Instant, String)>,
+) -> Result<String> {
+    if let Some((fetched_at, body)) = cache.as_ref()
+        && fetched_at.elapsed() < ttl
+    {
+        return Ok(body.clone());
+    }
+
+    let pull_requests = headless::fetch_pull_requests(config)?;
+    let body = serde_json::to_string(&PullRequestsBody {
+        fetched_at: Local::now().to_rfc3339(),
+        pull_requests: &pull_requests,
+    })?;
+    *cache = Some((Instant::now(), body.clone()));
+    Ok(body)
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Route {
+    PullRequests,
+    Health,
+    MethodNotAllowed,
+    NotFound,
+}
+
+fn route(request_line: &str) -> Route {
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Route::NotFound;
+    };
+    if method != "GET" {
+        return Route::MethodNotAllowed;
+    }
+
+    let path = target.split('?').next().unwrap_or_default();
+    match path.trim_end_matches('/') {
+        "" | "/pulls" => Route::PullRequests,
+        "/health" => Route::Health,
+        _ => Route::NotFound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Route, route};
+
+    #[test]
+    fn routes_requests_by_method_and_path() {
+        assert_eq!(route("GET / HTTP/1.1\r\n"), Route::PullRequests);
+        assert_eq!(route("GET /pulls?x=1 HTTP/1.1\r\n"), Route::PullRequests);
+        assert_eq!(route("GET /health HTTP/1.1\r\n"), Route::Health);
+        assert_eq!(route("POST /pulls HTTP/1.1\r\n"), Route::MethodNotAllowed);
+        assert_eq!(route("GET /nope HTTP/1.1\r\n"), Route::NotFound);
+        assert_eq!(route(""), Route::NotFound);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::bitbucket::PullRequest;
+use std::collections::BTreeMap;
+
+/// Standup digest over a set of pull requests.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub total: usize,
+    pub by_repo: BTreeMap<String, usize>,
+    pub by_state: BTreeMap<String, usize>,
+    pub approved: usize,
+    pub oldest_open: Option<OldestOpen>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct OldestOpen {
+    pub repo: String,
+    pub id: u64,
+    pub title: String,
+    pub created_on: String,
+}
+
+pub fn summarize(pull_requests: &[PullRequest]) -> Summary {
+    let mut summary = Summary {
+        total: pull_requests.len(),
+        ..Summary::default()
+    };
+
+    for pr in pull_requests {
+        *summary
+            .by_repo
+            .entry(format!("{}/{}", pr.workspace, pr.repo))
+            .or_insert(0) += 1;
+        *summary.by_state.entry(pr.state.clone()).or_insert(0) += 1;
+        if pr.approvals() > 0 {
+            summary.approved += 1;
+        }
+    }
+
+    // Bitbucket timestamps share one ISO-8601 format, so they sort as strings.
+    summary.oldest_open = pull_requests
+        .iter()
+        .filter(|pr| pr.state == "OPEN" && !pr.created_on.is_empty())
+        .min_by(|left, right| left.created_on.cmp(&right.created_on))
+        .map(|pr| OldestOpen {
+            repo: format!("{}/{}", pr.workspace, pr.repo),
+            id: pr.id,
+            title: pr.title.clone(),
+            created_on: pr.created_on.clone(),
+        });
+
+    summary
+}
+
+impl Summary {
+    pub fn lines(&self) -> Vec<String> {
+        let join_counts = |counts: &BTreeMap<String, usize>| {
+            counts
+                .iter()
+                .map(|(key, count)| format!("{key} {count}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut lines = vec![format!(
+            "Summary: {} PR(s) across {} repo(s), {} approved.",
+            self.total,
+            self.by_repo.len(),
+            self.approved
+        )];
+        if self.total == 0 {
+            return lines;
+        }
+
+        lines.push(format!("By repo: {}", join_counts(&self.by_repo)));
+        lines.push(format!("By state: {}", join_counts(&self.by_state)));
+        if let Some(oldest) = &self.oldest_open {
+            let created = oldest.created_on.get(..10).unwrap_or(&oldest.created_on);
+            lines.push(format!(
+                "Oldest open: {} #{} {} (created {created})",
+                oldest.repo, oldest.id, oldest.title
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::summarize;
+    use crate::bitbucket::{Participant, PullRequest};
+
+    fn pull_request(
+        repo: &str,
+        id: u64,
+        state: &str,
+        created_on: &str,
+        approved: bool,
+    ) -> PullRequest {
+        PullRequest {
+            state: state.to_string(),
+            created_on: created_on.to_string(),
+            updated_on: created_on.to_string(),
+            participants: vec![Participant {
+                uuid: Some("{bob}".to_string()),
+                account_id: None,
+                nickname: None,
+                display_name: "Bob".to_string(),
+                role: "REVIEWER".to_string(),
+                approved,
+            }],
+            ..PullRequest::fixture(repo, id)
+        }
+    }
+
+    #[test]
+    fn summarizes_counts_and_oldest_open() {
+        let summary = summarize(&[
+            pull_request("api", 1, "OPEN", "2026-03-01T10:00:00+00:00", true),
+            pull_request("api", 2, "MERGED", "2025-01-01T10:00:00+00:00", true),
+            pull_request("web", 3, "OPEN", "2026-02-01T10:00:00+00:00", false),
+        ]);
+
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.by_repo.get("team/api"), Some(&2));
+        assert_eq!(summary.by_repo.get("team/web"), Some(&1));
+        assert_eq!(summary.by_state.get("OPEN"), Some(&2));
+        assert_eq!(summary.approved, 2);
+        let oldest = summary.oldest_open.expect("oldest open PR");
+        assert_eq!((oldest.repo.as_str(), oldest.id), ("team/web", 3));
+    }
+
+    #[test]
+    fn summarizes_empty_list() {
+        let summary = summarize(&[]);
+        assert_eq!(summary.total, 0);
+        assert!(summary.oldest_open.is_none());
+        assert_eq!(summary.lines().len(), 1);
+    }
+}
@@ -1,20 +1,51 @@
-use crate::bitbucket::{BitbucketClient, PullRequest};
-use crate::config::{Config, PrStatus, RepoRef};
-use anyhow::{Result, anyhow};
+use crate::bitbucket::{
+    BitbucketClient, Comment, CommitSummary, DiffStat, Divergence, Endpoints, PullRequest,
+    PullRequestQuery, Relation, RequiredReviewers, UserId, http_status, is_connection_error,
+    validate_custom_query,
+};
+use crate::browser;
+use crate::capture;
+use crate::clipboard;
+use crate::config::{
+    Config, FetchStrategy, INSECURE_TLS_WARNING, ListSpacing, PrStatus, Provider, RepoRef,
+    SearchScope, SizeCategory,
+};
+use crate::credentials::CredentialSource;
+use crate::debug_log;
+use crate::fuzzy;
+use crate::headless::{retain_watched, sort_for_list};
+use crate::issues;
+use crate::keymap::{Action, Keymap};
+use crate::markdown;
+use crate::provider::{self, PrProvider};
+use crate::summary::summarize;
+use crate::whats_new;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Local, Utc};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
-use crossterm::execute;
 use crossterm::terminal::{
-    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+    self, EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode, enable_raw_mode,
 };
-use ratatui::Terminal;
+use crossterm::{cursor, execute};
 use ratatui::backend::CrosstermBackend;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::layout::{Constraint, Direction, Layout, Margin, Rect};
 use ratatui::style::{Color, Modifier, Style};
-use ratatui::text::{Line, Text};
-use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Wrap,
+};
+use ratatui::{Terminal, TerminalOptions, Viewport};
+use regex::Regex;
+use reqwest::StatusCode;
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, mpsc};
+use std::thread;
 use std::time::{Duration, Instant};
 
 #[derive(Clone, Copy)]
@@ -22,83 +53,551 @@ struct CommandSpec {
     name: &'static str,
     usage: &'static str,
     accepts_args: bool,
+    /// Changes PRs on Bitbucket; disabled in read-only mode.
+    writes: bool,
+    /// Needs the Bitbucket API or Bitbucket URLs; disabled with GitHub.
+    bitbucket_only: bool,
 }
 
-const COMMAND_SPECS: [CommandSpec; 7] = [
+const COMMAND_SPECS: [CommandSpec; 42] = [
     CommandSpec {
         name: "/help",
         usage: "show available commands",
         accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
     },
     CommandSpec {
         name: "/repo",
         usage: "add/rm repository entries",
         accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
     },
     CommandSpec {
         name: "/repos",
-        usage: "list configured repositories",
-        accepts_args: false,
+        usage: "list or sort configured repositories",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/workspace",
+        usage: "watch/unwatch all repos in a workspace",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/project",
+        usage: "watch/unwatch all repos in a project",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: true,
     },
     CommandSpec {
         name: "/status",
         usage: "set status filter",
         accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/relation",
+        usage: "show authored, reviewing, or both",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/q",
+        usage: "add a raw Bitbucket query (replaces /status)",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: true,
     },
     CommandSpec {
         name: "/refresh",
         usage: "reload pull requests",
         accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/retry-failed",
+        usage: "re-fetch only the repos that failed in the last refresh",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/approve",
+        usage: "approve or unapprove the selected PR",
+        accepts_args: false,
+        writes: true,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/reopen",
+        usage: "explain how to bring back the selected declined PR",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/approved",
+        usage: "filter PRs by approval count",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/waiting",
+        usage: "show unapproved PRs waiting over N days",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/size",
+        usage: "show PRs of one size (xs|s|m|l|xl) after /stats-all",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/solo",
+        usage: "show PRs nobody else has approved or commented on",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/mainline",
+        usage: "show PRs targeting the default branch",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/comment",
+        usage: "show the selected PR's latest comment",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/mark-all-read",
+        usage: "clear the dimmed visited markers",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/commits",
+        usage: "show the selected PR's commit count and latest commit",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/required",
+        usage: "list required reviewers yet to approve",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/stale",
+        usage: "list old open PRs and offer to decline them",
+        accepts_args: true,
+        writes: true,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/focus",
+        usage: "toggle hiding the log and input panes",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/density",
+        usage: "toggle compact/comfortable list spacing",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/issue",
+        usage: "open the selected PR's linked issue",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/export-md",
+        usage: "export the list as Markdown to a file or the clipboard",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/pin",
+        usage: "pin the selected PR to the top",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/unpin",
+        usage: "unpin the selected PR",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/pipeline",
+        usage: "open the selected PR's build page",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/behind",
+        usage: "show how far the selected PR's branch is behind its destination",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/open-repo",
+        usage: "open the selected PR's repo pull request list",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/standup",
+        usage: "copy a standup line for the selected PR",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/copy",
+        usage: "copy the selected PR's id, branch, or url",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/diff",
+        usage: "show the selected PR's diff",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/file",
+        usage: "show the diff of one file changed by the selected PR",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/stats-all",
+        usage: "fetch diff sizes for all listed PRs",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: true,
+    },
+    CommandSpec {
+        name: "/summary",
+        usage: "log a digest of loaded PRs",
+        accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
     },
     CommandSpec {
         name: "/search",
         usage: "filter PRs by number or text",
         accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/search-scope",
+        usage: "search titles only or titles and descriptions",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/find",
+        usage: "search PRs across all states",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
+    },
+    CommandSpec {
+        name: "/limit",
+        usage: "cap PRs fetched per repo",
+        accepts_args: true,
+        writes: false,
+        bitbucket_only: false,
     },
     CommandSpec {
         name: "/quit",
         usage: "exit the app",
         accepts_args: false,
+        writes: false,
+        bitbucket_only: false,
     },
 ];
+
+const READ_ONLY_NOTICE: &str = "read-only mode: action disabled";
+
+fn is_write_command(name: &str) -> bool {
+    COMMAND_SPECS
+        .iter()
+        .any(|spec| spec.name == name && spec.writes)
+}
+
+fn is_bitbucket_only_command(name: &str) -> bool {
+    COMMAND_SPECS
+        .iter()
+        .any(|spec| spec.name == name && spec.bitbucket_only)
+}
+
+/// Drops write commands from the comma-separated `/help` list in read-only
+/// mode.
+fn visible_commands(help: &str, read_only: bool) -> String {
+    help.split(", ")
+        .filter(|entry| {
+            !read_only || !is_write_command(entry.split_whitespace().next().unwrap_or_default())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Fills the `{id}`, `{title}`, `{repo}`, and `{url}` placeholders of a
+/// `standup_template`.
+fn standup_line(template: &str, pr: &PullRequest) -> String {
+    template
+        .replace("{id}", &pr.id.to_string())
+        .replace("{title}", &pr.title)
+        .replace("{repo}", &format!("{}/{}", pr.workspace, pr.repo))
+        .replace("{url}", &pr.url)
+}
+
+/// One lowercase search term. PRs must contain every term and none of the
+/// `exclude`d ones.
+#[derive(Debug, PartialEq, Eq)]
+struct SearchTerm {
+    text: String,
+    exclude: bool,
+}
+
+/// Splits a search query into terms. `"double quotes"` keep a phrase
+/// together as one exact term; an unclosed quote runs to the end of the
+/// query. A leading `-` (also before a quote) excludes the term.
+fn search_terms(query: &str) -> Vec<SearchTerm> {
+    let mut terms = Vec::new();
+    let mut rest = query.trim();
+    while !rest.is_empty() {
+        let negated = rest
+            .strip_prefix('-')
+            .filter(|tail| tail.starts_with(|c: char| !c.is_whitespace()));
+        let exclude = negated.is_some();
+        let unsigned = negated.unwrap_or(rest);
+        let (term, tail) = match unsigned.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => unsigned
+                .split_once(char::is_whitespace)
+                .unwrap_or((unsigned, "")),
+        };
+        if !term.trim().is_empty() {
+            terms.push(SearchTerm {
+                text: term.trim().to_ascii_lowercase(),
+                exclude,
+            });
+        }
+        rest = tail.trim_start();
+    }
+    terms
+}
+
 const MAX_LOGGED_UPDATES: usize = 6;
+const REPO_PALETTE: [Color; 8] = [
+    Color::Cyan,
+    Color::Green,
+    Color::Magenta,
+    Color::Blue,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightCyan,
+    Color::LightMagenta,
+];
+const WORKSPACE_EXPANSION_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_OFFLINE_BACKOFF: Duration = Duration::from_secs(15 * 60);
+const LIVE_SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+const STATS_WORKERS: usize = 4;
+/// Repos fetched at once during a refresh.
+const FETCH_WORKERS: usize = 8;
+const STATS_CONFIRM_THRESHOLD: usize = 20;
+const COMMENT_PREVIEW_CHARS: usize = 120;
+/// API calls listed in a confirmation dialog before the rest are summarized.
+const MAX_PREVIEW_CALLS: usize = 5;
+const PINNED_GROUP: &str = "Pinned";
 
-pub fn run_app(config: Config) -> Result<()> {
+/// Set while the alternate screen is active, so restoring the terminal
+/// (possibly from the panic hook) only leaves a screen that was entered.
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+/// Set once the original window title was pushed onto the terminal's title
+/// stack, so it is popped back on exit.
+static TERMINAL_TITLE: AtomicBool = AtomicBool::new(false);
+/// xterm window-title stack push/pop (XTWINOPS 22/23); ignored by
+/// terminals that lack it.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+/// Runs the TUI on the alternate screen, or inline in the main screen when
+/// `alternate_screen` is false or the terminal rejects switching screens.
+pub fn run_app(config: Config, alternate_screen: bool, timing: bool) -> Result<()> {
     enable_raw_mode()?;
+    let _guard = TerminalGuard::install();
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    let mut notice = None;
+    if alternate_screen {
+        match execute!(stdout, EnterAlternateScreen) {
+            Ok(()) => ALTERNATE_SCREEN.store(true, Ordering::Relaxed),
+            Err(err) => {
+                notice = Some(format!(
+                    "Alternate screen unavailable ({err}); running inline."
+                ));
+            }
+        }
+    }
 
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut terminal = if ALTERNATE_SCREEN.load(Ordering::Relaxed) {
+        Terminal::new(backend)?
+    } else {
+        let (_, rows) = terminal::size()?;
+        Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Inline(rows),
+            },
+        )?
+    };
     terminal.clear()?;
 
-    let result = run_event_loop(&mut terminal, config);
+    let result = run_event_loop(&mut terminal, config, notice, timing);
+    if !ALTERNATE_SCREEN.load(Ordering::Relaxed) {
+        // Leave the shell prompt on a clean area instead of the last frame.
+        terminal.clear()?;
+    }
+    result
+}
+
+/// Restores the terminal (raw mode off, main screen, visible cursor) when
+/// dropped, including while unwinding from a panic. A panic hook restores it
+/// before the panic message is printed, so the message stays readable; the
+/// hook that was installed before is put back on drop.
+struct TerminalGuard {
+    previous_hook: Arc<PanicHook>,
+}
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+type PanicHook = Box<dyn Fn(&std::panic::PanicHookInfo<'_>) + Send + Sync + 'static>;
 
-    result
+impl TerminalGuard {
+    fn install() -> Self {
+        let previous_hook = Arc::new(std::panic::take_hook());
+        let chained = Arc::clone(&previous_hook);
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            chained(info);
+        }));
+        Self { previous_hook }
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+        // The hook cannot be swapped while unwinding; the process is on its
+        // way out then anyway.
+        if !std::thread::panicking() {
+            let previous_hook = Arc::clone(&self.previous_hook);
+            std::panic::set_hook(Box::new(move |info| previous_hook(info)));
+        }
+    }
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    if ALTERNATE_SCREEN.swap(false, Ordering::Relaxed) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+    if TERMINAL_TITLE.swap(false, Ordering::Relaxed) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "{POP_TITLE}").and_then(|()| stdout.flush());
+    }
+    let _ = execute!(io::stdout(), cursor::Show);
 }
 
 fn run_event_loop(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     config: Config,
+    notice: Option<String>,
+    timing: bool,
 ) -> Result<()> {
     let mut app = App::new(config);
+    app.timing = timing;
     app.log("Type /help for commands.");
-    app.refresh_pull_requests(false);
+    app.announce_new_version(env!("CARGO_PKG_VERSION"));
+    if let Some(notice) = notice {
+        app.log(&notice);
+    }
+    if app.config.danger_accept_invalid_certs {
+        app.log(INSECURE_TLS_WARNING);
+    }
+    if app.config.set_terminal_title {
+        let mut stdout = io::stdout();
+        write!(stdout, "{PUSH_TITLE}")?;
+        stdout.flush()?;
+        TERMINAL_TITLE.store(true, Ordering::Relaxed);
+    }
+    // No refresh yet: the first pass through the loop paints the empty UI
+    // with the tips above, then `refresh_pull_requests_if_due` starts the
+    // first refresh on a worker thread.
+    if app.config.has_repo_sources() {
+        app.log("Loading pull requests...");
+    }
+    let mut shown_title = None;
 
     loop {
+        if TERMINAL_TITLE.load(Ordering::Relaxed) {
+            let title = app.terminal_title();
+            if shown_title.as_ref() != Some(&title) {
+                execute!(io::stdout(), SetTitle(&title))?;
+                shown_title = Some(title);
+            }
+        }
         terminal.draw(|frame| app.draw(frame))?;
 
-        if event::poll(Duration::from_millis(200))?
+        if event::poll(app.poll_timeout())?
             && let Event::Key(key) = event::read()?
             && key.kind == KeyEventKind::Press
         {
             app.handle_key(key)?;
         }
 
+        app.apply_live_search_if_due(Instant::now());
+        app.poll_stats_job();
+        app.poll_refresh_job();
         app.refresh_pull_requests_if_due();
 
         if app.should_quit {
@@ -109,142 +608,919 @@ fn run_event_loop(
     Ok(())
 }
 
-struct App {
-    config: Config,
-    status_filter: PrStatus,
-    input: String,
-    logs: Vec<String>,
-    pull_requests: Vec<PullRequest>,
-    all_pull_requests: Vec<PullRequest>,
-    search_query: Option<String>,
-    selected_index: usize,
-    command_suggestion_index: usize,
-    auto_refresh_interval: Duration,
-    last_refresh_at: Option<Instant>,
-    should_quit: bool,
+struct ListRow {
+    text: String,
+    is_header: bool,
+    /// Blank or separator line between repo groups; never selectable.
+    is_spacer: bool,
+    /// Fetch health shown after a repo group header, when known.
+    health: Option<HealthBadge>,
+    repo_key: String,
+    author: Option<String>,
+    /// A PR the user authored, listed among ones they review.
+    own: bool,
+    /// A PR the user opened or inspected since its last activity.
+    visited: bool,
+    /// Size badge, once the PR's diffstat is known.
+    size: Option<SizeCategory>,
 }
 
-impl App {
-    fn new(config: Config) -> Self {
-        let status_filter = config.status();
-        let auto_refresh_interval = Duration::from_secs(config.auto_refresh_seconds());
+impl ListRow {
+    fn spacer(text: String) -> Self {
         Self {
-            config,
-            status_filter,
-            input: String::new(),
-            logs: Vec::new(),
-            pull_requests: Vec::new(),
-            all_pull_requests: Vec::new(),
-            search_query: None,
-            selected_index: 0,
-            command_suggestion_index: 0,
-            auto_refresh_interval,
-            last_refresh_at: None,
-            should_quit: false,
+            text,
+            is_header: false,
+            is_spacer: true,
+            health: None,
+            repo_key: String::new(),
+            author: None,
+            own: false,
+            visited: false,
+            size: None,
         }
     }
 
-    fn draw(&self, frame: &mut ratatui::Frame) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Min(6),
-                Constraint::Length(8),
-                Constraint::Length(3),
-            ])
-            .split(frame.area());
+    fn header(text: String, repo_key: String, health: Option<HealthBadge>) -> Self {
+        Self {
+            text,
+            is_header: true,
+            is_spacer: false,
+            health,
+            repo_key,
+            author: None,
+            own: false,
+            visited: false,
+            size: None,
+        }
+    }
+}
 
-        let auth_status = if self.config.credentials().is_some() {
-            "configured"
-        } else {
-            "missing"
-        };
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HealthBadge {
+    Ok,
+    /// Local time (`HH:MM`) of the latest failed fetch.
+    Failed(String),
+}
 
-        let header = Paragraph::new(Text::from(vec![
-            Line::from("myprs - Bitbucket PR TUI"),
-            Line::from(format!(
-                "Repos: {} | Status: {} | API token auth: {} | Auto refresh: {}s",
-                self.config.repos().len(),
-                self.status_filter,
-                auth_status,
-                self.auto_refresh_interval.as_secs()
-            )),
-        ]))
-        .block(Block::default().borders(Borders::ALL).title("Status"));
-        frame.render_widget(header, chunks[0]);
+/// Outcome of the latest per-repo fetches, kept across refreshes so that
+/// repos failing repeatedly stand out.
+#[derive(Debug, Default, Clone)]
+struct RepoHealth {
+    last_success: Option<DateTime<Local>>,
+    last_error: Option<(DateTime<Local>, String)>,
+}
 
-        let list_title = match &self.search_query {
-            Some(query) => format!(
-                "My Pull Requests ({}) | Search: {}",
-                self.status_filter, query
-            ),
-            None => format!("My Pull Requests ({})", self.status_filter),
-        };
-        let list_block = Block::default().borders(Borders::ALL).title(list_title);
-        if self.pull_requests.is_empty() {
-            let empty_state = if let Some(query) = &self.search_query {
-                format!("No PRs match search '{query}'. Use /search clear to reset.")
-            } else {
-                "No pull requests loaded. Configure credentials, add repos, then run /refresh."
-                    .to_string()
-            };
-            frame.render_widget(Paragraph::new(empty_state).block(list_block), chunks[1]);
+impl RepoHealth {
+    fn is_failing(&self) -> bool {
+        match (&self.last_success, &self.last_error) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some(success), Some((failed, _))) => failed > success,
+        }
+    }
+
+    fn badge(&self) -> Option<HealthBadge> {
+        if self.is_failing() {
+            let (failed, _) = self.last_error.as_ref()?;
+            Some(HealthBadge::Failed(failed.format("%H:%M").to_string()))
         } else {
-            let (rows, selected_row) = self.grouped_rows();
-            let items = rows
-                .into_iter()
-                .map(|(text, is_header)| {
-                    if is_header {
-                        ListItem::new(text).style(Style::default().add_modifier(Modifier::BOLD))
-                    } else {
-                        ListItem::new(text)
-                    }
-                })
-                .collect::<Vec<_>>();
+            self.last_success.map(|_| HealthBadge::Ok)
+        }
+    }
+}
 
-            let list = List::new(items)
-                .block(list_block)
-                .highlight_style(
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .bg(Color::Black)
-                        .add_modifier(Modifier::BOLD),
-                )
-                .highlight_symbol("> ");
+/// Narrows the list by how many approvals a PR has (`/approved <n|none>`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ApprovalFilter {
+    AtLeast(usize),
+    Unapproved,
+}
 
-            let mut state = ListState::default();
-            state.select(selected_row);
-            frame.render_stateful_widget(list, chunks[1], &mut state);
+impl ApprovalFilter {
+    fn matches(self, pr: &PullRequest) -> bool {
+        match self {
+            Self::AtLeast(threshold) => pr.approvals() >= threshold,
+            Self::Unapproved => pr.approvals() == 0,
         }
+    }
+}
 
-        let log_lines = self
-            .logs
-            .iter()
-            .rev()
-            .take(6)
-            .cloned()
-            .collect::<Vec<_>>()
-            .into_iter()
-            .rev()
-            .collect::<Vec<_>>();
-        frame.render_widget(
-            Paragraph::new(log_lines.join("\n"))
-                .block(Block::default().borders(Borders::ALL).title("Log")),
-            chunks[2],
-        );
-
-        let input = Paragraph::new(self.input.as_str()).block(
+impl std::fmt::Display for ApprovalFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AtLeast(threshold) => write!(f, ">= {threshold}"),
+            Self::Unapproved => write!(f, "none"),
+        }
+    }
+}
+
+/// A write operation waiting for the user to confirm it with `y`.
+enum PendingAction {
+    Approval {
+        workspace: String,
+        repo: String,
+        id: u64,
+        approve: bool,
+    },
+    RemoveRepos {
+        repos: Vec<RepoRef>,
+        inaccessible: bool,
+    },
+    FetchStats {
+        targets: Vec<BatchTarget>,
+    },
+    DeclineStale {
+        targets: Vec<BatchTarget>,
+    },
+    /// Quitting while a confirmation is open or background work runs. The
+    /// interrupted confirmation comes back if the quit is cancelled.
+    Quit {
+        reason: String,
+        resume: Option<Box<PendingAction>>,
+    },
+}
+
+impl PendingAction {
+    /// Whether confirming the action changes PRs on Bitbucket.
+    fn writes(&self) -> bool {
+        matches!(self, Self::Approval { .. } | Self::DeclineStale { .. })
+    }
+
+    fn prompt(&self) -> String {
+        match self {
+            Self::Approval {
+                workspace,
+                repo,
+                id,
+                approve: true,
+            } => format!("Approve {workspace}/{repo} PR #{id}?"),
+            Self::Approval {
+                workspace,
+                repo,
+                id,
+                approve: false,
+            } => format!("Remove your approval from {workspace}/{repo} PR #{id}?"),
+            Self::RemoveRepos {
+                repos,
+                inaccessible,
+            } => format!(
+                "Remove {}repo(s) from config: {}?",
+                if *inaccessible { "inaccessible " } else { "" },
+                repos
+                    .iter()
+                    .map(RepoRef::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::FetchStats { targets } => format!(
+                "Fetch diffstats for {} PRs ({} API calls or more)?",
+                targets.len(),
+                targets.len()
+            ),
+            Self::DeclineStale { targets } => format!(
+                "Decline {} stale PR(s): {}?",
+                targets.len(),
+                targets
+                    .iter()
+                    .map(|target| format!("#{}", target.id))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Quit { reason, .. } => format!("Quit anyway? {reason}."),
+        }
+    }
+
+    /// The Bitbucket calls confirming this action will make, as
+    /// `METHOD url` lines. Empty for actions that only touch local state.
+    fn api_calls(&self, endpoints: &Endpoints) -> Vec<String> {
+        match self {
+            Self::Approval {
+                workspace,
+                repo,
+                id,
+                approve,
+            } => vec![format!(
+                "{} {}",
+                if *approve { "POST" } else { "DELETE" },
+                endpoints.pull_request_resource(workspace, repo, *id, "approve")
+            )],
+            Self::DeclineStale { targets } => targets
+                .iter()
+                .map(|target| {
+                    format!(
+                        "POST {}",
+                        endpoints.pull_request_resource(
+                            &target.workspace,
+                            &target.repo,
+                            target.id,
+                            "decline"
+                        )
+                    )
+                })
+                .collect(),
+            Self::RemoveRepos { .. } | Self::FetchStats { .. } | Self::Quit { .. } => Vec::new(),
+        }
+    }
+}
+
+/// "Refresh completed in 3.2s (slowest: team/app 1.8s of 4 repo(s))". The
+/// slowest repo is only known for per-repo fetches.
+fn timing_summary(total: Duration, repo_timings: &[(String, Duration)]) -> String {
+    let mut summary = format!("Refresh completed in {:.1}s", total.as_secs_f64());
+    if let Some((repo, elapsed)) = repo_timings.iter().max_by_key(|(_, elapsed)| *elapsed) {
+        summary.push_str(&format!(
+            " (slowest: {repo} {:.1}s of {} repo(s))",
+            elapsed.as_secs_f64(),
+            repo_timings.len()
+        ));
+    }
+    summary
+}
+
+/// A PR acted on by a batch command (`/stats-all`, `/stale`).
+#[derive(Debug, Clone)]
+struct BatchTarget {
+    workspace: String,
+    repo: String,
+    id: u64,
+}
+
+enum StatsMessage {
+    Fetched(String, DiffStat),
+    Failed(String, String),
+    RateLimited,
+}
+
+/// Diffstats being fetched in the background by `/stats-all`; results are
+/// drained from `receiver` by the event loop.
+struct StatsJob {
+    receiver: mpsc::Receiver<StatsMessage>,
+    total: usize,
+    done: usize,
+    failed: usize,
+    rate_limited: bool,
+}
+
+impl StatsJob {
+    fn progress_label(&self) -> String {
+        const WIDTH: usize = 10;
+        let filled = (self.done * WIDTH).checked_div(self.total).unwrap_or(WIDTH);
+        format!(
+            "Diffstat [{}{}] {}/{}",
+            "#".repeat(filled),
+            "-".repeat(WIDTH - filled),
+            self.done,
+            self.total
+        )
+    }
+}
+
+/// Key actions offered in the command palette next to the commands.
+const PALETTE_ACTIONS: [(Action, &str); 9] = [
+    (Action::Refresh, "Refresh now"),
+    (Action::Open, "Open selected PR in browser"),
+    (Action::Detail, "Show selected PR's details"),
+    (Action::CopyId, "Copy selected PR id"),
+    (Action::Pipeline, "Open selected PR's pipeline"),
+    (Action::OpenRepo, "Open selected PR's repo"),
+    (Action::Focus, "Toggle focus mode"),
+    (Action::Collapse, "Collapse or expand selected group"),
+    (Action::Quit, "Quit"),
+];
+const MAX_PALETTE_ROWS: usize = 10;
+
+#[derive(Clone, Copy)]
+enum PaletteEntry {
+    Command(CommandSpec),
+    Action(Action, &'static str),
+}
+
+impl PaletteEntry {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Command(spec) => spec.name,
+            Self::Action(_, label) => label,
+        }
+    }
+
+    fn detail(&self) -> &'static str {
+        match self {
+            Self::Command(spec) => spec.usage,
+            Self::Action(..) => "key action",
+        }
+    }
+}
+
+/// Fuzzy-searchable list of commands and key actions opened with Ctrl+P.
+#[derive(Debug, Default)]
+struct CommandPalette {
+    query: String,
+    selected: usize,
+}
+
+/// Scrollable overlay with the full description of the PR it was opened on.
+struct DetailView {
+    pull_request: PullRequest,
+    /// Fetched when the overlay opens; `Err` says why it couldn't be.
+    latest_comment: Result<Option<Comment>, String>,
+    /// Required reviewers who still have to approve.
+    pending_reviewers: Vec<String>,
+    scroll: OverlayScroll,
+}
+
+/// Scroll position of a text popup. Each draw records how far the wrapped
+/// text can scroll and how many rows fit, so keys can page and clamp without
+/// wrapping it again.
+#[derive(Default)]
+struct OverlayScroll {
+    offset: u16,
+    max: Cell<u16>,
+    page: Cell<u16>,
+}
+
+impl OverlayScroll {
+    /// Moves a line with Up/Down, a page with PgUp/PgDn, and to either end
+    /// with Home/End. Returns false for other keys.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        let max = self.max.get();
+        let page = self.page.get().max(1);
+        let offset = self.offset.min(max);
+        self.offset = match code {
+            KeyCode::Up => offset.saturating_sub(1),
+            KeyCode::Down => offset.saturating_add(1).min(max),
+            KeyCode::PageUp => offset.saturating_sub(page),
+            KeyCode::PageDown => offset.saturating_add(page).min(max),
+            KeyCode::Home => 0,
+            KeyCode::End => max,
+            _ => return false,
+        };
+        true
+    }
+
+    /// Records the scroll range of `paragraph` drawn in the bordered `area`
+    /// and returns the offset to draw it at, with that position as a
+    /// percentage.
+    fn layout(&self, paragraph: &Paragraph, area: Rect) -> (u16, u32) {
+        let page = area.height.saturating_sub(2);
+        let wrapped = paragraph.line_count(area.width.saturating_sub(2));
+        let max = wrapped
+            .saturating_sub(usize::from(page))
+            .min(usize::from(u16::MAX)) as u16;
+        self.max.set(max);
+        self.page.set(page);
+        let offset = self.offset.min(max);
+        let percent = if max == 0 {
+            100
+        } else {
+            u32::from(offset) * 100 / u32::from(max)
+        };
+        (offset, percent)
+    }
+}
+
+/// Popup showing a pull request's diff, opened by `/diff`, or one file's
+/// diff, opened by `/file`.
+struct DiffView {
+    pull_request: PullRequest,
+    /// The file `/file` showed; `None` for the whole diff.
+    path: Option<String>,
+    diff: String,
+    scroll: OverlayScroll,
+}
+
+/// Overlay opened by `/repos edit` for managing the configured repo list.
+#[derive(Default)]
+struct RepoManager {
+    selected: usize,
+    /// Text typed after pressing `a`, until Enter adds it or Esc cancels.
+    adding: Option<String>,
+}
+
+/// Token prompt opened when Bitbucket rejects the credentials (401)
+/// mid-session; holds the token typed so far.
+#[derive(Default)]
+struct ReauthPrompt {
+    token: String,
+}
+
+struct FetchOutcome {
+    pull_requests: Vec<PullRequest>,
+    repo_count: usize,
+    failed_repos: usize,
+}
+
+/// Client a refresh lists PRs with: Bitbucket, queried per repo, or any
+/// other provider, which lists the user's PRs across repos.
+enum FetchSource {
+    Bitbucket(BitbucketClient),
+    Provider(Box<dyn PrProvider>),
+}
+
+enum RefreshMessage {
+    /// A listing fetched another page: the repo (`None` for the cross-repo
+    /// listing), the page number, and the PRs fetched so far.
+    Page(Option<String>, usize, usize),
+    Log(String),
+    Finished(Box<Fetcher>, Option<FetchOutcome>),
+}
+
+/// A refresh fetching on a background thread; its messages are drained
+/// from `receiver` by the event loop.
+struct RefreshJob {
+    receiver: mpsc::Receiver<RefreshMessage>,
+    started: Instant,
+    notify_updates: bool,
+    /// Latest page reported by a listing, e.g. `team/api: page 3`.
+    progress: Option<String>,
+}
+
+impl RefreshJob {
+    fn progress_label(&self) -> String {
+        match &self.progress {
+            Some(progress) => format!("Loading ({progress})"),
+            None => "Loading...".to_string(),
+        }
+    }
+}
+
+/// The network half of a refresh. It starts from a copy of the app state
+/// that fetching reads and updates, so it can run off the UI thread, and
+/// `App::finish_fetch` takes that state back once it is done.
+struct Fetcher {
+    config: Config,
+    relation: Relation,
+    custom_query: Option<String>,
+    issue_pattern: Option<Regex>,
+    user_id: Option<UserId>,
+    offline: bool,
+    offline_refreshes: u32,
+    workspace_repos: HashMap<String, (Instant, Vec<RepoRef>)>,
+    repo_health: HashMap<String, RepoHealth>,
+    required_reviewers: HashMap<String, RequiredReviewers>,
+    repo_timings: Vec<(String, Duration)>,
+    /// Configured repos that answered 404/403.
+    dead_repos: Vec<RepoRef>,
+    /// Bitbucket answered 401 when looking up the current user.
+    token_rejected: bool,
+    sender: mpsc::Sender<RefreshMessage>,
+}
+
+struct App {
+    config: Config,
+    status_filter: PrStatus,
+    input: String,
+    logs: Vec<String>,
+    pull_requests: Vec<PullRequest>,
+    all_pull_requests: Vec<PullRequest>,
+    search_query: Option<String>,
+    find_query: Option<String>,
+    approval_filter: Option<ApprovalFilter>,
+    waiting_filter: Option<i64>,
+    size_filter: Option<SizeCategory>,
+    solo_filter: bool,
+    mainline_filter: bool,
+    custom_query: Option<String>,
+    relation: Relation,
+    pinned: HashSet<String>,
+    /// Group keys (repo or pinned) folded down to their header.
+    collapsed: HashSet<String>,
+    /// PRs opened or inspected this session, by PR key, with the
+    /// `updated_on` seen at the time; new activity marks them unread again.
+    visited: HashMap<String, String>,
+    selected_index: usize,
+    command_suggestion_index: usize,
+    auto_refresh_interval: Duration,
+    last_refresh_at: Option<Instant>,
+    /// Last keystroke, for backing off auto refresh while the user is away.
+    last_interaction: Instant,
+    last_refreshed: Option<DateTime<Local>>,
+    offline: bool,
+    offline_refreshes: u32,
+    workspace_repos: HashMap<String, (Instant, Vec<RepoRef>)>,
+    user_id: Option<UserId>,
+    pending_action: Option<PendingAction>,
+    repo_manager: Option<RepoManager>,
+    diff_view: Option<DiffView>,
+    palette: Option<CommandPalette>,
+    detail: Option<DetailView>,
+    reauth: Option<ReauthPrompt>,
+    offered_repo_removals: HashSet<String>,
+    stats_job: Option<StatsJob>,
+    refresh_job: Option<RefreshJob>,
+    /// Latest comment per PR key, tagged with the comment count it was
+    /// fetched at so new comments invalidate it.
+    latest_comments: HashMap<String, (u64, Option<Comment>)>,
+    /// Commit summary per PR key, tagged with the `updated_on` it was
+    /// fetched at so new pushes invalidate it.
+    commit_summaries: HashMap<String, (String, CommitSummary)>,
+    /// Ahead/behind counts per PR key from `/behind`; rows of PRs found
+    /// behind their destination are flagged until the next check.
+    divergences: HashMap<String, Divergence>,
+    /// Per-repo fetch outcomes, keyed by `workspace/repo`.
+    repo_health: HashMap<String, RepoHealth>,
+    /// Default reviewers per repo key, fetched once per session.
+    required_reviewers: HashMap<String, RequiredReviewers>,
+    live_search_edited_at: Option<Instant>,
+    keymap: Keymap,
+    issue_pattern: Option<Regex>,
+    focus: bool,
+    /// Log refresh durations (`--timing`).
+    timing: bool,
+    /// How long each repo took during the latest per-repo refresh.
+    repo_timings: Vec<(String, Duration)>,
+    should_quit: bool,
+}
+
+impl App {
+    fn new(config: Config) -> Self {
+        let status_filter = config.launch_status();
+        let auto_refresh_interval = Duration::from_secs(config.auto_refresh_seconds());
+        let search_query = config
+            .default_search
+            .clone()
+            .filter(|query| !query.trim().is_empty());
+        // `Config::load` already rejected invalid `[keys]` entries.
+        let keymap = Keymap::resolve(&config.keys).unwrap_or_default();
+        let issue_pattern = issues::compile(&config.issue_key_pattern).ok();
+        let pinned = if config.persist_pins {
+            config.pinned_prs.iter().cloned().collect()
+        } else {
+            HashSet::new()
+        };
+        Self {
+            config,
+            status_filter,
+            input: String::new(),
+            logs: Vec::new(),
+            pull_requests: Vec::new(),
+            all_pull_requests: Vec::new(),
+            search_query,
+            find_query: None,
+            approval_filter: None,
+            waiting_filter: None,
+            size_filter: None,
+            solo_filter: false,
+            mainline_filter: false,
+            custom_query: None,
+            relation: Relation::Authored,
+            pinned,
+            collapsed: HashSet::new(),
+            visited: HashMap::new(),
+            selected_index: 0,
+            command_suggestion_index: 0,
+            auto_refresh_interval,
+            last_refresh_at: None,
+            last_interaction: Instant::now(),
+            last_refreshed: None,
+            offline: false,
+            offline_refreshes: 0,
+            workspace_repos: HashMap::new(),
+            user_id: None,
+            pending_action: None,
+            repo_manager: None,
+            diff_view: None,
+            palette: None,
+            detail: None,
+            reauth: None,
+            offered_repo_removals: HashSet::new(),
+            stats_job: None,
+            refresh_job: None,
+            latest_comments: HashMap::new(),
+            commit_summaries: HashMap::new(),
+            divergences: HashMap::new(),
+            repo_health: HashMap::new(),
+            required_reviewers: HashMap::new(),
+            live_search_edited_at: None,
+            keymap,
+            issue_pattern,
+            focus: false,
+            timing: false,
+            repo_timings: Vec::new(),
+            should_quit: false,
+        }
+    }
+
+    /// Logs what changed since the last run's version, once, and remembers
+    /// `version` as seen.
+    fn announce_new_version(&mut self, version: &str) {
+        if self.config.last_seen_version.as_deref() == Some(version) {
+            return;
+        }
+        if let Some(note) = whats_new::note(self.config.last_seen_version.as_deref(), version) {
+            self.log(&note);
+        }
+        self.config.last_seen_version = Some(version.to_string());
+        if let Err(err) = self.config.save() {
+            debug_log::record(
+                "WARN",
+                &format!("failed to record last seen version: {err:#}"),
+            );
+        }
+    }
+
+    /// Window title for `set_terminal_title`: the number of open PRs loaded
+    /// by the latest refresh.
+    fn terminal_title(&self) -> String {
+        let open = self
+            .all_pull_requests
+            .iter()
+            .filter(|pr| pr.state == "OPEN")
+            .count();
+        format!("myprs: {open} open")
+    }
+
+    /// First-run guidance shown in the list area while nothing is configured
+    /// to query.
+    fn onboarding_lines(&self) -> Vec<Line<'static>> {
+        let resolved = self.config.resolved_credentials();
+        let source_or_missing = |value: Option<(&str, CredentialSource)>| {
+            value
+                .map(|(_, source)| format!("from {source}"))
+                .unwrap_or_else(|| "missing".to_string())
+        };
+        let credential_status = format!(
+            "Credentials: {} (email: {}, API token: {})",
+            if resolved.pair().is_some() {
+                "configured"
+            } else {
+                "missing"
+            },
+            source_or_missing(resolved.email),
+            source_or_missing(resolved.api_token),
+        );
+        let config_path = Config::config_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| "~/.config/myprs/config.toml".to_string());
+
+        vec![
+            Line::from("No repos configured yet."),
+            Line::from(""),
+            Line::from("1. Add a repo (or a whole workspace) here:"),
+            Line::from("     /repo add <workspace>/<repo>"),
+            Line::from("     /workspace add <workspace>"),
+            Line::from("2. Set credentials, then restart myprs:"),
+            Line::from("     export BITBUCKET_EMAIL=you@example.com"),
+            Line::from("     export BITBUCKET_API_TOKEN=<token>"),
+            Line::from("   or: myprs --email you@example.com --api-token <token>"),
+            Line::from("3. Run /refresh to load pull requests."),
+            Line::from(""),
+            Line::from(credential_status),
+            Line::from(format!("Config file: {config_path}")),
+        ]
+    }
+
+    fn draw(&self, frame: &mut ratatui::Frame) {
+        let area = frame.area();
+        let (min_width, min_height) = (
+            self.config.min_terminal_width,
+            self.config.min_terminal_height,
+        );
+        if area.width < min_width || area.height < min_height {
+            let notice = Paragraph::new(format!(
+                "Terminal too small ({}x{}). Resize to at least {min_width}x{min_height}.",
+                area.width, area.height
+            ))
+            .wrap(Wrap { trim: true });
+            frame.render_widget(notice, area);
+            return;
+        }
+
+        // Focus mode hides the log and keeps the input only while typing.
+        let show_input = !self.focus || !self.input.is_empty();
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Min(6),
+                Constraint::Length(if self.focus { 0 } else { 8 }),
+                Constraint::Length(if show_input { 3 } else { 0 }),
+            ])
+            .split(frame.area());
+
+        let resolved = self.config.resolved_credentials();
+        let auth_status = match (self.config.provider(), resolved.pair(), resolved.api_token) {
+            (Provider::Github, ..) if self.config.github_token().is_some() => {
+                "configured (GitHub token)".to_string()
+            }
+            (Provider::Github, ..) => "missing (GitHub token)".to_string(),
+            (_, Some(_), Some((_, source))) => format!("configured (token from {source})"),
+            _ => "missing".to_string(),
+        };
+
+        let header = Paragraph::new(Text::from(vec![
+            Line::from(format!(
+                "Repos: {} | Status: {} | API token auth: {} | Auto refresh: {}s{}{}{}",
+                self.config.repos().len(),
+                self.status_filter,
+                auth_status,
+                self.effective_refresh_interval().as_secs(),
+                if self.idle_refresh_interval() > self.auto_refresh_interval {
+                    " (idle)"
+                } else {
+                    ""
+                },
+                if self.offline { " | OFFLINE" } else { "" },
+                if self.config.is_read_only() {
+                    " | READ-ONLY"
+                } else {
+                    ""
+                }
+            )),
+            Line::from(format!("Last refreshed: {}", self.last_refreshed_label())),
+        ]))
+        .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Command (/help)"),
+                .title("myprs - Bitbucket PR TUI"),
         );
-        frame.render_widget(input, chunks[3]);
-        frame.set_cursor_position((chunks[3].x + self.input.len() as u16 + 1, chunks[3].y + 1));
+        frame.render_widget(header, chunks[0]);
+
+        let mut list_title = match (&self.find_query, &self.search_query) {
+            (Some(query), _) => format!("My Pull Requests (all states) | Find: {query}"),
+            (None, Some(query)) => format!(
+                "My Pull Requests ({}) | Search: {}{}",
+                self.status_filter,
+                query,
+                if self.is_default_search() {
+                    " (default)"
+                } else {
+                    ""
+                }
+            ),
+            (None, None) => format!("My Pull Requests ({})", self.status_filter),
+        };
+        if self.config.search_scope == SearchScope::Title {
+            list_title.push_str(" | Scope: title");
+        }
+        if self.relation != Relation::Authored {
+            list_title.push_str(&format!(" | Relation: {}", self.relation));
+        }
+        if let Some(query) = &self.custom_query {
+            list_title.push_str(&format!(" | Query: {query}"));
+        }
+        if let Some(job) = &self.refresh_job {
+            list_title.push_str(&format!(" | {}", job.progress_label()));
+        }
+        if let Some(job) = &self.stats_job {
+            list_title.push_str(&format!(" | {}", job.progress_label()));
+        }
+        if let Some(filter) = self.approval_filter {
+            list_title.push_str(&format!(" | Approvals: {filter}"));
+        }
+        if let Some(days) = self.waiting_filter {
+            list_title.push_str(&format!(" | Waiting > {days}d"));
+        }
+        if let Some(size) = self.size_filter {
+            list_title.push_str(&format!(" | Size: {size}"));
+        }
+        if let Some(repo) = self.config.only_repo() {
+            list_title.push_str(&format!(" | Only: {repo}"));
+        }
+        if self.solo_filter {
+            list_title.push_str(" | Solo");
+        }
+        if self.mainline_filter {
+            list_title.push_str(&format!(" | Mainline: {}", self.config.default_branch));
+        }
+        let list_block = Block::default().borders(Borders::ALL).title(list_title);
+        if self.pull_requests.is_empty() && !self.config.has_repo_sources() {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title("Getting started");
+            frame.render_widget(
+                Paragraph::new(self.onboarding_lines())
+                    .block(block)
+                    .wrap(Wrap { trim: false }),
+                chunks[1],
+            );
+        } else if self.pull_requests.is_empty() {
+            let empty_state = if let Some(query) = &self.search_query {
+                format!("No PRs match search '{query}'. Use /search clear to reset.")
+            } else if let Some(filter) = self.approval_filter {
+                format!("No PRs with approvals {filter}. Use /approved clear to reset.")
+            } else {
+                "No pull requests loaded. Configure credentials, add repos, then run /refresh."
+                    .to_string()
+            };
+            frame.render_widget(Paragraph::new(empty_state).block(list_block), chunks[1]);
+        } else {
+            let (rows, selected_row) = self.grouped_rows();
+            let row_count = rows.len();
+            let items = rows
+                .into_iter()
+                .map(|row| {
+                    if row.is_spacer {
+                        return ListItem::new(row.text).style(Style::default().fg(Color::DarkGray));
+                    }
+                    let color = self.repo_color(&row.repo_key);
+                    if row.is_header {
+                        let mut spans = vec![Span::styled(
+                            row.text,
+                            Style::default().fg(color).add_modifier(Modifier::BOLD),
+                        )];
+                        match row.health {
+                            Some(HealthBadge::Ok) => spans
+                                .push(Span::styled(" \u{25cf}", Style::default().fg(Color::Green))),
+                            Some(HealthBadge::Failed(at)) => spans.push(Span::styled(
+                                format!(" \u{25cf} failed {at}"),
+                                Style::default().fg(Color::Red),
+                            )),
+                            None => {}
+                        }
+                        ListItem::new(Line::from(spans))
+                    } else {
+                        let author = row.author.unwrap_or_default();
+                        let mut text_style = Style::default();
+                        if row.own {
+                            text_style = text_style.add_modifier(Modifier::ITALIC);
+                        }
+                        if row.visited {
+                            text_style = text_style.fg(Color::DarkGray);
+                        }
+                        let mut spans = vec![
+                            Span::raw("  "),
+                            Span::styled("\u{25cf} ", Style::default().fg(color)),
+                            Span::styled(
+                                format!("{} ", author_initials(&author)),
+                                Style::default()
+                                    .fg(palette_color(&author))
+                                    .add_modifier(Modifier::BOLD),
+                            ),
+                        ];
+                        if let Some(size) = row.size {
+                            spans.push(Span::styled(
+                                format!("[{size}] "),
+                                Style::default().fg(size_color(size)),
+                            ));
+                        }
+                        spans.push(Span::styled(row.text, text_style));
+                        ListItem::new(Line::from(spans))
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let list = List::new(items)
+                .block(list_block)
+                .highlight_style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .bg(Color::Black)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol("> ");
+
+            let mut state = ListState::default();
+            state.select(selected_row);
+            frame.render_stateful_widget(list, chunks[1], &mut state);
+
+            let visible_rows = chunks[1].height.saturating_sub(2) as usize;
+            if row_count > visible_rows {
+                let mut scrollbar_state = ScrollbarState::new(self.pull_requests.len())
+                    .position(self.selected_index)
+                    .viewport_content_length(visible_rows);
+                frame.render_stateful_widget(
+                    Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                    chunks[1].inner(Margin {
+                        vertical: 1,
+                        horizontal: 0,
+                    }),
+                    &mut scrollbar_state,
+                );
+            }
+        }
+
+        let log_lines = self
+            .logs
+            .iter()
+            .rev()
+            .take(6)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>();
+        if !self.focus {
+            frame.render_widget(
+                Paragraph::new(log_lines.join("\n"))
+                    .block(Block::default().borders(Borders::ALL).title("Log")),
+                chunks[2],
+            );
+        }
+
+        if show_input {
+            let input = Paragraph::new(self.input.as_str()).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Command (/help | r/F5: refresh)"),
+            );
+            frame.render_widget(input, chunks[3]);
+            frame.set_cursor_position((chunks[3].x + self.input.len() as u16 + 1, chunks[3].y + 1));
+        }
 
         let suggestions = self.command_suggestions();
         if !suggestions.is_empty() {
-            let popup_height = suggestions.len() as u16 + 2;
+            // Never taller than the space above the input; the list scrolls.
+            let popup_height = (suggestions.len() as u16 + 2).min(chunks[3].y);
             let popup_width = chunks[3].width.min(72);
             let popup_area = Rect::new(
                 chunks[3].x,
@@ -274,604 +1550,5399 @@ impl App {
             frame.render_widget(Clear, popup_area);
             frame.render_stateful_widget(list, popup_area, &mut state);
         }
-    }
 
-    fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
+        if let Some(manager) = &self.repo_manager {
+            self.draw_repo_manager(frame, manager);
+        }
+
+        if let Some(view) = &self.diff_view {
+            draw_diff_view(frame, view);
+        }
+
+        if let Some(palette) = &self.palette {
+            self.draw_palette(frame, palette);
+        }
+
+        if let Some(detail) = &self.detail {
+            draw_detail(frame, detail);
+        }
+
+        if let Some(prompt) = &self.reauth {
+            let area = centered_rect(64, 5, frame.area());
+            let dialog = Paragraph::new(Text::from(vec![
+                Line::from("Bitbucket rejected the API token (401). Enter a new one:"),
+                Line::from(format!("{}_", "*".repeat(prompt.token.chars().count()))),
+                Line::from("Enter: save and retry | Esc: cancel"),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Re-authenticate"),
+            );
+            frame.render_widget(Clear, area);
+            frame.render_widget(dialog, area);
+        }
+
+        if let Some(action) = &self.pending_action {
+            let mut lines = vec![action.prompt()];
+            let calls = action.api_calls(&Endpoints::new(&self.config.bitbucket_base_url));
+            if !calls.is_empty() {
+                lines.push(String::new());
+                lines.extend(calls.iter().take(MAX_PREVIEW_CALLS).cloned());
+                if calls.len() > MAX_PREVIEW_CALLS {
+                    lines.push(format!("... and {} more", calls.len() - MAX_PREVIEW_CALLS));
+                }
             }
-            KeyCode::Esc => {
-                self.should_quit = true;
+            lines.push("y/Enter: confirm | n/Esc: cancel".to_string());
+            let longest = lines.iter().map(|line| line.chars().count()).max();
+            let width = (longest.unwrap_or_default() as u16 + 4).clamp(32, frame.area().width);
+            let area = centered_rect(width, lines.len() as u16 + 2, frame.area());
+            let dialog = Paragraph::new(Text::from(
+                lines.into_iter().map(Line::from).collect::<Vec<_>>(),
+            ))
+            .block(Block::default().borders(Borders::ALL).title("Confirm"));
+            frame.render_widget(Clear, area);
+            frame.render_widget(dialog, area);
+        }
+    }
+
+    fn draw_repo_manager(&self, frame: &mut ratatui::Frame, manager: &RepoManager) {
+        let repos = &self.config.repos;
+        let height = (repos.len() as u16 + 4).clamp(6, frame.area().height);
+        let area = centered_rect(60, height, frame.area());
+        let title = match &manager.adding {
+            Some(input) => format!("Add repo (workspace/repo): {input}_"),
+            None => "Repos (a: add | x: remove | Esc: close)".to_string(),
+        };
+        let block = Block::default().borders(Borders::ALL).title(title);
+        frame.render_widget(Clear, area);
+
+        if repos.is_empty() {
+            frame.render_widget(
+                Paragraph::new("No repos configured. Press a to add one.").block(block),
+                area,
+            );
+            return;
+        }
+
+        let items = repos
+            .iter()
+            .map(|repo| {
+                ListItem::new(repo.to_string())
+                    .style(Style::default().fg(self.repo_color(&repo.to_string())))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        state.select(Some(manager.selected.min(repos.len() - 1)));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_palette(&self, frame: &mut ratatui::Frame, palette: &CommandPalette) {
+        let matches = self.palette_matches(&palette.query);
+        let height = (matches.len().min(MAX_PALETTE_ROWS) as u16 + 2).clamp(3, frame.area().height);
+        let area = centered_rect(72, height, frame.area());
+        let block = Block::default().borders(Borders::ALL).title(format!(
+            "Command palette: {}_ (Enter: run | Esc: close)",
+            palette.query
+        ));
+        frame.render_widget(Clear, area);
+
+        if matches.is_empty() {
+            frame.render_widget(Paragraph::new("No matching commands.").block(block), area);
+            return;
+        }
+
+        let items = matches
+            .iter()
+            .map(|entry| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<16}", entry.label()),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(entry.detail(), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        state.select(Some(palette.selected.min(matches.len() - 1)));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// Palette entries matching `query`, best first. Write commands are left
+    /// out in read-only mode, as in the suggestions.
+    fn palette_matches(&self, query: &str) -> Vec<PaletteEntry> {
+        let read_only = self.config.is_read_only();
+        let mut scored = PALETTE_ACTIONS
+            .iter()
+            .map(|(action, label)| PaletteEntry::Action(*action, label))
+            .chain(
+                COMMAND_SPECS
+                    .iter()
+                    .filter(|spec| !(read_only && spec.writes))
+                    .map(|spec| PaletteEntry::Command(*spec)),
+            )
+            .filter_map(|entry| {
+                let text = format!("{} {}", entry.label(), entry.detail());
+                fuzzy::score(query, &text).map(|score| (score, entry))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    fn handle_palette_key(&mut self, key: KeyEvent) -> Result<()> {
+        let match_count = self
+            .palette
+            .as_ref()
+            .map_or(0, |palette| self.palette_matches(&palette.query).len());
+        let Some(palette) = self.palette.as_mut() else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.palette = None;
+                self.request_quit();
+            }
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Up => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Down if palette.selected + 1 < match_count => palette.selected += 1,
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
+            }
+            KeyCode::Char(ch) => {
+                palette.query.push(ch);
+                palette.selected = 0;
+            }
+            KeyCode::Enter => {
+                let Some(palette) = self.palette.take() else {
+                    return Ok(());
+                };
+                let matches = self.palette_matches(&palette.query);
+                let selected = palette.selected.min(matches.len().saturating_sub(1));
+                if let Some(entry) = matches.get(selected).copied() {
+                    self.run_palette_entry(entry)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Runs a chosen palette entry. Commands that take arguments are put in
+    /// the input to be completed instead.
+    fn run_palette_entry(&mut self, entry: PaletteEntry) -> Result<()> {
+        match entry {
+            PaletteEntry::Command(spec) if spec.accepts_args => {
+                self.input = format!("{} ", spec.name);
+                self.command_suggestion_index = 0;
+            }
+            PaletteEntry::Command(spec) => {
+                if let Err(err) = self.execute_command(spec.name) {
+                    self.log(&format!("Command failed: {err}"));
+                }
+            }
+            PaletteEntry::Action(action, _) => return self.run_action(action, true),
+        }
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        self.last_interaction = Instant::now();
+        if self.pending_action.is_some() {
+            self.handle_pending_action_key(key);
+            return Ok(());
+        }
+        if self.reauth.is_some() {
+            self.handle_reauth_key(key);
+            return Ok(());
+        }
+        if self.repo_manager.is_some() {
+            return self.handle_repo_manager_key(key);
+        }
+        if self.diff_view.is_some() {
+            return self.handle_diff_view_key(key);
+        }
+        if self.palette.is_some() {
+            return self.handle_palette_key(key);
+        }
+        if self.detail.is_some() {
+            return self.handle_detail_key(key);
+        }
+
+        let input_empty = self.input.is_empty();
+        if let Some(action) = self.keymap.action(&key, input_empty) {
+            return self.run_action(action, input_empty);
+        }
+
+        match key.code {
+            KeyCode::Enter => return self.submit_input(),
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.command_suggestion_index = 0;
+                self.note_input_edit();
+            }
+            KeyCode::Char(ch) => {
+                self.input.push(ch);
+                self.command_suggestion_index = 0;
+                self.note_input_edit();
             }
-            KeyCode::Up => {
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn run_action(&mut self, action: Action, input_empty: bool) -> Result<()> {
+        match action {
+            Action::Quit => self.request_quit(),
+            Action::Up => {
                 if self.has_command_suggestions() {
                     self.move_command_selection(-1);
                 } else {
-                    self.selected_index = self.selected_index.saturating_sub(1);
+                    self.move_selection(-1);
                 }
             }
-            KeyCode::Down => {
+            Action::Down => {
                 if self.has_command_suggestions() {
                     self.move_command_selection(1);
-                } else if self.selected_index + 1 < self.pull_requests.len() {
-                    self.selected_index += 1;
+                } else {
+                    self.move_selection(1);
+                }
+            }
+            Action::Refresh => self.refresh_pull_requests(true),
+            Action::CopyId => {
+                if let Err(err) = self.copy_selected("id") {
+                    self.log(&format!("Command failed: {err}"));
+                }
+            }
+            Action::Pipeline if self.unsupported_by_provider("Opening pipelines") => {}
+            Action::Pipeline => {
+                if let Err(err) = self.open_pipeline() {
+                    self.log(&format!("Command failed: {err}"));
                 }
             }
-            KeyCode::Tab => {
+            Action::Complete => {
                 let _ = self.apply_command_completion();
             }
+            Action::Focus => self.toggle_focus(),
+            Action::Collapse => self.toggle_selected_group(),
+            Action::OpenRepo if self.unsupported_by_provider("Opening repos") => {}
+            Action::OpenRepo => {
+                if let Err(err) = self.open_repo() {
+                    self.log(&format!("Command failed: {err}"));
+                }
+            }
+            // Enter with a typed command submits it instead.
+            Action::Open if !input_empty => return self.submit_input(),
+            Action::Open => self.open_selected(),
+            Action::Palette => self.palette = Some(CommandPalette::default()),
+            Action::Detail => self.open_detail(),
+        }
+        Ok(())
+    }
+
+    fn open_detail(&mut self) {
+        let Some(pr) = self.selected_pull_request().cloned() else {
+            self.log("No pull request selected.");
+            return;
+        };
+        self.visited
+            .insert(Self::pr_key(&pr), pr.updated_on.clone());
+        let latest_comment = self
+            .selected_latest_comment()
+            .map_err(|err| format!("{err:#}"));
+        let pending_reviewers = self
+            .pending_required_reviewers(&pr)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        self.detail = Some(DetailView {
+            pull_request: pr,
+            latest_comment,
+            pending_reviewers,
+            scroll: OverlayScroll::default(),
+        });
+    }
+
+    /// Scrolls the detail overlay; Enter opens the PR in the browser and
+    /// Esc or the detail key closes it.
+    fn handle_detail_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.keymap.action(&key, true) == Some(Action::Detail) {
+            self.detail = None;
+            return Ok(());
+        }
+        let Some(detail) = self.detail.as_mut() else {
+            return Ok(());
+        };
+        if detail.scroll.handle_key(key.code) {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.detail = None;
+                self.request_quit();
+            }
+            KeyCode::Esc => self.detail = None,
             KeyCode::Enter => {
-                if self.apply_command_completion_if_partial() {
-                    return Ok(());
+                let url = detail.pull_request.url.clone();
+                self.open_url(&url)?;
+                self.detail = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn submit_input(&mut self) -> Result<()> {
+        if self.apply_command_completion_if_partial() {
+            return Ok(());
+        }
+
+        let command = self.input.trim().to_string();
+        if let Ok(number) = command.parse::<usize>() {
+            self.input.clear();
+            if let Err(err) = self.open_pull_request_in_focused_group(number) {
+                self.log(&format!("Command failed: {err}"));
+            }
+            return Ok(());
+        }
+        if !command.is_empty() && !command.starts_with("/search") {
+            self.clear_search_filter_if_active();
+        }
+        self.input.clear();
+        self.live_search_edited_at = None;
+        if command.is_empty() {
+            return Ok(());
+        }
+        if let Err(err) = self.execute_command(&command) {
+            self.log(&format!("Command failed: {err}"));
+        }
+        Ok(())
+    }
+
+    fn toggle_focus(&mut self) {
+        self.focus = !self.focus;
+        self.log(if self.focus {
+            "Focus mode on: log hidden, type / to show the input. /focus or f to exit."
+        } else {
+            "Focus mode off."
+        });
+    }
+
+    /// Whether the PR at `index` can be selected: every PR of an expanded
+    /// group, and only the first PR of a collapsed one, which stands in for
+    /// its header.
+    fn is_selectable(&self, index: usize) -> bool {
+        let Some(pr) = self.pull_requests.get(index) else {
+            return false;
+        };
+        let group = self.group_key(pr);
+        !self.collapsed.contains(&group)
+            || index == 0
+            || self.group_key(&self.pull_requests[index - 1]) != group
+    }
+
+    fn move_selection(&mut self, direction: isize) {
+        let mut index = self.selected_index;
+        loop {
+            match index.checked_add_signed(direction) {
+                Some(next) if next < self.pull_requests.len() => index = next,
+                _ => return,
+            }
+            if self.is_selectable(index) {
+                self.selected_index = index;
+                return;
+            }
+        }
+    }
+
+    /// Folds or unfolds the selected PR's group. A folded group keeps the
+    /// selection on its header.
+    fn toggle_selected_group(&mut self) {
+        let Some(group) = self.selected_pull_request().map(|pr| self.group_key(pr)) else {
+            return;
+        };
+        if !self.collapsed.remove(&group) {
+            self.collapsed.insert(group.clone());
+            if let Some(first) = self
+                .pull_requests
+                .iter()
+                .position(|pr| self.group_key(pr) == group)
+            {
+                self.selected_index = first;
+            }
+        }
+    }
+
+    fn open_selected(&mut self) {
+        if self.pull_requests.is_empty() {
+            self.log("No pull request selected.");
+            return;
+        }
+        if let Some(pr) = self.selected_pull_request()
+            && self.collapsed.contains(&self.group_key(pr))
+        {
+            self.toggle_selected_group();
+            return;
+        }
+        let index = self
+            .selected_index
+            .min(self.pull_requests.len().saturating_sub(1))
+            + 1;
+        if let Err(err) = self.open_pull_request(index) {
+            self.log(&format!("Command failed: {err}"));
+        }
+    }
+
+    fn handle_reauth_key(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.reauth.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.request_quit();
+            }
+            KeyCode::Char(ch) => prompt.token.push(ch),
+            KeyCode::Backspace => {
+                prompt.token.pop();
+            }
+            KeyCode::Esc => {
+                self.reauth = None;
+                self.log("Re-authentication cancelled. Run /refresh to retry.");
+            }
+            KeyCode::Enter => {
+                let token = prompt.token.trim().to_string();
+                if token.is_empty() {
+                    return;
                 }
+                self.reauth = None;
+                debug_log::redact(&token);
+                match self.config.set_api_token(token) {
+                    None => {
+                        if let Err(err) = self.config.save() {
+                            self.log(&format!("Failed to save new API token: {err:#}"));
+                        }
+                        self.log("API token updated. Retrying refresh...");
+                    }
+                    Some(source) => self.log(&format!(
+                        "API token updated for this session only; update the token from {source} to keep it. Retrying refresh..."
+                    )),
+                }
+                self.refresh_pull_requests(false);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_repo_manager_key(&mut self, key: KeyEvent) -> Result<()> {
+        let repo_count = self.config.repos.len();
+        let Some(manager) = self.repo_manager.as_mut() else {
+            return Ok(());
+        };
 
-                let command = self.input.trim().to_string();
-                if !command.is_empty() && !command.starts_with("/search") {
-                    self.clear_search_filter_if_active();
+        if let Some(input) = manager.adding.as_mut() {
+            match key.code {
+                KeyCode::Char(ch) => input.push(ch),
+                KeyCode::Backspace => {
+                    input.pop();
                 }
-                self.input.clear();
-                if command.is_empty() {
-                    if self.pull_requests.is_empty() {
-                        self.log("No pull request selected.");
-                    } else {
-                        let index = self
-                            .selected_index
-                            .min(self.pull_requests.len().saturating_sub(1))
-                            + 1;
-                        if let Err(err) = self.open_pull_request(index) {
-                            self.log(&format!("Command failed: {err}"));
+                KeyCode::Esc => manager.adding = None,
+                KeyCode::Enter => {
+                    let input = manager.adding.take().unwrap_or_default();
+                    let repo_ref = RepoRef::parse(input.trim())?;
+                    if self.config.add_repo(repo_ref.clone()) {
+                        self.config.save()?;
+                        self.log(&format!("Added repo {repo_ref}"));
+                        if let Some(manager) = self.repo_manager.as_mut() {
+                            manager.selected = repo_count;
                         }
+                    } else {
+                        self.log(&format!("Repo {repo_ref} already exists"));
                     }
-                } else if let Err(err) = self.execute_command(&command) {
-                    self.log(&format!("Command failed: {err}"));
                 }
+                _ => {}
             }
-            KeyCode::Backspace => {
-                self.input.pop();
-                self.command_suggestion_index = 0;
+            return Ok(());
+        }
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.request_quit();
             }
-            KeyCode::Char(ch) => {
-                self.input.push(ch);
-                self.command_suggestion_index = 0;
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.repo_manager = None;
+                self.log("Closed repo manager. Run /refresh to reload PRs.");
+            }
+            KeyCode::Up => manager.selected = manager.selected.saturating_sub(1),
+            KeyCode::Down if manager.selected + 1 < repo_count => manager.selected += 1,
+            KeyCode::Char('a') => manager.adding = Some(String::new()),
+            KeyCode::Char('x') | KeyCode::Delete => {
+                if let Some(repo) = self.config.repos.get(manager.selected) {
+                    self.pending_action = Some(PendingAction::RemoveRepos {
+                        repos: vec![repo.clone()],
+                        inaccessible: false,
+                    });
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_pending_action_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.request_quit();
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                if let Some(action) = self.pending_action.take()
+                    && let Err(err) = self.run_pending_action(action)
+                {
+                    self.log(&format!("Command failed: {err}"));
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.pending_action = match self.pending_action.take() {
+                    Some(PendingAction::Quit { resume, .. }) => resume.map(|action| *action),
+                    _ => None,
+                };
+                self.log("Cancelled.");
             }
             _ => {}
         }
-
-        Ok(())
     }
 
-    fn execute_command(&mut self, command: &str) -> Result<()> {
-        if !command.starts_with('/') {
-            self.log("Commands must start with '/'. Try /help.");
-            return Ok(());
+    /// Quits, unless that would drop an open confirmation or cut off
+    /// background work; then asks first. Asking again quits.
+    fn request_quit(&mut self) {
+        let resume = self.pending_action.take();
+        let reason = match (&resume, &self.stats_job) {
+            (Some(PendingAction::Quit { .. }), _) | (None, None) => {
+                self.should_quit = true;
+                return;
+            }
+            (Some(action), _) => format!("This is still waiting: {}", action.prompt()),
+            (None, Some(job)) => format!(
+                "Diffstats are still being fetched ({}/{})",
+                job.done, job.total
+            ),
+        };
+        self.pending_action = Some(PendingAction::Quit {
+            reason,
+            resume: resume.map(Box::new),
+        });
+    }
+
+    fn run_pending_action(&mut self, action: PendingAction) -> Result<()> {
+        if self.config.is_read_only() && action.writes() {
+            self.log(READ_ONLY_NOTICE);
+            return Ok(());
+        }
+        match action {
+            PendingAction::Approval {
+                workspace,
+                repo,
+                id,
+                approve,
+            } => {
+                let Some(client) = self.build_client() else {
+                    return Ok(());
+                };
+                if approve {
+                    client.approve_pull_request(&workspace, &repo, id)?;
+                    self.log(&format!("Approved {workspace}/{repo} PR #{id}."));
+                } else {
+                    client.unapprove_pull_request(&workspace, &repo, id)?;
+                    self.log(&format!(
+                        "Removed approval from {workspace}/{repo} PR #{id}."
+                    ));
+                }
+            }
+            PendingAction::FetchStats { targets } => {
+                self.start_stats_job(targets);
+                return Ok(());
+            }
+            PendingAction::Quit { .. } => {
+                self.should_quit = true;
+                return Ok(());
+            }
+            PendingAction::DeclineStale { targets } => {
+                let Some(client) = self.build_client() else {
+                    return Ok(());
+                };
+                let mut declined = Vec::new();
+                for target in &targets {
+                    match client.decline_pull_request(&target.workspace, &target.repo, target.id) {
+                        Ok(()) => declined.push(target),
+                        Err(err) => self.log(&format!(
+                            "Failed to decline {}/{} PR #{}: {err}",
+                            target.workspace, target.repo, target.id
+                        )),
+                    }
+                }
+                self.log(&format!(
+                    "Declined {} of {} stale PR(s).",
+                    declined.len(),
+                    targets.len()
+                ));
+                // Drop them right away so the list is right even if the
+                // refresh below fails.
+                self.all_pull_requests.retain(|pr| {
+                    !declined.iter().any(|target| {
+                        target.workspace == pr.workspace
+                            && target.repo == pr.repo
+                            && target.id == pr.id
+                    })
+                });
+                self.apply_search_filter();
+            }
+            PendingAction::RemoveRepos { repos, .. } => {
+                for repo in &repos {
+                    self.config.remove_repo(repo);
+                }
+                if let Some(manager) = self.repo_manager.as_mut() {
+                    manager.selected = manager
+                        .selected
+                        .min(self.config.repos.len().saturating_sub(1));
+                }
+                self.config.save()?;
+                self.log(&format!("Removed {} repo(s) from config.", repos.len()));
+            }
+        }
+
+        self.refresh_pull_requests(false);
+        Ok(())
+    }
+
+    fn selected_pull_request(&self) -> Option<&PullRequest> {
+        self.pull_requests.get(
+            self.selected_index
+                .min(self.pull_requests.len().saturating_sub(1)),
+        )
+    }
+
+    fn handle_approve_command(&mut self) -> Result<()> {
+        let user_id = self
+            .user_id
+            .clone()
+            .ok_or_else(|| anyhow!("current user unknown; run /refresh first"))?;
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+
+        self.pending_action = Some(PendingAction::Approval {
+            workspace: pr.workspace.clone(),
+            repo: pr.repo.clone(),
+            id: pr.id,
+            approve: !pr.is_approved_by(&user_id),
+        });
+        Ok(())
+    }
+
+    /// Bitbucket Cloud has no way to reopen a declined PR, so this only says
+    /// so and how to get it back.
+    fn explain_reopen(&mut self) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let message = if pr.state != "DECLINED" {
+            format!(
+                "PR #{} is {}; only declined PRs could be reopened.",
+                pr.id, pr.state
+            )
+        } else {
+            let branch = pr
+                .source_branch
+                .as_deref()
+                .map(|branch| format!(" from {branch}"))
+                .unwrap_or_default();
+            format!(
+                "Bitbucket Cloud cannot reopen declined PRs; create a new PR{branch} to replace #{}.",
+                pr.id
+            )
+        };
+        self.log(&message);
+        Ok(())
+    }
+
+    fn execute_command(&mut self, command: &str) -> Result<()> {
+        if !command.starts_with('/') {
+            self.log("Commands must start with '/'. Try /help.");
+            return Ok(());
+        }
+
+        let mut parts = command.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let args = parts.collect::<Vec<_>>();
+
+        if self.config.is_read_only() && is_write_command(name) {
+            self.log(READ_ONLY_NOTICE);
+            return Ok(());
+        }
+        if is_bitbucket_only_command(name) && self.unsupported_by_provider(name) {
+            return Ok(());
+        }
+
+        match name {
+            "/help" => {
+                let commands = "/repo add <w>/<r>, /repo rm <w>/<r>, /repos, /repos sort, /repos edit, /workspace add|rm <w>, /project add|rm <w>/<key>, /status <open|merged|declined|all>, /relation <authored|reviewing|both>, /q <expr>|clear, /refresh, /retry-failed, /approve, /reopen, /approved <n|none|clear>, /waiting <days|clear>, /size <xs|s|m|l|xl|clear>, /solo, /mainline, /pipeline, /open-repo, /issue [key|n], /copy <id|branch|url>, /diff, /file <path>, /standup, /comment, /commits, /behind, /mark-all-read, /required, /stale [days], /focus, /density [compact|comfortable], /pin, /unpin, /stats-all, /summary, /export-md [path], /search <text|pr-number>, /search clear|reset, /search-scope <title|all>, /find <text|pr-number>, /limit <n|off>, /quit";
+                self.log(&format!(
+                    "Commands: {}",
+                    visible_commands(commands, self.config.is_read_only())
+                ));
+                self.log(
+                    "Tip: type '/' to show command suggestions; use Up/Down + Tab to autocomplete.",
+                );
+                self.log("Tip: press Enter with empty command input to open selected PR.");
+                self.log("Tip: press r (with empty input) or F5 to refresh, b to open the selected PR's pipeline.");
+                self.log("Tip: type a number and press Enter to open that PR in the selected repo group.");
+                self.log(&format!(
+                    "Tip: auto refresh runs every {} seconds and rings terminal bell when updates are detected.",
+                    self.auto_refresh_interval.as_secs()
+                ));
+            }
+            "/quit" => self.request_quit(),
+            "/repo" => {
+                self.handle_repo_command(&args)?;
+            }
+            "/repos" => {
+                self.handle_repos_command(&args)?;
+            }
+            "/status" => {
+                self.handle_status_command(&args)?;
+            }
+            "/refresh" => self.refresh_pull_requests(true),
+            "/retry-failed" => self.retry_failed_repos(),
+            "/workspace" => self.handle_workspace_command(&args)?,
+            "/project" => self.handle_project_command(&args)?,
+            "/approve" => self.handle_approve_command()?,
+            "/reopen" => self.explain_reopen()?,
+            "/pipeline" => self.open_pipeline()?,
+            "/open-repo" => self.open_repo()?,
+            "/issue" => self.open_issue(args.first().copied())?,
+            "/stats-all" => self.handle_stats_all_command()?,
+            "/comment" => self.show_latest_comment()?,
+            "/commits" => self.show_commits()?,
+            "/behind" => self.show_divergence(),
+            "/mark-all-read" => self.mark_all_read(),
+            "/stale" => self.handle_stale_command(&args)?,
+            "/focus" => self.toggle_focus(),
+            "/required" => self.show_required_reviewers()?,
+            "/density" => self.handle_density_command(&args)?,
+            "/pin" => self.set_selected_pinned(true)?,
+            "/unpin" => self.set_selected_pinned(false)?,
+            "/copy" => self.copy_selected(args.first().copied().unwrap_or("url"))?,
+            "/diff" => self.show_diff()?,
+            "/file" => self.show_file_diff(&args)?,
+            "/standup" => self.copy_standup_line()?,
+            "/export-md" => self.export_markdown(args.first().copied())?,
+            "/summary" => {
+                for line in summarize(&self.all_pull_requests).lines() {
+                    self.log(&line);
+                }
+            }
+            "/approved" => self.handle_approved_command(&args)?,
+            "/waiting" => self.handle_waiting_command(&args)?,
+            "/size" => self.handle_size_command(&args)?,
+            "/solo" => self.toggle_solo_filter(),
+            "/mainline" => self.toggle_mainline_filter(),
+            "/q" => self.handle_custom_query_command(&args)?,
+            "/relation" => self.handle_relation_command(&args)?,
+            "/search" => self.handle_search_command(&args),
+            "/search-scope" => self.handle_search_scope_command(&args)?,
+            "/find" => self.handle_find_command(&args)?,
+            "/limit" => self.handle_limit_command(&args)?,
+            _ => {
+                self.log("Unknown command. Try /help.");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_repo_command(&mut self, args: &[&str]) -> Result<()> {
+        if args.is_empty() {
+            return Err(anyhow!(
+                "usage: /repo add <workspace>/<repo> | /repo rm <workspace>/<repo>"
+            ));
+        }
+
+        match args[0] {
+            "add" => {
+                let repo = args
+                    .get(1)
+                    .ok_or_else(|| anyhow!("usage: /repo add <workspace>/<repo>"))?;
+                let repo_ref = RepoRef::parse(repo)?;
+                if self.config.add_repo(repo_ref.clone()) {
+                    self.config.save()?;
+                    self.log(&format!("Added repo {repo_ref}"));
+                } else {
+                    self.log(&format!("Repo {repo_ref} already exists"));
+                }
+            }
+            "rm" | "remove" => {
+                let repo = args
+                    .get(1)
+                    .ok_or_else(|| anyhow!("usage: /repo rm <workspace>/<repo>"))?;
+                let repo_ref = RepoRef::parse(repo)?;
+                if self.config.remove_repo(&repo_ref) {
+                    self.config.save()?;
+                    self.log(&format!("Removed repo {repo_ref}"));
+                } else {
+                    self.log(&format!("Repo {repo_ref} not found"));
+                }
+            }
+            repo_value => {
+                let repo_ref = RepoRef::parse(repo_value)?;
+                if self.config.add_repo(repo_ref.clone()) {
+                    self.config.save()?;
+                    self.log(&format!("Added repo {repo_ref}"));
+                } else {
+                    self.log(&format!("Repo {repo_ref} already exists"));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_repos_command(&mut self, args: &[&str]) -> Result<()> {
+        match args.first() {
+            None => self.show_repos(),
+            Some(&"edit") => self.repo_manager = Some(RepoManager::default()),
+            Some(&"sort") => {
+                if self.config.sort_repos() {
+                    self.config.save()?;
+                    self.log("Sorted configured repos.");
+                } else {
+                    self.log("Configured repos are already sorted.");
+                }
+            }
+            Some(_) => return Err(anyhow!("usage: /repos [sort|edit]")),
+        }
+
+        Ok(())
+    }
+
+    fn handle_workspace_command(&mut self, args: &[&str]) -> Result<()> {
+        let usage = "usage: /workspace add <workspace> | /workspace rm <workspace>";
+        let (Some(action), Some(workspace)) = (args.first(), args.get(1)) else {
+            return Err(anyhow!(usage));
+        };
+
+        match *action {
+            "add" => {
+                if self.config.add_workspace(workspace)? {
+                    self.config.save()?;
+                    self.log(&format!(
+                        "Watching all repos in workspace {workspace}. Refresh to load them."
+                    ));
+                } else {
+                    self.log(&format!("Workspace {workspace} is already watched"));
+                }
+            }
+            "rm" | "remove" => {
+                if self.config.remove_workspace(workspace) {
+                    self.config.save()?;
+                    self.workspace_repos.remove(*workspace);
+                    self.log(&format!("Stopped watching workspace {workspace}"));
+                } else {
+                    self.log(&format!("Workspace {workspace} is not watched"));
+                }
+            }
+            _ => return Err(anyhow!(usage)),
+        }
+
+        Ok(())
+    }
+
+    fn handle_project_command(&mut self, args: &[&str]) -> Result<()> {
+        let usage =
+            "usage: /project add <workspace>/<project-key> | /project rm <workspace>/<project-key>";
+        let (Some(action), Some(project)) = (args.first(), args.get(1)) else {
+            return Err(anyhow!(usage));
+        };
+
+        match *action {
+            "add" => {
+                if self.config.add_project(project)? {
+                    self.config.save()?;
+                    self.log(&format!(
+                        "Watching all repos in project {project}. Refresh to load them."
+                    ));
+                } else {
+                    self.log(&format!("Project {project} is already watched"));
+                }
+            }
+            "rm" | "remove" => {
+                if self.config.remove_project(project) {
+                    self.config.save()?;
+                    self.workspace_repos.remove(*project);
+                    self.log(&format!("Stopped watching project {project}"));
+                } else {
+                    self.log(&format!("Project {project} is not watched"));
+                }
+            }
+            _ => return Err(anyhow!(usage)),
+        }
+
+        Ok(())
+    }
+
+    fn show_repos(&mut self) {
+        let workspaces = self.config.workspaces().to_vec();
+        if !workspaces.is_empty() {
+            self.log(&format!("Watched workspaces: {}", workspaces.join(", ")));
+        }
+        let projects = self.config.projects().to_vec();
+        if !projects.is_empty() {
+            self.log(&format!("Watched projects: {}", projects.join(", ")));
+        }
+
+        if self.config.repos().is_empty() {
+            if !self.config.has_repo_sources() {
+                self.log("No repos configured. Add one with /repo add <workspace>/<repo>.");
+            }
+            return;
+        }
+
+        self.log("Configured repos:");
+        let repo_lines = self
+            .config
+            .repos()
+            .iter()
+            .map(|repo| format!("- {repo}"))
+            .collect::<Vec<_>>();
+        for line in repo_lines {
+            self.log(&line);
+        }
+    }
+
+    fn handle_status_command(&mut self, args: &[&str]) -> Result<()> {
+        let value = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /status <open|merged|declined|all>"))?;
+        let status = PrStatus::from_str(value)?;
+        self.status_filter = status;
+
+        if self.config.set_status(status) {
+            self.config.save()?;
+        }
+
+        self.log(&format!("Status filter set to {}. Refreshing...", status));
+        self.refresh_pull_requests(false);
+        Ok(())
+    }
+
+    fn set_selected_pinned(&mut self, pin: bool) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let key = Self::pr_key(pr);
+
+        let changed = if pin {
+            self.pinned.insert(key.clone())
+        } else {
+            self.pinned.remove(&key)
+        };
+        if !changed {
+            self.log(&format!(
+                "{key} is {} pinned.",
+                if pin { "already" } else { "not" }
+            ));
+            return Ok(());
+        }
+
+        self.apply_search_filter();
+        if let Some(index) = self
+            .pull_requests
+            .iter()
+            .position(|pr| Self::pr_key(pr) == key)
+        {
+            self.selected_index = index;
+        }
+        if self.config.persist_pins {
+            let mut pinned = self.pinned.iter().cloned().collect::<Vec<_>>();
+            pinned.sort();
+            self.config.pinned_prs = pinned;
+            self.config.save()?;
+        }
+        self.log(&format!(
+            "{} {key}.",
+            if pin { "Pinned" } else { "Unpinned" }
+        ));
+        Ok(())
+    }
+
+    fn handle_stats_all_command(&mut self) -> Result<()> {
+        if self.stats_job.is_some() {
+            return Err(anyhow!("diffstats are already being fetched"));
+        }
+        let targets = self
+            .pull_requests
+            .iter()
+            .filter(|pr| pr.diffstat.is_none())
+            .map(|pr| BatchTarget {
+                workspace: pr.workspace.clone(),
+                repo: pr.repo.clone(),
+                id: pr.id,
+            })
+            .collect::<Vec<_>>();
+
+        if targets.is_empty() {
+            self.log("All listed PRs already have diffstats.");
+        } else if targets.len() > STATS_CONFIRM_THRESHOLD {
+            self.pending_action = Some(PendingAction::FetchStats { targets });
+        } else {
+            self.start_stats_job(targets);
+        }
+        Ok(())
+    }
+
+    /// Fetches diffstats on background threads, at most `STATS_WORKERS` at a
+    /// time. Workers stop picking up new PRs once Bitbucket answers 429.
+    fn start_stats_job(&mut self, targets: Vec<BatchTarget>) {
+        let Some(client) = self.build_client() else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        let total = targets.len();
+        self.log(&format!("Fetching diffstats for {total} PR(s)..."));
+
+        thread::spawn(move || {
+            let next = AtomicUsize::new(0);
+            let stop = AtomicBool::new(false);
+            thread::scope(|scope| {
+                for _ in 0..STATS_WORKERS.min(targets.len()) {
+                    let sender = sender.clone();
+                    let (client, targets, next, stop) = (&client, &targets, &next, &stop);
+                    scope.spawn(move || {
+                        while !stop.load(Ordering::Relaxed) {
+                            let Some(target) = targets.get(next.fetch_add(1, Ordering::Relaxed))
+                            else {
+                                break;
+                            };
+                            let key = format!("{}/{}/{}", target.workspace, target.repo, target.id);
+                            let message =
+                                match client.diffstat(&target.workspace, &target.repo, target.id) {
+                                    Ok(diffstat) => StatsMessage::Fetched(key, diffstat),
+                                    Err(err)
+                                        if http_status(&err)
+                                            == Some(StatusCode::TOO_MANY_REQUESTS) =>
+                                    {
+                                        stop.store(true, Ordering::Relaxed);
+                                        StatsMessage::RateLimited
+                                    }
+                                    Err(err) => StatsMessage::Failed(key, err.to_string()),
+                                };
+                            if sender.send(message).is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            });
+        });
+
+        self.stats_job = Some(StatsJob {
+            receiver,
+            total,
+            done: 0,
+            failed: 0,
+            rate_limited: false,
+        });
+    }
+
+    /// Applies diffstats that arrived since the last call and reports once
+    /// the background job has finished.
+    fn poll_stats_job(&mut self) {
+        let Some(job) = self.stats_job.as_mut() else {
+            return;
+        };
+
+        let mut fetched = Vec::new();
+        let mut failures = Vec::new();
+        let finished = loop {
+            match job.receiver.try_recv() {
+                Ok(StatsMessage::Fetched(key, diffstat)) => {
+                    job.done += 1;
+                    fetched.push((key, diffstat));
+                }
+                Ok(StatsMessage::Failed(key, err)) => {
+                    job.done += 1;
+                    job.failed += 1;
+                    failures.push(format!("Diffstat failed for {key}: {err}"));
+                }
+                Ok(StatsMessage::RateLimited) => job.rate_limited = true,
+                Err(mpsc::TryRecvError::Empty) => break false,
+                Err(mpsc::TryRecvError::Disconnected) => break true,
+            }
+        };
+
+        for (key, diffstat) in fetched {
+            for pr in self
+                .all_pull_requests
+                .iter_mut()
+                .chain(self.pull_requests.iter_mut())
+                .filter(|pr| Self::pr_key(pr) == key)
+            {
+                pr.diffstat = Some(diffstat);
+            }
+        }
+        for failure in failures {
+            self.log(&failure);
+        }
+
+        if finished && let Some(job) = self.stats_job.take() {
+            let fetched = job.done - job.failed;
+            self.log(&format!(
+                "Fetched diffstats for {fetched}/{} PR(s).",
+                job.total
+            ));
+            if job.rate_limited {
+                self.log(
+                    "Stopped early: Bitbucket rate limit reached. Try /stats-all again later.",
+                );
+            }
+        }
+    }
+
+    fn handle_relation_command(&mut self, args: &[&str]) -> Result<()> {
+        let value = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /relation <authored|reviewing|both>"))?;
+        self.relation = Relation::from_str(value)?;
+        self.log(&format!("Showing {} PRs. Refreshing...", self.relation));
+        self.refresh_pull_requests(false);
+        Ok(())
+    }
+
+    fn handle_custom_query_command(&mut self, args: &[&str]) -> Result<()> {
+        let expr = args.join(" ");
+        if expr.is_empty() {
+            return Err(anyhow!("usage: /q <expr> | /q clear"));
+        }
+
+        if expr.eq_ignore_ascii_case("clear") {
+            self.custom_query = None;
+            self.log(&format!(
+                "Custom query cleared; status filter '{}' applies again. Refreshing...",
+                self.status_filter
+            ));
+        } else {
+            validate_custom_query(&expr)?;
+            self.log(&format!(
+                "Custom query set: {expr} (overrides the status filter). Refreshing..."
+            ));
+            self.custom_query = Some(expr);
+        }
+        self.refresh_pull_requests(false);
+        Ok(())
+    }
+
+    fn handle_approved_command(&mut self, args: &[&str]) -> Result<()> {
+        let value = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /approved <n|none|clear>"))?;
+        let filter = if value.eq_ignore_ascii_case("clear") {
+            None
+        } else if value.eq_ignore_ascii_case("none") {
+            Some(ApprovalFilter::Unapproved)
+        } else {
+            Some(ApprovalFilter::AtLeast(value.parse::<usize>().map_err(
+                |_| anyhow!("approval threshold must be a number, 'none', or 'clear'"),
+            )?))
+        };
+
+        self.approval_filter = filter;
+        self.apply_search_filter();
+        match filter {
+            Some(filter) => self.log(&format!(
+                "Showing PRs with approvals {filter}: {} matching PR(s).",
+                self.pull_requests.len()
+            )),
+            None => self.log("Approval filter cleared."),
+        }
+        Ok(())
+    }
+
+    /// Latest comment on the selected PR, fetched once per comment count.
+    fn selected_latest_comment(&mut self) -> Result<Option<Comment>> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let key = Self::pr_key(pr);
+        let comment_count = pr.comment_count;
+        if let Some((fetched_at, comment)) = self.latest_comments.get(&key)
+            && *fetched_at == comment_count
+        {
+            return Ok(comment.clone());
+        }
+        if comment_count == 0 {
+            return Ok(None);
+        }
+
+        let (workspace, repo, id) = (pr.workspace.clone(), pr.repo.clone(), pr.id);
+        let provider = self.config.provider();
+        if provider != Provider::Bitbucket {
+            bail!("comments are not supported for {}", provider.name());
+        }
+        let Some(client) = self.build_client() else {
+            bail!("cannot load comments without a Bitbucket client");
+        };
+        let comment = client.latest_comment(&workspace, &repo, id)?;
+        self.latest_comments
+            .insert(key, (comment_count, comment.clone()));
+        Ok(comment)
+    }
+
+    fn show_latest_comment(&mut self) -> Result<()> {
+        self.mark_selected_visited();
+        let message = match self.selected_latest_comment()? {
+            Some(comment) => format!(
+                "Latest comment by {} ({}): {}",
+                comment.author,
+                comment.created_on.get(..10).unwrap_or(&comment.created_on),
+                markdown::one_line(&comment.raw, COMMENT_PREVIEW_CHARS)
+            ),
+            None => "No comments on this PR yet.".to_string(),
+        };
+        self.log(&message);
+        Ok(())
+    }
+
+    /// Commit summary of the selected PR, fetched once per `updated_on`.
+    fn selected_commit_summary(&mut self) -> Result<Option<(u64, CommitSummary)>> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let key = Self::pr_key(pr);
+        let (workspace, repo, id) = (pr.workspace.clone(), pr.repo.clone(), pr.id);
+        let updated_on = pr.updated_on.clone();
+        if let Some((fetched_at, summary)) = self.commit_summaries.get(&key)
+            && *fetched_at == updated_on
+        {
+            return Ok(Some((id, summary.clone())));
+        }
+
+        let Some(client) = self.build_client() else {
+            return Ok(None);
+        };
+        let summary = client.pull_request_commits(&workspace, &repo, id)?;
+        self.commit_summaries
+            .insert(key, (updated_on, summary.clone()));
+        Ok(Some((id, summary)))
+    }
+
+    fn show_commits(&mut self) -> Result<()> {
+        let Some((id, summary)) = self.selected_commit_summary()? else {
+            return Ok(());
+        };
+        self.mark_selected_visited();
+        let message = match (summary.count, summary.latest_subject) {
+            (Some(0), _) | (None, None) => format!("PR #{id} has no commits."),
+            (Some(count), Some(subject)) => {
+                format!("PR #{id} has {count} commit(s); latest: {subject}")
+            }
+            (Some(count), None) => format!("PR #{id} has {count} commit(s)."),
+            (None, Some(subject)) => format!("PR #{id} latest commit: {subject}"),
+        };
+        self.log(&message);
+        Ok(())
+    }
+
+    /// Compares the selected PR's branch with its destination and logs how
+    /// far behind it is. PRs whose branches the destination repo does not
+    /// know, such as ones from forks, are reported as unavailable.
+    fn show_divergence(&mut self) {
+        let Some(pr) = self.selected_pull_request() else {
+            self.log("No pull request selected.");
+            return;
+        };
+        let (key, workspace, repo, id) = (
+            Self::pr_key(pr),
+            pr.workspace.clone(),
+            pr.repo.clone(),
+            pr.id,
+        );
+        let (Some(source), Some(destination)) =
+            (pr.source_branch.clone(), pr.destination_branch.clone())
+        else {
+            self.log(&format!(
+                "Ahead/behind is not available for PR #{id}: its branches are unknown."
+            ));
+            return;
+        };
+        let Some(client) = self.build_client() else {
+            return;
+        };
+
+        match client.branch_divergence(&workspace, &repo, &source, &destination) {
+            Ok(divergence) => {
+                self.divergences.insert(key, divergence);
+                self.log(&if divergence.behind.count > 0 {
+                    format!(
+                        "PR #{id} is behind {destination} by {} commit(s) and ahead by {}; it likely needs a rebase.",
+                        divergence.behind, divergence.ahead
+                    )
+                } else {
+                    format!(
+                        "PR #{id} is up to date with {destination} (ahead by {}).",
+                        divergence.ahead
+                    )
+                });
+            }
+            Err(err) => {
+                self.divergences.remove(&key);
+                self.log(&format!(
+                    "Ahead/behind is not available for PR #{id}: {err:#}"
+                ));
+            }
+        }
+    }
+
+    /// Lists the open PRs the user authored that are older than
+    /// `stale_after_days` (or the given number of days) and offers to
+    /// decline them all.
+    fn handle_stale_command(&mut self, args: &[&str]) -> Result<()> {
+        let days = match args.first() {
+            Some(value) => value
+                .parse::<u32>()
+                .map_err(|_| anyhow!("usage: /stale [days]"))?,
+            None => self.config.stale_after_days,
+        };
+        let now = Utc::now();
+        let stale = self
+            .all_pull_requests
+            .iter()
+            .filter(|pr| {
+                pr.state == "OPEN" && matches!(pr.relation, Relation::Authored | Relation::Both)
+            })
+            .filter_map(|pr| {
+                let age = pr.age_days(now)?;
+                (age > i64::from(days)).then_some((pr, age))
+            })
+            .collect::<Vec<_>>();
+
+        if stale.is_empty() {
+            self.log(&format!("No open PRs older than {days} day(s)."));
+            return Ok(());
+        }
+
+        let lines = stale
+            .iter()
+            .map(|(pr, age)| {
+                format!(
+                    "  {}/{} #{} ({age}d) {}",
+                    pr.workspace, pr.repo, pr.id, pr.title
+                )
+            })
+            .collect::<Vec<_>>();
+        let targets = stale
+            .iter()
+            .map(|(pr, _)| BatchTarget {
+                workspace: pr.workspace.clone(),
+                repo: pr.repo.clone(),
+                id: pr.id,
+            })
+            .collect();
+        self.log(&format!(
+            "{} open PR(s) older than {days} day(s):",
+            lines.len()
+        ));
+        for line in &lines {
+            self.log(line);
+        }
+        self.pending_action = Some(PendingAction::DeclineStale { targets });
+        Ok(())
+    }
+
+    fn handle_density_command(&mut self, args: &[&str]) -> Result<()> {
+        let spacing = match args.first() {
+            Some(value) => value.parse::<ListSpacing>()?,
+            None => match self.config.list_spacing {
+                ListSpacing::Compact => ListSpacing::Comfortable,
+                ListSpacing::Comfortable => ListSpacing::Compact,
+            },
+        };
+        if spacing != self.config.list_spacing {
+            self.config.list_spacing = spacing;
+            self.config.save()?;
+        }
+        self.log(&format!("List density set to {spacing}."));
+        Ok(())
+    }
+
+    fn handle_waiting_command(&mut self, args: &[&str]) -> Result<()> {
+        let value = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /waiting <days|clear>"))?;
+        self.waiting_filter = if value.eq_ignore_ascii_case("clear") {
+            None
+        } else {
+            Some(
+                value
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("days must be a number or 'clear'"))?
+                    .into(),
+            )
+        };
+
+        self.apply_search_filter();
+        match self.waiting_filter {
+            Some(days) => self.log(&format!(
+                "{} unapproved PR(s) waiting more than {days} day(s) for review.",
+                self.pull_requests.len()
+            )),
+            None => self.log("Waiting filter cleared."),
+        }
+        Ok(())
+    }
+
+    fn handle_size_command(&mut self, args: &[&str]) -> Result<()> {
+        let value = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /size <xs|s|m|l|xl|clear>"))?;
+        self.size_filter = if value.eq_ignore_ascii_case("clear") {
+            None
+        } else {
+            Some(value.parse::<SizeCategory>()?)
+        };
+
+        self.apply_search_filter();
+        match self.size_filter {
+            Some(size) => {
+                self.log(&format!(
+                    "{} PR(s) of size {size}.",
+                    self.pull_requests.len()
+                ));
+                let unsized_count = self
+                    .all_pull_requests
+                    .iter()
+                    .filter(|pr| pr.diffstat.is_none())
+                    .count();
+                if unsized_count > 0 {
+                    self.log(&format!(
+                        "{unsized_count} PR(s) have no diffstat yet and are hidden; run /stats-all to size them."
+                    ));
+                }
+            }
+            None => self.log("Size filter cleared."),
+        }
+        Ok(())
+    }
+
+    fn toggle_mainline_filter(&mut self) {
+        self.mainline_filter = !self.mainline_filter;
+        self.apply_search_filter();
+        if self.mainline_filter {
+            self.log(&format!(
+                "{} PR(s) targeting {}.",
+                self.pull_requests.len(),
+                self.config.default_branch
+            ));
+        } else {
+            self.log("Mainline filter cleared.");
+        }
+    }
+
+    fn toggle_solo_filter(&mut self) {
+        self.solo_filter = !self.solo_filter;
+        self.apply_search_filter();
+        if self.solo_filter {
+            self.log(&format!(
+                "{} PR(s) with no approvals or comments from anyone else.",
+                self.pull_requests.len()
+            ));
+        } else {
+            self.log("Solo filter cleared.");
+        }
+    }
+
+    fn handle_limit_command(&mut self, args: &[&str]) -> Result<()> {
+        let value = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /limit <n|off>"))?;
+        let limit = if value.eq_ignore_ascii_case("off") {
+            None
+        } else {
+            Some(
+                value
+                    .parse::<u32>()
+                    .map_err(|_| anyhow!("limit must be a positive integer or 'off'"))?,
+            )
+        };
+
+        if self.config.set_max_results_per_repo(limit)? {
+            self.config.save()?;
+        }
+
+        match limit {
+            Some(limit) => self.log(&format!("Fetching at most {limit} PR(s) per repo.")),
+            None => self.log("Per-repo PR limit removed."),
+        }
+        Ok(())
+    }
+
+    fn refresh_pull_requests_if_due(&mut self) {
+        if self.reauth.is_some() || self.refresh_job.is_some() {
+            return;
+        }
+        let should_refresh = match self.last_refresh_at {
+            Some(last) => last.elapsed() >= self.effective_refresh_interval(),
+            None => true,
+        };
+
+        if should_refresh {
+            self.refresh_pull_requests(true);
+        }
+    }
+
+    /// The longer of the offline and idle backoffs.
+    fn effective_refresh_interval(&self) -> Duration {
+        self.offline_refresh_interval()
+            .max(self.idle_refresh_interval())
+    }
+
+    /// Doubles the auto-refresh interval for each consecutive offline refresh,
+    /// capped at `MAX_OFFLINE_BACKOFF`, so a dead network isn't hammered.
+    fn offline_refresh_interval(&self) -> Duration {
+        if !self.offline {
+            return self.auto_refresh_interval;
+        }
+
+        let factor = 2u32.saturating_pow(self.offline_refreshes.min(8));
+        self.auto_refresh_interval
+            .saturating_mul(factor)
+            .min(MAX_OFFLINE_BACKOFF.max(self.auto_refresh_interval))
+    }
+
+    /// Doubles the auto-refresh interval for each `idle_backoff_after_seconds`
+    /// without a keystroke, capped at `idle_backoff_max_seconds`. Any key
+    /// resets it to the configured interval.
+    fn idle_refresh_interval(&self) -> Duration {
+        let after = self.config.idle_backoff_after_seconds;
+        if after == 0 {
+            return self.auto_refresh_interval;
+        }
+
+        let idle_periods = self.last_interaction.elapsed().as_secs() / after;
+        let factor = 2u32.saturating_pow(idle_periods.min(8) as u32);
+        let cap = Duration::from_secs(self.config.idle_backoff_max_seconds);
+        self.auto_refresh_interval
+            .saturating_mul(factor)
+            .min(cap.max(self.auto_refresh_interval))
+    }
+
+    /// Starts fetching PRs on a background thread; `poll_refresh_job`
+    /// applies them when they arrive. A refresh already running is
+    /// abandoned, since it was fetching with the old filters.
+    fn refresh_pull_requests(&mut self, notify_updates: bool) {
+        self.last_refresh_at = Some(Instant::now());
+        self.refresh_job = None;
+        if let Some(source) = self.fetch_source() {
+            self.start_refresh(source, notify_updates);
+        }
+    }
+
+    fn start_refresh(&mut self, source: FetchSource, notify_updates: bool) {
+        let started = Instant::now();
+        let (sender, receiver) = mpsc::channel();
+        let mut fetcher = self.fetcher(sender.clone());
+        let status = self.status_filter;
+        thread::spawn(move || {
+            let outcome = fetcher.fetch_pull_requests(&source, status);
+            let _ = sender.send(RefreshMessage::Finished(Box::new(fetcher), outcome));
+        });
+        self.refresh_job = Some(RefreshJob {
+            receiver,
+            started,
+            notify_updates,
+            progress: None,
+        });
+    }
+
+    /// Logs what the background refresh reported since the last call and
+    /// applies its result once it has finished.
+    fn poll_refresh_job(&mut self) {
+        let Some(job) = self.refresh_job.as_mut() else {
+            return;
+        };
+
+        let mut logs = Vec::new();
+        let finished = loop {
+            match job.receiver.try_recv() {
+                Ok(RefreshMessage::Page(Some(repo), page, _)) => {
+                    job.progress = Some(format!("{repo}: page {page}"));
+                }
+                Ok(RefreshMessage::Page(None, _, fetched)) => {
+                    job.progress = Some(format!("fetched {fetched} PR(s)"));
+                }
+                Ok(RefreshMessage::Log(line)) => logs.push(line),
+                Ok(RefreshMessage::Finished(fetcher, outcome)) => {
+                    break Some(Some((fetcher, outcome)));
+                }
+                Err(mpsc::TryRecvError::Empty) => break None,
+                Err(mpsc::TryRecvError::Disconnected) => break Some(None),
+            }
+        };
+        for line in logs {
+            self.log(&line);
+        }
+
+        let Some(finished) = finished else {
+            return;
+        };
+        let Some(job) = self.refresh_job.take() else {
+            return;
+        };
+        let Some((fetcher, outcome)) = finished else {
+            self.log("Refresh stopped unexpectedly.");
+            return;
+        };
+        self.finish_fetch(*fetcher);
+        if self.timing {
+            let summary = timing_summary(job.started.elapsed(), &self.repo_timings);
+            self.log(&summary);
+        }
+        if let Some(outcome) = outcome {
+            self.apply_refresh(outcome, job.notify_updates);
+        }
+    }
+
+    /// Replaces the list with freshly fetched PRs, keeping the selection.
+    fn apply_refresh(&mut self, outcome: FetchOutcome, notify_updates: bool) {
+        let FetchOutcome {
+            pull_requests: all_prs,
+            repo_count,
+            failed_repos,
+        } = outcome;
+
+        // /find results span every state, so they are no baseline for updates.
+        let previous_by_key = if self.find_query.take().is_some() {
+            self.search_query = self.default_search();
+            HashMap::new()
+        } else {
+            self.all_pull_requests
+                .iter()
+                .cloned()
+                .map(|pr| (Self::pr_key(&pr), pr))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let updates = if notify_updates && !previous_by_key.is_empty() {
+            self.collect_refresh_updates(&previous_by_key, &all_prs)
+        } else {
+            Vec::new()
+        };
+
+        let selected_key = self.selected_pull_request().map(Self::pr_key);
+        let previous = std::mem::take(&mut self.all_pull_requests);
+        self.all_pull_requests = merge_pull_requests(previous, all_prs);
+        self.apply_search_filter();
+        self.selected_index = selected_key
+            .and_then(|key| {
+                self.pull_requests
+                    .iter()
+                    .position(|pr| Self::pr_key(pr) == key)
+            })
+            .unwrap_or(0);
+
+        if let Some(query) = &self.search_query {
+            self.log(&format!(
+                "Loaded {} matching PR(s) out of {} total with status '{}' across {} repo(s) | search='{}'",
+                self.pull_requests.len(),
+                self.all_pull_requests.len(),
+                self.status_filter,
+                repo_count,
+                query
+            ));
+        } else {
+            self.log(&format!(
+                "Loaded {} PR(s) with status '{}' across {} repo(s)",
+                self.pull_requests.len(),
+                self.status_filter,
+                repo_count
+            ));
+        }
+
+        if failed_repos > 0 {
+            self.log(&format!("{} repo(s) failed during refresh", failed_repos));
+        }
+
+        if !updates.is_empty() {
+            self.emit_update_notifications(&updates);
+        }
+
+        self.last_refreshed = Some(Local::now());
+    }
+
+    /// Logs that `feature` needs Bitbucket and returns true when the
+    /// session lists PRs from another provider.
+    fn unsupported_by_provider(&mut self, feature: &str) -> bool {
+        let provider = self.config.provider();
+        if provider == Provider::Bitbucket {
+            return false;
+        }
+        self.log(&format!(
+            "{feature} is not supported for {}.",
+            provider.name()
+        ));
+        true
+    }
+
+    fn build_client(&mut self) -> Option<BitbucketClient> {
+        if self.unsupported_by_provider("Bitbucket requests") {
+            return None;
+        }
+        let Some((email, api_token)) = self
+            .config
+            .credentials()
+            .map(|(email, token)| (email.to_string(), token.to_string()))
+        else {
+            self.log("Missing credentials. Set BITBUCKET_EMAIL and BITBUCKET_API_TOKEN.");
+            return None;
+        };
+
+        match BitbucketClient::new(
+            self.config.bitbucket_base_url.clone(),
+            email,
+            api_token,
+            &self.config.tls_options(),
+        ) {
+            Ok(client) => Some(capture::apply(client, self.config.api_capture())),
+            Err(err) => {
+                self.log(&format!("Failed to create Bitbucket client: {err:#}"));
+                None
+            }
+        }
+    }
+
+    /// Client for the configured provider's PR listing.
+    fn fetch_source(&mut self) -> Option<FetchSource> {
+        if self.config.provider() == Provider::Bitbucket {
+            return self.build_client().map(FetchSource::Bitbucket);
+        }
+        match provider::from_config(&self.config) {
+            Ok(provider) => Some(FetchSource::Provider(provider)),
+            Err(err) => {
+                self.log(&format!("{err:#}"));
+                None
+            }
+        }
+    }
+
+    fn fetcher(&self, sender: mpsc::Sender<RefreshMessage>) -> Fetcher {
+        Fetcher {
+            config: self.config.clone(),
+            relation: self.relation,
+            custom_query: self.custom_query.clone(),
+            issue_pattern: self.issue_pattern.clone(),
+            user_id: self.user_id.clone(),
+            offline: self.offline,
+            offline_refreshes: self.offline_refreshes,
+            workspace_repos: self.workspace_repos.clone(),
+            repo_health: self.repo_health.clone(),
+            required_reviewers: self.required_reviewers.clone(),
+            repo_timings: Vec::new(),
+            dead_repos: Vec::new(),
+            token_rejected: false,
+            sender,
+        }
+    }
+
+    /// Takes back the state `fetcher` updated while fetching.
+    fn finish_fetch(&mut self, fetcher: Fetcher) {
+        self.user_id = fetcher.user_id;
+        self.offline = fetcher.offline;
+        self.offline_refreshes = fetcher.offline_refreshes;
+        self.workspace_repos = fetcher.workspace_repos;
+        self.repo_health = fetcher.repo_health;
+        self.required_reviewers = fetcher.required_reviewers;
+        self.repo_timings = fetcher.repo_timings;
+        if fetcher.token_rejected && self.reauth.is_none() {
+            self.log("Bitbucket rejected the API token (401). Enter a new token to continue.");
+            self.reauth = Some(ReauthPrompt::default());
+        }
+        self.offer_dead_repo_removal(fetcher.dead_repos);
+    }
+
+    /// Runs `fetch` on the UI thread, for commands that use its result
+    /// right away, then logs what it reported and takes back its state.
+    fn fetch_now<R>(&mut self, fetch: impl FnOnce(&mut Fetcher) -> R) -> R {
+        let (sender, receiver) = mpsc::channel();
+        let mut fetcher = self.fetcher(sender);
+        let result = fetch(&mut fetcher);
+        for message in receiver.try_iter() {
+            if let RefreshMessage::Log(line) = message {
+                self.log(&line);
+            }
+        }
+        self.finish_fetch(fetcher);
+        result
+    }
+
+    /// Repos whose most recent fetch failed, in key order.
+    fn failed_repos(&self) -> Vec<RepoRef> {
+        let mut failed = self
+            .repo_health
+            .iter()
+            .filter(|(_, health)| health.is_failing())
+            .filter_map(|(repo_key, _)| RepoRef::parse(repo_key).ok())
+            .collect::<Vec<_>>();
+        failed.sort_by_key(ToString::to_string);
+        failed
+    }
+
+    /// Re-fetches only the repos that failed in the last refresh and swaps
+    /// their PRs into the list, leaving every other repo as it was.
+    fn retry_failed_repos(&mut self) {
+        let failed = self.failed_repos();
+        if failed.is_empty() {
+            self.log("No failed repos to retry.");
+            return;
+        }
+        let Some(user_id) = self.user_id.clone() else {
+            self.log("Nothing to retry yet; use /refresh.");
+            return;
+        };
+        let Some(client) = self.build_client() else {
+            return;
+        };
+        let status = self.status_filter;
+        let Some((fetched, still_failing)) = self.fetch_now(|fetcher| {
+            let (mut fetched, still_failing) =
+                fetcher.fetch_per_repo(&client, &user_id, status, &failed)?;
+            fetcher.tag_issue_keys(&mut fetched);
+            fetcher.load_required_reviewers(&client, &fetched);
+            Some((fetched, still_failing))
+        }) else {
+            return;
+        };
+
+        let mut merged = self
+            .all_pull_requests
+            .iter()
+            .filter(|pr| {
+                !failed
+                    .iter()
+                    .any(|repo| repo.workspace == pr.workspace && repo.repo == pr.repo)
+            })
+            .cloned()
+            .chain(fetched)
+            .collect::<Vec<_>>();
+        sort_for_list(&mut merged);
+
+        let selected_key = self.selected_pull_request().map(Self::pr_key);
+        let previous = std::mem::take(&mut self.all_pull_requests);
+        self.all_pull_requests = merge_pull_requests(previous, merged);
+        self.apply_search_filter();
+        self.selected_index = selected_key
+            .and_then(|key| {
+                self.pull_requests
+                    .iter()
+                    .position(|pr| Self::pr_key(pr) == key)
+            })
+            .unwrap_or(0);
+
+        self.log(&format!(
+            "Recovered {} of {} failed repo(s).",
+            failed.len() - still_failing,
+            failed.len()
+        ));
+    }
+
+    /// Required reviewers of `pr` who have not approved yet.
+    fn pending_required_reviewers<'a>(&'a self, pr: &PullRequest) -> Vec<&'a str> {
+        if pr.state != "OPEN" {
+            return Vec::new();
+        }
+        self.required_reviewers
+            .get(&format!("{}/{}", pr.workspace, pr.repo))
+            .map(|required| pr.pending_required_reviewers(required))
+            .unwrap_or_default()
+    }
+
+    fn show_required_reviewers(&mut self) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let message = match self.pending_required_reviewers(pr).as_slice() {
+            [] => format!("PR #{} is not waiting on required reviewers.", pr.id),
+            pending => format!(
+                "PR #{} is waiting on required reviewers: {}",
+                pr.id,
+                pending.join(", ")
+            ),
+        };
+        self.log(&message);
+        Ok(())
+    }
+
+    /// Asks once per session whether to drop configured repos that returned
+    /// 404/403. Repos from watched workspaces or `--local` are left alone.
+    fn offer_dead_repo_removal(&mut self, dead_repos: Vec<RepoRef>) {
+        if !self.config.offer_dead_repo_removal || self.pending_action.is_some() {
+            return;
+        }
+        let repos = dead_repos
+            .into_iter()
+            .filter(|repo| self.config.repos.contains(repo))
+            .filter(|repo| self.offered_repo_removals.insert(repo.to_string()))
+            .collect::<Vec<_>>();
+        if !repos.is_empty() {
+            self.pending_action = Some(PendingAction::RemoveRepos {
+                repos,
+                inaccessible: true,
+            });
+        }
+    }
+
+    fn last_refreshed_label(&self) -> String {
+        let Some(refreshed) = self.last_refreshed else {
+            return "never".to_string();
+        };
+
+        let elapsed = (Local::now() - refreshed).num_seconds().max(0);
+        let relative = match elapsed {
+            0..60 => "just now".to_string(),
+            60..3600 => format!("{}m ago", elapsed / 60),
+            _ => format!("{}h ago", elapsed / 3600),
+        };
+        format!("{} ({relative})", refreshed.format("%H:%M"))
+    }
+
+    fn open_pull_request(&mut self, index: usize) -> Result<()> {
+        if index == 0 {
+            return Err(anyhow!("pull request index must be >= 1"));
+        }
+        let zero_index = index - 1;
+
+        let Some(pr) = self.pull_requests.get(zero_index) else {
+            return Err(anyhow!("no pull request at index {index}"));
+        };
+
+        let (url, label) = (
+            pr.url.clone(),
+            format!("{}/{} PR #{}", pr.workspace, pr.repo, pr.id),
+        );
+        self.visited.insert(Self::pr_key(pr), pr.updated_on.clone());
+        self.open_url(&url)?;
+        self.log(&format!("Opened {label} in browser."));
+        Ok(())
+    }
+
+    fn is_visited(&self, pr: &PullRequest) -> bool {
+        self.visited.get(&Self::pr_key(pr)) == Some(&pr.updated_on)
+    }
+
+    fn mark_selected_visited(&mut self) {
+        if let Some(pr) = self.selected_pull_request() {
+            let (key, updated_on) = (Self::pr_key(pr), pr.updated_on.clone());
+            self.visited.insert(key, updated_on);
+        }
+    }
+
+    fn mark_all_read(&mut self) {
+        let count = self.visited.len();
+        self.visited.clear();
+        self.log(&format!("Cleared {count} visited marker(s)."));
+    }
+
+    /// Opens `url` with the configured `browser_command` or the default
+    /// browser, logging the command line when a custom command is used.
+    fn open_url(&mut self, url: &str) -> Result<()> {
+        if let Some(command) = browser::open(url, self.config.browser_command.as_deref())? {
+            self.log(&format!("Launched: {command}"));
+        }
+        Ok(())
+    }
+
+    /// Opens the pull request list of the selected PR's repo. On a collapsed
+    /// group the selection sits on the header, so this opens that group's repo.
+    fn open_repo(&mut self) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let (url, label) = (
+            pr.repo_pull_requests_url(),
+            format!("{}/{}", pr.workspace, pr.repo),
+        );
+        self.open_url(&url)?;
+        self.log(&format!("Opened {label} pull requests in browser."));
+        Ok(())
+    }
+
+    /// Opens a linked issue of the selected PR: the first one, or the one
+    /// given by key or 1-based position.
+    fn open_issue(&mut self, which: Option<&str>) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let template = self.config.issue_url_template.clone().ok_or_else(|| {
+            anyhow!("set issue_url_template in config.toml, e.g. https://jira.example.com/browse/{{key}}")
+        })?;
+        if pr.issue_keys.is_empty() {
+            self.log(&format!("PR #{} has no linked issues.", pr.id));
+            return Ok(());
+        }
+        let key = match which {
+            None => pr.issue_keys[0].clone(),
+            Some(which) => match which.parse::<usize>() {
+                Ok(position) => pr
+                    .issue_keys
+                    .get(position.wrapping_sub(1))
+                    .cloned()
+                    .ok_or_else(|| {
+                        anyhow!("PR #{} has {} linked issue(s)", pr.id, pr.issue_keys.len())
+                    })?,
+                Err(_) => pr
+                    .issue_keys
+                    .iter()
+                    .find(|key| key.eq_ignore_ascii_case(which))
+                    .cloned()
+                    .ok_or_else(|| anyhow!("PR #{} does not link {which}", pr.id))?,
+            },
+        };
+
+        self.open_url(&issues::issue_url(&template, &key))?;
+        self.log(&format!("Opened {key} in browser."));
+        Ok(())
+    }
+
+    /// Copies the selected PR's `#id`, source branch, or URL to the clipboard.
+    fn copy_selected(&mut self, what: &str) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let text = match what {
+            "id" => format!("#{}", pr.id),
+            "branch" => pr
+                .source_branch
+                .clone()
+                .ok_or_else(|| anyhow!("PR #{} has no source branch", pr.id))?,
+            "url" => pr.url.clone(),
+            _ => return Err(anyhow!("usage: /copy <id|branch|url>")),
+        };
+
+        clipboard::copy(&text)?;
+        self.log(&format!("Copied '{text}' to clipboard."));
+        Ok(())
+    }
+
+    /// Fetches the selected PR's diff and opens it in the diff popup.
+    fn show_diff(&mut self) -> Result<()> {
+        let Some(pr) = self.selected_pull_request().cloned() else {
+            self.log("No pull request selected.");
+            return Ok(());
+        };
+        let Some(client) = self.build_client() else {
+            return Ok(());
+        };
+        let diff = client.diff(&pr.workspace, &pr.repo, pr.id, None)?;
+        self.open_diff_view(pr, None, diff);
+        Ok(())
+    }
+
+    fn open_diff_view(&mut self, pull_request: PullRequest, path: Option<String>, diff: String) {
+        self.diff_view = Some(DiffView {
+            pull_request,
+            path,
+            diff,
+            scroll: OverlayScroll::default(),
+        });
+    }
+
+    /// Opens the diff popup on one changed file; `args` is the file's path or
+    /// a unique trailing part of it.
+    fn show_file_diff(&mut self, args: &[&str]) -> Result<()> {
+        if args.is_empty() {
+            self.log("Usage: /file <path>");
+            return Ok(());
+        }
+        let name = args.join(" ");
+        let Some(pr) = self.selected_pull_request().cloned() else {
+            self.log("No pull request selected.");
+            return Ok(());
+        };
+        let Some(client) = self.build_client() else {
+            return Ok(());
+        };
+        let (path, diff) = client.file_diff(&pr.workspace, &pr.repo, pr.id, &name)?;
+        self.open_diff_view(pr, Some(path), diff);
+        Ok(())
+    }
+
+    /// Scrolls the diff popup; Enter opens the PR in the browser and Esc
+    /// closes the popup.
+    fn handle_diff_view_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(view) = self.diff_view.as_mut() else {
+            return Ok(());
+        };
+        if view.scroll.handle_key(key.code) {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.diff_view = None;
+                self.request_quit();
+            }
+            KeyCode::Esc => self.diff_view = None,
+            KeyCode::Enter => {
+                let url = view.pull_request.url.clone();
+                self.diff_view = None;
+                self.open_url(&url)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Copies the selected PR formatted with `standup_template`.
+    fn copy_standup_line(&mut self) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let line = standup_line(&self.config.standup_template, pr);
+        clipboard::copy(&line)?;
+        self.log(&format!("Copied '{line}' to clipboard."));
+        Ok(())
+    }
+
+    /// Writes the filtered list as Markdown to `path`, or copies it to the
+    /// clipboard when no path is given.
+    fn export_markdown(&mut self, path: Option<&str>) -> Result<()> {
+        if self.pull_requests.is_empty() {
+            bail!("no pull requests to export");
+        }
+        let markdown = self.markdown_export();
+        match path {
+            Some(path) => {
+                std::fs::write(path, &markdown)
+                    .with_context(|| format!("failed to write {path}"))?;
+                self.log(&format!(
+                    "Exported {} PR(s) as Markdown to {path}.",
+                    self.pull_requests.len()
+                ));
+            }
+            None => {
+                clipboard::copy(&markdown)?;
+                self.log(&format!(
+                    "Copied {} PR(s) as Markdown to clipboard.",
+                    self.pull_requests.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The filtered PRs as a Markdown list under one heading per group, in
+    /// list order.
+    fn markdown_export(&self) -> String {
+        let mut markdown = String::new();
+        let mut current_group: Option<String> = None;
+        for pr in &self.pull_requests {
+            let group_key = self.group_key(pr);
+            if current_group.as_deref() != Some(group_key.as_str()) {
+                if current_group.is_some() {
+                    markdown.push('\n');
+                }
+                markdown.push_str(&format!("## {group_key}\n\n"));
+                current_group = Some(group_key);
+            }
+            let title = pr.title.replace('[', "\\[").replace(']', "\\]");
+            markdown.push_str(&format!(
+                "- [#{} {}]({}) - {}\n",
+                pr.id, title, pr.url, pr.state
+            ));
+        }
+        markdown
+    }
+
+    /// Opens the build/pipeline page of the selected PR, looking the URL up
+    /// from its build statuses the first time and remembering it afterwards.
+    fn open_pipeline(&mut self) -> Result<()> {
+        let pr = self
+            .selected_pull_request()
+            .ok_or_else(|| anyhow!("no pull request selected"))?;
+        let (key, workspace, repo, id) = (
+            Self::pr_key(pr),
+            pr.workspace.clone(),
+            pr.repo.clone(),
+            pr.id,
+        );
+
+        let build_url = match pr.build_url.clone() {
+            Some(url) => Some(url),
+            None => {
+                let Some(client) = self.build_client() else {
+                    return Ok(());
+                };
+                let url = client.latest_build_url(&workspace, &repo, id)?;
+                if let Some(url) = &url {
+                    for pr in self
+                        .all_pull_requests
+                        .iter_mut()
+                        .chain(self.pull_requests.iter_mut())
+                        .filter(|pr| Self::pr_key(pr) == key)
+                    {
+                        pr.build_url = Some(url.clone());
+                    }
+                }
+                url
+            }
+        };
+
+        let Some(build_url) = build_url else {
+            self.log(&format!(
+                "No build or pipeline reported yet for {workspace}/{repo} PR #{id}."
+            ));
+            return Ok(());
+        };
+
+        self.open_url(&build_url)?;
+        self.log(&format!(
+            "Opened pipeline for {workspace}/{repo} PR #{id} in browser."
+        ));
+        Ok(())
+    }
+
+    /// Opens the `number`-th PR (1-based, as shown in the list) of the repo
+    /// group that contains the current selection.
+    fn open_pull_request_in_focused_group(&mut self, number: usize) -> Result<()> {
+        let Some(focused) = self.pull_requests.get(
+            self.selected_index
+                .min(self.pull_requests.len().saturating_sub(1)),
+        ) else {
+            return Err(anyhow!("no pull requests loaded"));
+        };
+        let group = self.group_key(focused);
+
+        let index = self
+            .pull_requests
+            .iter()
+            .enumerate()
+            .filter(|(_, pr)| self.group_key(pr) == group)
+            .map(|(index, _)| index)
+            .nth(number.wrapping_sub(1))
+            .ok_or_else(|| anyhow!("{group} has no PR numbered {number}"))?;
+
+        self.selected_index = index;
+        self.open_pull_request(index + 1)
+    }
+
+    fn handle_find_command(&mut self, args: &[&str]) -> Result<()> {
+        let query = args.join(" ").trim().to_string();
+        if query.is_empty() {
+            return Err(anyhow!("usage: /find <text|pr-number>"));
+        }
+
+        let Some(source) = self.fetch_source() else {
+            return Ok(());
+        };
+        // A refresh still running would replace these results with the
+        // status-filtered list.
+        self.refresh_job = None;
+        let Some(outcome) =
+            self.fetch_now(|fetcher| fetcher.fetch_pull_requests(&source, PrStatus::All))
+        else {
+            return Ok(());
+        };
+
+        self.selected_index = 0;
+        self.all_pull_requests = outcome.pull_requests;
+        self.search_query = Some(query.clone());
+        self.find_query = Some(query.clone());
+        self.apply_search_filter();
+        self.log(&format!(
+            "Found {} PR(s) matching '{query}' across all states. The next refresh restores the '{}' filter.",
+            self.pull_requests.len(),
+            self.status_filter
+        ));
+        Ok(())
+    }
+
+    fn handle_search_command(&mut self, args: &[&str]) {
+        let query = args.join(" ").trim().to_string();
+        if query.is_empty() || query.eq_ignore_ascii_case("clear") {
+            self.search_query = None;
+            self.apply_search_filter();
+            self.log("Search cleared.");
+            return;
+        }
+        if query.eq_ignore_ascii_case("reset") {
+            self.search_query = self.default_search();
+            self.apply_search_filter();
+            match &self.search_query {
+                Some(query) => self.log(&format!(
+                    "Search reset to default '{query}'. {} matching PR(s).",
+                    self.pull_requests.len()
+                )),
+                None => self.log("No default_search configured; search cleared."),
+            }
+            return;
+        }
+
+        self.search_query = Some(query.clone());
+        self.apply_search_filter();
+        self.log(&format!(
+            "Search set to '{query}'. {} matching PR(s).",
+            self.pull_requests.len()
+        ));
+    }
+
+    fn handle_search_scope_command(&mut self, args: &[&str]) -> Result<()> {
+        let scope = args
+            .first()
+            .ok_or_else(|| anyhow!("usage: /search-scope <title|all>"))?
+            .parse::<SearchScope>()?;
+        if scope != self.config.search_scope {
+            self.config.search_scope = scope;
+            self.config.save()?;
+        }
+        self.apply_search_filter();
+        self.log(&match scope {
+            SearchScope::Title => "Search now matches PR titles only.".to_string(),
+            SearchScope::All => "Search now matches titles and descriptions.".to_string(),
+        });
+        Ok(())
+    }
+
+    /// Query typed so far in a `/search ` command, if the input is one.
+    fn live_search_input(&self) -> Option<&str> {
+        self.input.strip_prefix("/search ").map(str::trim)
+    }
+
+    fn note_input_edit(&mut self) {
+        self.live_search_edited_at =
+            if self.config.live_search && self.live_search_input().is_some() {
+                Some(Instant::now())
+            } else {
+                None
+            };
+    }
+
+    /// Applies the `/search` query being typed once no key has been pressed
+    /// for `LIVE_SEARCH_DEBOUNCE`. Enter still commits the search as usual.
+    fn apply_live_search_if_due(&mut self, now: Instant) {
+        let Some(edited_at) = self.live_search_edited_at else {
+            return;
+        };
+        if now.duration_since(edited_at) < LIVE_SEARCH_DEBOUNCE {
+            return;
+        }
+        self.live_search_edited_at = None;
+
+        let Some(query) = self.live_search_input() else {
+            return;
+        };
+        let query = (!query.is_empty()).then(|| query.to_string());
+        if query != self.search_query {
+            self.search_query = query;
+            self.apply_search_filter();
+        }
+    }
+
+    /// How long the event loop may block waiting for input; shortened while a
+    /// live search is pending so the filter lands right after the debounce.
+    fn poll_timeout(&self) -> Duration {
+        match self.live_search_edited_at {
+            Some(edited_at) => LIVE_SEARCH_DEBOUNCE
+                .saturating_sub(edited_at.elapsed())
+                .max(Duration::from_millis(10)),
+            // Start the first refresh right after the first paint.
+            None if self.last_refresh_at.is_none() && self.reauth.is_none() => Duration::ZERO,
+            None if self.stats_job.is_some() || self.refresh_job.is_some() => {
+                Duration::from_millis(50)
+            }
+            None => Duration::from_millis(200),
+        }
+    }
+
+    /// The configured `default_search`, if set to something non-blank.
+    fn default_search(&self) -> Option<String> {
+        self.config
+            .default_search
+            .as_ref()
+            .map(|query| query.trim().to_string())
+            .filter(|query| !query.is_empty())
+    }
+
+    fn is_default_search(&self) -> bool {
+        self.search_query.is_some() && self.search_query == self.default_search()
+    }
+
+    fn clear_search_filter_if_active(&mut self) {
+        // The default search is a standing filter, not a one-off search.
+        if self.search_query.is_none() || self.is_default_search() {
+            return;
+        }
+        self.search_query = None;
+        self.apply_search_filter();
+        self.log("Search cleared due to non-search command.");
+    }
+
+    fn log(&mut self, message: &str) {
+        debug_log::record("INFO", message);
+        self.logs.push(message.to_string());
+    }
+
+    fn grouped_rows(&self) -> (Vec<ListRow>, Option<usize>) {
+        let now = Utc::now();
+        let mut rows = Vec::new();
+        let mut selected_row = None;
+        let mut current_repo: Option<String> = None;
+        let mut repo_pr_index = 0usize;
+        let selected_pr_index = self
+            .selected_index
+            .min(self.pull_requests.len().saturating_sub(1));
+
+        let mut repo_counts = std::collections::HashMap::<String, usize>::new();
+        for pr in &self.pull_requests {
+            *repo_counts.entry(self.group_key(pr)).or_insert(0) += 1;
+        }
+
+        for (pr_index, pr) in self.pull_requests.iter().enumerate() {
+            let repo_key = format!("{}/{}", pr.workspace, pr.repo);
+            let group_key = self.group_key(pr);
+            let pinned = group_key == PINNED_GROUP;
+            if current_repo.as_deref() != Some(group_key.as_str()) {
+                if current_repo.is_some() {
+                    if self.config.list_spacing == ListSpacing::Comfortable {
+                        rows.push(ListRow::spacer(String::new()));
+                    }
+                    if self.config.group_separators {
+                        rows.push(ListRow::spacer("\u{2500}".repeat(40)));
+                    }
+                }
+                repo_pr_index = 0;
+                let count = repo_counts.get(&group_key).copied().unwrap_or(0);
+                let label = if count == 1 { "PR" } else { "PRs" };
+                let health = self.repo_health.get(&group_key).and_then(RepoHealth::badge);
+                let fold = if self.collapsed.contains(&group_key) {
+                    "\u{25b8} "
+                } else {
+                    ""
+                };
+                rows.push(ListRow::header(
+                    format!("{}{} ({} {}):", fold, group_key, count, label),
+                    group_key.clone(),
+                    health,
+                ));
+                current_repo = Some(group_key.clone());
+            }
+
+            repo_pr_index += 1;
+            if self.collapsed.contains(&group_key) {
+                if pr_index == selected_pr_index {
+                    selected_row = Some(rows.len() - 1);
+                }
+                continue;
+            }
+            if pr_index == selected_pr_index {
+                selected_row = Some(rows.len());
+            }
+
+            let size = pr
+                .diffstat
+                .map(|diffstat| format!(" | +{}/-{}", diffstat.lines_added, diffstat.lines_removed))
+                .unwrap_or_default();
+            let mut marker = if pinned {
+                format!("\u{1f4cc} {repo_key} ")
+            } else {
+                String::new()
+            };
+            let own = self.relation != Relation::Authored
+                && matches!(pr.relation, Relation::Authored | Relation::Both);
+            match self.relation {
+                Relation::Both => marker.insert_str(0, &format!("{} ", pr.relation.marker())),
+                Relation::Reviewing if own => marker.insert_str(0, "(mine) "),
+                _ => {}
+            }
+            if !self.pending_required_reviewers(pr).is_empty() {
+                marker.insert(0, '!');
+            }
+            let issues = pr
+                .issue_keys
+                .iter()
+                .map(|key| format!(" [{key}]"))
+                .collect::<String>();
+            let waiting = pr
+                .waiting_for_review_days(now)
+                .filter(|days| *days > 0)
+                .map(|days| format!(" - waiting {days}d for review"))
+                .unwrap_or_else(|| {
+                    if pr.source_branch_left_behind() {
+                        " - branch not deleted".to_string()
+                    } else {
+                        String::new()
+                    }
+                });
+            let behind = self
+                .divergences
+                .get(&Self::pr_key(pr))
+                .filter(|divergence| divergence.behind.count > 0)
+                .map(|divergence| format!(" - behind by {}", divergence.behind))
+                .unwrap_or_default();
+            rows.push(ListRow {
+                text: format!(
+                    "{}. {}#{} [{} | comments:{}{}] {} ({}){}{}{}",
+                    repo_pr_index,
+                    marker,
+                    pr.id,
+                    pr.state,
+                    pr.comment_count,
+                    size,
+                    pr.title,
+                    pr.author,
+                    issues,
+                    waiting,
+                    behind
+                ),
+                is_header: false,
+                is_spacer: false,
+                health: None,
+                repo_key,
+                author: Some(pr.author.clone()),
+                own,
+                visited: self.is_visited(pr),
+                size: pr.size_category(&self.config.size_thresholds),
+            });
+        }
+
+        // Failing repos have no PRs to group, so list their headers last.
+        let mut failing = self
+            .repo_health
+            .iter()
+            .filter(|(repo_key, health)| {
+                health.is_failing() && !repo_counts.contains_key(*repo_key)
+            })
+            .collect::<Vec<_>>();
+        failing.sort_by(|left, right| left.0.cmp(right.0));
+        for (repo_key, health) in failing {
+            if !rows.is_empty() && self.config.list_spacing == ListSpacing::Comfortable {
+                rows.push(ListRow::spacer(String::new()));
+            }
+            rows.push(ListRow::header(
+                format!("{repo_key} (0 PRs):"),
+                repo_key.clone(),
+                health.badge(),
+            ));
+        }
+
+        (rows, selected_row)
+    }
+
+    /// Color for a repo group: the `[repo_colors]` entry when configured,
+    /// otherwise a stable pick from `REPO_PALETTE` hashed from the repo name.
+    fn repo_color(&self, repo_key: &str) -> Color {
+        if let Some(color) = self
+            .config
+            .repo_colors
+            .get(repo_key)
+            .and_then(|value| Color::from_str(value).ok())
+        {
+            return color;
+        }
+
+        palette_color(repo_key)
+    }
+
+    /// Rebuilds the visible list from `all_pull_requests`, applying the
+    /// search query, approval filter, and waiting filter together.
+    fn apply_search_filter(&mut self) {
+        let terms = self
+            .search_query
+            .as_deref()
+            .map(search_terms)
+            .unwrap_or_default();
+        let approval_filter = self.approval_filter;
+        let waiting_filter = self.waiting_filter;
+        let size_filter = self.size_filter;
+        let size_thresholds = self.config.size_thresholds;
+        let solo_filter = self.solo_filter;
+        let mainline_branch = self
+            .mainline_filter
+            .then(|| self.config.default_branch.clone());
+        let search_scope = self.config.search_scope;
+        let now = Utc::now();
+
+        self.pull_requests = self
+            .all_pull_requests
+            .iter()
+            .filter(|pr| approval_filter.is_none_or(|filter| filter.matches(pr)))
+            .filter(|pr| !solo_filter || pr.is_solo())
+            .filter(|pr| {
+                size_filter.is_none_or(|size| pr.size_category(&size_thresholds) == Some(size))
+            })
+            .filter(|pr| {
+                mainline_branch
+                    .as_deref()
+                    .is_none_or(|branch| pr.targets_default_branch(branch))
+            })
+            .filter(|pr| {
+                waiting_filter.is_none_or(|days| {
+                    pr.waiting_for_review_days(now)
+                        .is_some_and(|waiting| waiting > days)
+                })
+            })
+            .filter(|pr| {
+                if terms.is_empty() {
+                    return true;
+                }
+                let id = pr.id.to_string();
+                let searchable = match search_scope {
+                    SearchScope::Title => pr.title.to_ascii_lowercase(),
+                    SearchScope::All => {
+                        format!("{} {}", pr.title, pr.description).to_ascii_lowercase()
+                    }
+                };
+                // Exclusions also look at the author, so bots can be hidden.
+                let author = pr.author.to_ascii_lowercase();
+                terms.iter().all(|term| {
+                    let text = term.text.as_str();
+                    if term.exclude {
+                        !searchable.contains(text) && !author.contains(text)
+                    } else {
+                        id.contains(text) || searchable.contains(text)
+                    }
+                })
+            })
+            .cloned()
+            .collect();
+        if !self.pinned.is_empty() {
+            let (mut pinned, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pull_requests)
+                .into_iter()
+                .partition(|pr| self.pinned.contains(&Self::pr_key(pr)));
+            pinned.extend(rest);
+            self.pull_requests = pinned;
+        }
+
+        self.selected_index = self
+            .selected_index
+            .min(self.pull_requests.len().saturating_sub(1));
+    }
+
+    /// Group a PR is listed under: the pinned group, or its repo.
+    fn group_key(&self, pr: &PullRequest) -> String {
+        if self.pinned.contains(&Self::pr_key(pr)) {
+            PINNED_GROUP.to_string()
+        } else {
+            format!("{}/{}", pr.workspace, pr.repo)
+        }
+    }
+
+    fn command_query(&self) -> Option<&str> {
+        let trimmed = self.input.trim_start();
+        if !trimmed.starts_with('/') {
+            return None;
+        }
+        if trimmed.ends_with(' ') {
+            return None;
+        }
+
+        let mut parts = trimmed.split_whitespace();
+        let command = parts.next().unwrap_or_default();
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(command)
+    }
+
+    fn command_suggestions(&self) -> Vec<CommandSpec> {
+        let Some(query) = self.command_query() else {
+            return Vec::new();
+        };
+
+        let read_only = self.config.is_read_only();
+        COMMAND_SPECS
+            .iter()
+            .copied()
+            .filter(|spec| spec.name.starts_with(query) && !(read_only && spec.writes))
+            .collect()
+    }
+
+    fn has_command_suggestions(&self) -> bool {
+        !self.command_suggestions().is_empty()
+    }
+
+    fn move_command_selection(&mut self, direction: i32) {
+        let suggestions = self.command_suggestions();
+        if suggestions.is_empty() {
+            self.command_suggestion_index = 0;
+            return;
+        }
+
+        let len = suggestions.len() as i32;
+        let next = (self.command_suggestion_index as i32 + direction).rem_euclid(len);
+        self.command_suggestion_index = next as usize;
+    }
+
+    fn apply_command_completion(&mut self) -> bool {
+        let suggestions = self.command_suggestions();
+        if suggestions.is_empty() {
+            return false;
+        }
+
+        let selected = suggestions[self.command_suggestion_index.min(suggestions.len() - 1)];
+        self.input = if selected.accepts_args {
+            format!("{} ", selected.name)
+        } else {
+            selected.name.to_string()
+        };
+        self.command_suggestion_index = 0;
+        true
+    }
+
+    fn apply_command_completion_if_partial(&mut self) -> bool {
+        let Some(query) = self.command_query() else {
+            return false;
+        };
+
+        let suggestions = self.command_suggestions();
+        if suggestions.is_empty() {
+            return false;
+        }
+
+        let selected = suggestions[self.command_suggestion_index.min(suggestions.len() - 1)];
+        if query == selected.name {
+            return false;
+        }
+
+        self.input = if selected.accepts_args {
+            format!("{} ", selected.name)
+        } else {
+            selected.name.to_string()
+        };
+        self.command_suggestion_index = 0;
+        true
+    }
+
+    fn collect_refresh_updates(
+        &self,
+        previous_by_key: &HashMap<String, PullRequest>,
+        latest_pull_requests: &[PullRequest],
+    ) -> Vec<String> {
+        let mut updates = Vec::new();
+        let mut latest_keys = HashSet::new();
+
+        for pr in latest_pull_requests {
+            let key = Self::pr_key(pr);
+            latest_keys.insert(key.clone());
+
+            match previous_by_key.get(&key) {
+                Some(previous_pr) => {
+                    let mut has_specific_change = false;
+
+                    if previous_pr.comment_count != pr.comment_count {
+                        let delta = pr.comment_count as i64 - previous_pr.comment_count as i64;
+                        let signed_delta = if delta > 0 {
+                            format!("+{delta}")
+                        } else {
+                            delta.to_string()
+                        };
+                        updates.push(format!(
+                            "PR {}/{} #{} comments: {} -> {} ({signed_delta}).",
+                            pr.workspace,
+                            pr.repo,
+                            pr.id,
+                            previous_pr.comment_count,
+                            pr.comment_count
+                        ));
+                        has_specific_change = true;
+                    }
+
+                    if previous_pr.state != pr.state {
+                        updates.push(format!(
+                            "PR {}/{} #{} state: {} -> {}.",
+                            pr.workspace, pr.repo, pr.id, previous_pr.state, pr.state
+                        ));
+                        has_specific_change = true;
+                    }
+
+                    if !has_specific_change && previous_pr.updated_on != pr.updated_on {
+                        updates.push(format!(
+                            "PR {}/{} #{} has new activity.",
+                            pr.workspace, pr.repo, pr.id
+                        ));
+                    }
+                }
+                None => updates.push(format!(
+                    "New PR detected: {}/{} #{} {}.",
+                    pr.workspace, pr.repo, pr.id, pr.title
+                )),
+            }
+        }
+
+        for (key, previous_pr) in previous_by_key {
+            if latest_keys.contains(key) {
+                continue;
+            }
+            updates.push(format!(
+                "PR {}/{} #{} no longer in '{}' results.",
+                previous_pr.workspace, previous_pr.repo, previous_pr.id, self.status_filter
+            ));
+        }
+
+        updates
+    }
+
+    fn emit_update_notifications(&mut self, updates: &[String]) {
+        self.log(&format!("{} PR update(s) detected.", updates.len()));
+        for update in updates.iter().take(MAX_LOGGED_UPDATES) {
+            self.log(update);
+        }
+        if updates.len() > MAX_LOGGED_UPDATES {
+            self.log(&format!(
+                "...and {} more update(s).",
+                updates.len() - MAX_LOGGED_UPDATES
+            ));
+        }
+        self.ring_terminal_bell();
+    }
+
+    fn ring_terminal_bell(&self) {
+        let _ = io::stdout().write_all(b"\x07");
+        let _ = io::stdout().flush();
+    }
+
+    fn pr_key(pr: &PullRequest) -> String {
+        format!("{}/{}/{}", pr.workspace, pr.repo, pr.id)
+    }
+}
+
+impl Fetcher {
+    /// Fetches PRs with `status` from every configured repo, logging per-repo
+    /// failures. Returns `None` when nothing could be fetched at all.
+    fn fetch_pull_requests(
+        &mut self,
+        source: &FetchSource,
+        status: PrStatus,
+    ) -> Option<FetchOutcome> {
+        let client = match source {
+            FetchSource::Bitbucket(client) => client,
+            FetchSource::Provider(provider) => {
+                return self.fetch_from_provider(provider.as_ref(), status);
+            }
+        };
+        if !self.config.has_repo_sources() {
+            self.log("No repos configured. Add repos via /repo add <workspace>/<repo>.");
+            return None;
+        }
+
+        let mut user_ids = match client.current_user_ids(self.config.author_identifier) {
+            Ok(user_ids) => user_ids,
+            Err(err) if is_connection_error(&err) => {
+                self.mark_offline();
+                return None;
+            }
+            Err(err) if http_status(&err) == Some(StatusCode::UNAUTHORIZED) => {
+                self.token_rejected = true;
+                return None;
+            }
+            Err(err) => {
+                self.log(&format!("Failed to fetch current user: {err}"));
+                return None;
+            }
+        };
+
+        // Start with the identifier that matched last time.
+        if let Some(position) = user_ids
+            .iter()
+            .position(|user_id| Some(user_id) == self.user_id.as_ref())
+        {
+            user_ids[..=position].rotate_right(1);
+        }
+
+        let repos = self.resolve_repos(client);
+        if repos.is_empty() {
+            self.log("Watched workspaces have no repos to query.");
+            return None;
+        }
+        self.repo_health
+            .retain(|repo_key, _| repos.iter().any(|repo| repo.to_string() == *repo_key));
+
+        let mut all_prs = Vec::new();
+        let mut failed_repos = 0;
+        for (attempt, user_id) in user_ids.iter().enumerate() {
+            // The cross-repo listing only covers authored PRs.
+            (all_prs, failed_repos) = match self.config.fetch_strategy {
+                FetchStrategy::Aggregate if self.relation == Relation::Authored => {
+                    self.fetch_aggregate(client, user_id, status, &repos)?
+                }
+                _ => self.fetch_per_repo(client, user_id, status, &repos)?,
+            };
+            if attempt == 0 || !all_prs.is_empty() {
+                self.user_id = Some(user_id.clone());
+            }
+            if !all_prs.is_empty() {
+                if attempt > 0 {
+                    self.log(&format!(
+                        "No PRs matched by {}; found them by {} instead.",
+                        user_ids[0].query_field(),
+                        user_id.query_field()
+                    ));
+                }
+                break;
+            }
+        }
+
+        sort_for_list(&mut all_prs);
+        self.tag_issue_keys(&mut all_prs);
+        self.load_required_reviewers(client, &all_prs);
+
+        Some(FetchOutcome {
+            pull_requests: all_prs,
+            repo_count: repos.len(),
+            failed_repos,
+        })
+    }
+
+    fn tag_issue_keys(&self, pull_requests: &mut [PullRequest]) {
+        if let Some(pattern) = &self.issue_pattern {
+            for pr in pull_requests {
+                pr.issue_keys = issues::issue_keys(pattern, pr);
+            }
+        }
+    }
+
+    /// Looks up required reviewers once per session for each repo with
+    /// open PRs, several repos at once. Repos whose lookup fails (e.g.
+    /// missing permission) are treated as having none, so the `!` marker
+    /// simply stays off.
+    fn load_required_reviewers(&mut self, client: &BitbucketClient, pull_requests: &[PullRequest]) {
+        let mut repos = pull_requests
+            .iter()
+            .filter(|pr| pr.state == "OPEN")
+            .map(|pr| (pr.workspace.clone(), pr.repo.clone()))
+            .filter(|(workspace, repo)| {
+                !self
+                    .required_reviewers
+                    .contains_key(&format!("{workspace}/{repo}"))
+            })
+            .collect::<Vec<_>>();
+        repos.sort();
+        repos.dedup();
+
+        let results = fetch_concurrently(&repos, |(workspace, repo)| {
+            client.required_reviewers(workspace, repo)
+        });
+        for ((workspace, repo), (_, result)) in repos.into_iter().zip(results) {
+            let repo_key = format!("{workspace}/{repo}");
+            let required = result.unwrap_or_else(|err| {
+                debug_log::record(
+                    "WARN",
+                    &format!("required reviewers unavailable for {repo_key}: {err:#}"),
+                );
+                RequiredReviewers::default()
+            });
+            self.required_reviewers.insert(repo_key, required);
+        }
+    }
+
+    /// Queries each repo for the user's PRs, `FETCH_WORKERS` repos at a
+    /// time. Returns the PRs and the number of repos that failed, or `None`
+    /// when every repo was unreachable.
+    fn fetch_per_repo(
+        &mut self,
+        client: &BitbucketClient,
+        user_id: &UserId,
+        status: PrStatus,
+        repos: &[RepoRef],
+    ) -> Option<(Vec<PullRequest>, usize)> {
+        let max_results = self.config.max_results_per_repo();
+        let custom_query = self.custom_query.clone();
+        let query = PullRequestQuery {
+            user: user_id,
+            relation: self.relation,
+            status,
+            max_results,
+            custom_query: custom_query.as_deref(),
+        };
+
+        // Results come back in config order so logs and health read as
+        // before.
+        let sender = &self.sender;
+        let results = fetch_concurrently(repos, |repo| {
+            let pages = Cell::new(0);
+            let batch = client.list_pull_requests(
+                &repo.workspace,
+                &repo.repo,
+                &query,
+                |page, fetched| {
+                    pages.set(page);
+                    debug_log::record(
+                        "DEBUG",
+                        &format!("{repo}: fetched {fetched} PR(s), page {page}"),
+                    );
+                    let _ =
+                        sender.send(RefreshMessage::Page(Some(repo.to_string()), page, fetched));
+                },
+            )?;
+            Ok((batch, pages.get()))
+        });
+
+        let mut all_prs = Vec::new();
+        let mut failed_repos = 0usize;
+        let mut unreachable_repos = 0usize;
+        let mut dead_repos = Vec::new();
+        for (repo, (elapsed, result)) in repos.iter().zip(results) {
+            self.repo_timings.push((repo.to_string(), elapsed));
+            let health = self.repo_health.entry(repo.to_string()).or_default();
+            match &result {
+                Ok(_) => health.last_success = Some(Local::now()),
+                Err(err) => health.last_error = Some((Local::now(), format!("{err:#}"))),
+            }
+            match result {
+                Ok((mut batch, pages)) => {
+                    if pages > 1 {
+                        self.log(&format!(
+                            "Fetched {} PR(s) from {repo} in {pages} pages.",
+                            batch.pull_requests.len()
+                        ));
+                    }
+                    if let Some(truncation) = batch.truncated {
+                        self.log(&format!("Results for {repo} {truncation}."));
+                    }
+                    all_prs.append(&mut batch.pull_requests);
+                }
+                Err(err) => {
+                    failed_repos += 1;
+                    match http_status(&err) {
+                        _ if is_connection_error(&err) => unreachable_repos += 1,
+                        Some(StatusCode::NOT_FOUND) => {
+                            self.log(&format!("{repo}: repo not found (renamed or deleted?)"));
+                            dead_repos.push(repo.clone());
+                        }
+                        Some(StatusCode::FORBIDDEN) => {
+                            self.log(&format!("No access to {repo}."));
+                            dead_repos.push(repo.clone());
+                        }
+                        _ => self.log(&format!("Failed loading {}: {err}", repo)),
+                    }
+                }
+            }
+        }
+
+        if unreachable_repos == repos.len() {
+            self.mark_offline();
+            return None;
+        }
+        self.mark_online();
+        if unreachable_repos > 0 {
+            self.log(&format!(
+                "{unreachable_repos} repo(s) unreachable (connection error)"
+            ));
+        }
+
+        self.dead_repos.extend(dead_repos);
+        Some((all_prs, failed_repos))
+    }
+
+    /// Fetches the user's PRs across all repos in one listing and keeps the
+    /// ones in watched repos.
+    fn fetch_aggregate(
+        &mut self,
+        client: &BitbucketClient,
+        user_id: &UserId,
+        status: PrStatus,
+        repos: &[RepoRef],
+    ) -> Option<(Vec<PullRequest>, usize)> {
+        let pages = Cell::new(0);
+        match client.list_my_pull_requests(
+            user_id,
+            status,
+            self.custom_query.as_deref(),
+            |page, fetched| {
+                pages.set(page);
+                debug_log::record("DEBUG", &format!("fetched {fetched} PR(s), page {page}"));
+                let _ = self.sender.send(RefreshMessage::Page(None, page, fetched));
+            },
+        ) {
+            Ok(pull_requests) => {
+                if pages.get() > 1 {
+                    self.log(&format!(
+                        "Fetched {} PR(s) in {} pages.",
+                        pull_requests.len(),
+                        pages.get()
+                    ));
+                }
+                self.mark_online();
+                let watched =
+                    retain_watched(pull_requests, repos, self.config.max_results_per_repo());
+                Some((watched, 0))
+            }
+            Err(err) if is_connection_error(&err) => {
+                self.mark_offline();
+                None
+            }
+            Err(err) => {
+                self.log(&format!("Failed loading pull requests: {err}"));
+                None
+            }
+        }
+    }
+
+    /// Lists the user's PRs from a non-Bitbucket provider, across all repos
+    /// or only the configured ones when there are any.
+    fn fetch_from_provider(
+        &mut self,
+        provider: &dyn PrProvider,
+        status: PrStatus,
+    ) -> Option<FetchOutcome> {
+        if self.relation != Relation::Authored {
+            self.log(&format!(
+                "Only authored PRs can be listed from {}.",
+                self.config.provider().name()
+            ));
+            return None;
+        }
+
+        let result = provider.current_user().and_then(|user| {
+            let pull_requests = provider.list_pull_requests_created_by(&user, status)?;
+            Ok((user, pull_requests))
+        });
+        let (user, pull_requests) = match result {
+            Ok(found) => found,
+            Err(err) if is_connection_error(&err) => {
+                self.mark_offline();
+                return None;
+            }
+            Err(err) => {
+                self.log(&format!("Failed loading pull requests: {err:#}"));
+                return None;
+            }
+        };
+        self.mark_online();
+        self.user_id = Some(user);
+
+        let repos = self.config.repos();
+        let mut pull_requests = if repos.is_empty() {
+            pull_requests
+        } else {
+            retain_watched(pull_requests, &repos, self.config.max_results_per_repo())
+        };
+        sort_for_list(&mut pull_requests);
+        self.tag_issue_keys(&mut pull_requests);
+        let repo_count = pull_requests
+            .iter()
+            .map(|pr| (&pr.workspace, &pr.repo))
+            .collect::<HashSet<_>>()
+            .len();
+
+        Some(FetchOutcome {
+            pull_requests,
+            repo_count,
+            failed_repos: 0,
+        })
+    }
+
+    /// Configured repos plus every repo of the watched workspaces. Workspace
+    /// listings are cached for `WORKSPACE_EXPANSION_TTL` to avoid re-listing
+    /// on every refresh.
+    fn resolve_repos(&mut self, client: &BitbucketClient) -> Vec<RepoRef> {
+        let mut repos = self.config.repos();
+        // Watched workspaces and projects, keyed by `workspace` and
+        // `workspace/PROJECT_KEY` in the expansion cache.
+        let sources = self
+            .config
+            .workspaces()
+            .iter()
+            .map(|workspace| (workspace.clone(), workspace.clone(), None))
+            .chain(self.config.projects().iter().filter_map(|project| {
+                let (workspace, key) = project.split_once('/')?;
+                Some((
+                    project.clone(),
+                    workspace.to_string(),
+                    Some(key.to_string()),
+                ))
+            }))
+            .collect::<Vec<_>>();
+        self.workspace_repos
+            .retain(|source, _| sources.iter().any(|(key, _, _)| key == source));
+
+        for (source, workspace, project_key) in sources {
+            let cached = self
+                .workspace_repos
+                .get(&source)
+                .filter(|(fetched_at, _)| fetched_at.elapsed() < WORKSPACE_EXPANSION_TTL)
+                .map(|(_, repos)| repos.clone());
+
+            let expanded = match cached {
+                Some(expanded) => expanded,
+                None => match client.list_repositories(&workspace, project_key.as_deref()) {
+                    Ok(slugs) => {
+                        let expanded = slugs
+                            .into_iter()
+                            .map(|slug| RepoRef::new(workspace.clone(), slug))
+                            .collect::<Vec<_>>();
+                        self.workspace_repos
+                            .insert(source.clone(), (Instant::now(), expanded.clone()));
+                        expanded
+                    }
+                    Err(err) => {
+                        let kind = if project_key.is_some() {
+                            "project"
+                        } else {
+                            "workspace"
+                        };
+                        self.log(&format!("Failed listing repos in {kind} {source}: {err}"));
+                        // Fall back to a stale listing rather than dropping the source.
+                        self.workspace_repos
+                            .get(&source)
+                            .map(|(_, repos)| repos.clone())
+                            .unwrap_or_default()
+                    }
+                },
+            };
+
+            for repo in expanded {
+                if !repos.contains(&repo) {
+                    repos.push(repo);
+                }
+            }
+        }
+
+        repos
+    }
+
+    fn mark_offline(&mut self) {
+        if !self.offline {
+            self.log(
+                "Offline - showing cached data. Auto refresh will back off until reconnected.",
+            );
+        }
+        self.offline = true;
+        self.offline_refreshes = self.offline_refreshes.saturating_add(1);
+    }
+
+    fn mark_online(&mut self) {
+        if self.offline {
+            self.log("Connection restored.");
+        }
+        self.offline = false;
+        self.offline_refreshes = 0;
+    }
+
+    fn log(&self, message: &str) {
+        let _ = self.sender.send(RefreshMessage::Log(message.to_string()));
+    }
+}
+
+/// Stable color for `key` from `REPO_PALETTE`. FNV-1a keeps the mapping the
+/// same across runs and Rust versions.
+fn palette_color(key: &str) -> Color {
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    REPO_PALETTE[(hash % REPO_PALETTE.len() as u64) as usize]
+}
+
+/// Green for small PRs through red for the largest.
+fn size_color(size: SizeCategory) -> Color {
+    match size {
+        SizeCategory::Xs | SizeCategory::S => Color::Green,
+        SizeCategory::M => Color::Yellow,
+        SizeCategory::L => Color::LightRed,
+        SizeCategory::Xl => Color::Red,
+    }
+}
+
+/// Two-letter badge for an author: first and last name initials, or the
+/// first two letters of a single-word name.
+fn author_initials(name: &str) -> String {
+    let words = name.split_whitespace().collect::<Vec<_>>();
+    let letters = match words.as_slice() {
+        [] => vec!['?', '?'],
+        [single] => single.chars().take(2).collect(),
+        [first, .., last] => first.chars().take(1).chain(last.chars().take(1)).collect(),
+    };
+
+    letters.into_iter().flat_map(char::to_uppercase).collect()
+}
+
+/// Runs `fetch` on each item, up to `FETCH_WORKERS` at once, and returns
+/// how long each call took and its result, in the order of `items`. A call
+/// that panics becomes an error for its item instead of taking down the
+/// refresh.
+fn fetch_concurrently<T, R>(
+    items: &[T],
+    fetch: impl Fn(&T) -> Result<R> + Sync,
+) -> Vec<(Duration, Result<R>)>
+where
+    T: Sync,
+    R: Send,
+{
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..FETCH_WORKERS.min(items.len()))
+            .map(|_| {
+                let (fetch, next) = (&fetch, &next);
+                scope.spawn(move || {
+                    let mut fetched = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        let started = Instant::now();
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| fetch(item)))
+                            .unwrap_or_else(|_| Err(anyhow!("the fetch panicked")));
+                        fetched.push((index, started.elapsed(), result));
+                    }
+                    fetched
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(index, ..)| *index);
+    results
+        .into_iter()
+        .map(|(_, elapsed, result)| (elapsed, result))
+        .collect()
+}
+
+/// Merges a fresh fetch into the current list by `(workspace, repo, id)`:
+/// PRs missing from `new` are dropped, new ones are added, and existing ones
+/// take the fetched data while keeping lazily loaded details (build URL and
+/// diffstat).
+/// The result follows the order of `new`.
+fn merge_pull_requests(old: Vec<PullRequest>, new: Vec<PullRequest>) -> Vec<PullRequest> {
+    let mut old_by_key: HashMap<String, PullRequest> =
+        old.into_iter().map(|pr| (App::pr_key(&pr), pr)).collect();
+    new.into_iter()
+        .map(|mut pr| {
+            if let Some(existing) = old_by_key.remove(&App::pr_key(&pr)) {
+                pr.build_url = pr.build_url.or(existing.build_url);
+                pr.diffstat = pr.diffstat.or(existing.diffstat);
+            }
+            pr
+        })
+        .collect()
+}
+
+/// Header fields followed by the description and the latest comment, both
+/// rendered by `description_lines`; the paragraph wraps them to the overlay
+/// width.
+fn detail_lines(detail: &DetailView, now: DateTime<Utc>) -> Vec<Line<'static>> {
+    let pr = &detail.pull_request;
+    let field = |label: &str, value: &str| {
+        Line::from(vec![
+            Span::styled(format!("{label:<9}"), Style::default().fg(Color::DarkGray)),
+            Span::raw(value.to_string()),
+        ])
+    };
+    let mut lines = vec![
+        Line::styled(
+            pr.title.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        field("Repo", &format!("{}/{} #{}", pr.workspace, pr.repo, pr.id)),
+        field("Author", &pr.author),
+        field("State", &pr.state),
+        field("Updated", &pr.updated_on),
+    ];
+    if let Some(days) = pr.waiting_for_review_days(now) {
+        lines.push(field("Waiting", &format!("{days}d for review")));
+    }
+    if !detail.pending_reviewers.is_empty() {
+        lines.push(field(
+            "Blocked",
+            &format!(
+                "needs approval from {}",
+                detail.pending_reviewers.join(", ")
+            ),
+        ));
+    }
+    lines.extend([field("URL", &pr.url), Line::default()]);
+    if pr.description.trim().is_empty() {
+        lines.push(Line::styled(
+            "No description.",
+            Style::default().fg(Color::DarkGray),
+        ));
+    } else {
+        lines.extend(description_lines(&pr.description));
+    }
+
+    let dim = Style::default().fg(Color::DarkGray);
+    match &detail.latest_comment {
+        Ok(Some(comment)) => {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                format!(
+                    "Latest comment by {} ({}):",
+                    comment.author,
+                    comment.created_on.get(..10).unwrap_or(&comment.created_on)
+                ),
+                dim,
+            ));
+            lines.extend(description_lines(&comment.raw));
+        }
+        Ok(None) => {}
+        Err(err) => {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                format!("Latest comment unavailable: {err}"),
+                dim,
+            ));
+        }
+    }
+    lines
+}
+
+/// Markdown-lite rendering of a PR description: bold headings, bullets as
+/// `•`, quotes and fenced code dimmed, inline code highlighted, and runs of
+/// blank lines collapsed to one. Anything else is shown as written.
+fn description_lines(raw: &str) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in raw.lines() {
+        // Tabs and control characters would throw off the terminal layout.
+        let line = line
+            .replace('\t', "    ")
+            .replace(|c: char| c.is_control(), "");
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(Line::styled(line, dim));
+            continue;
+        }
+        if trimmed.is_empty() {
+            if lines.last().is_some_and(|last: &Line| last.width() > 0) {
+                lines.push(Line::default());
+            }
+            continue;
+        }
+
+        let heading = trimmed.trim_start_matches('#');
+        if heading.len() < trimmed.len() && heading.starts_with(' ') {
+            lines.push(Line::styled(
+                heading.trim().to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            lines.push(Line::styled(
+                format!("\u{2502} {}", quote.trim_start()),
+                dim,
+            ));
+        } else if let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            let indent = &line[..line.len() - trimmed.len()];
+            let mut spans = vec![Span::raw(format!("{indent}\u{2022} "))];
+            spans.extend(inline_code_spans(item));
+            lines.push(Line::from(spans));
+        } else {
+            lines.push(Line::from(inline_code_spans(&line)));
+        }
+    }
+    while lines.last().is_some_and(|last| last.width() == 0) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Splits `text` on paired backticks, highlighting the code between them.
+/// An unpaired backtick is kept as written.
+fn inline_code_spans(text: &str) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('`') {
+        let Some(len) = rest[start + 1..].find('`') else {
+            break;
+        };
+        if start > 0 {
+            spans.push(Span::raw(rest[..start].to_string()));
+        }
+        spans.push(Span::styled(
+            rest[start + 1..start + 1 + len].to_string(),
+            Style::default().fg(Color::Yellow),
+        ));
+        rest = &rest[start + len + 2..];
+    }
+    if !rest.is_empty() {
+        spans.push(Span::raw(rest.to_string()));
+    }
+    spans
+}
+
+fn draw_detail(frame: &mut ratatui::Frame, detail: &DetailView) {
+    let screen = frame.area();
+    let area = centered_rect(
+        screen.width.saturating_sub(8).min(100),
+        screen.height.saturating_sub(4),
+        screen,
+    );
+    let pr = &detail.pull_request;
+    let paragraph =
+        Paragraph::new(Text::from(detail_lines(detail, Utc::now()))).wrap(Wrap { trim: false });
+    let (scroll, percent) = detail.scroll.layout(&paragraph, area);
+    let paragraph = paragraph
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "PR #{} {percent}% (Up/Down/PgUp/PgDn: scroll | Enter: open in browser | Esc: close)",
+            pr.id
+        )))
+        .scroll((scroll, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+/// Unified diff text with added lines green, removed lines red, hunk headers
+/// cyan, and file headers dimmed.
+fn diff_lines(diff: &str) -> Vec<Line<'static>> {
+    if diff.trim().is_empty() {
+        return vec![Line::from(
+            "No textual changes (binary file or mode change only).",
+        )];
+    }
+    diff.lines()
+        .map(|line| {
+            let line = line
+                .replace('\t', "    ")
+                .replace(|c: char| c.is_control(), "");
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Style::default().fg(Color::DarkGray)
+            } else if line.starts_with('+') {
+                Style::default().fg(Color::Green)
+            } else if line.starts_with('-') {
+                Style::default().fg(Color::Red)
+            } else if line.starts_with("@@") {
+                Style::default().fg(Color::Cyan)
+            } else if line.starts_with("diff ") || line.starts_with("index ") {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default()
+            };
+            Line::styled(line, style)
+        })
+        .collect()
+}
+
+fn draw_diff_view(frame: &mut ratatui::Frame, view: &DiffView) {
+    let screen = frame.area();
+    let area = centered_rect(
+        screen.width.saturating_sub(8).min(100),
+        screen.height.saturating_sub(4),
+        screen,
+    );
+    let paragraph = Paragraph::new(Text::from(diff_lines(&view.diff))).wrap(Wrap { trim: false });
+    let (scroll, percent) = view.scroll.layout(&paragraph, area);
+    let subject = match &view.path {
+        Some(path) => format!("PR #{} {path}", view.pull_request.id),
+        None => format!("PR #{} diff", view.pull_request.id),
+    };
+    let paragraph = paragraph
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "{subject} {percent}% (Up/Down/PgUp/PgDn: scroll | Enter: open in browser | Esc: close)"
+        )))
+        .scroll((scroll, 0));
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        App, Comment, CommitSummary, FETCH_WORKERS, FetchSource, HealthBadge, LIVE_SEARCH_DEBOUNCE,
+        PendingAction, READ_ONLY_NOTICE, ReauthPrompt, RepoHealth, RepoManager, StatsJob,
+        StatsMessage, author_initials, description_lines, diff_lines, merge_pull_requests,
+        search_terms, standup_line, timing_summary,
+    };
+    use crate::bitbucket::{
+        ApiResponse, BitbucketClient, CommitCount, DefaultReviewerRule, DiffStat, Divergence,
+        Participant, PullRequest, Relation, RequiredReviewers, Transport, UserId,
+    };
+    use crate::config::{Config, ListSpacing, PrStatus, Provider, RepoRef, SearchScope};
+    use crate::github::GithubClient;
+    use crate::keymap::Keymap;
+    use chrono::{Local, Utc};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::style::{Color, Modifier};
+    use reqwest::{Method, StatusCode, Url};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex, mpsc};
+    use std::time::{Duration, Instant};
+
+    fn pull_request(repo: &str, id: u64, title: &str, author: &str) -> PullRequest {
+        PullRequest {
+            title: title.to_string(),
+            author: author.to_string(),
+            comment_count: 2,
+            created_on: "2026-01-01T00:00:00+00:00".to_string(),
+            updated_on: "2026-01-01T00:00:00+00:00".to_string(),
+            url: format!("https://bitbucket.org/team/{repo}/pull-requests/{id}"),
+            ..PullRequest::fixture(repo, id)
+        }
+    }
+
+    fn app_with(pull_requests: Vec<PullRequest>) -> App {
+        let mut app = App::new(Config::default());
+        app.all_pull_requests = pull_requests;
+        app.apply_search_filter();
+        app
+    }
+
+    fn render(app: &App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).expect("test terminal");
+        terminal.draw(|frame| app.draw(frame)).expect("draw");
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    fn with_approvals(mut pr: PullRequest, approvals: usize) -> PullRequest {
+        pr.participants = (0..approvals)
+            .map(|index| Participant {
+                uuid: Some(format!("{{reviewer-{index}}}")),
+                account_id: None,
+                nickname: None,
+                display_name: format!("Reviewer {index}"),
+                role: "REVIEWER".to_string(),
+                approved: true,
+            })
+            .collect();
+        pr
+    }
+
+    #[test]
+    fn solo_filter_hides_prs_others_contributed_to() {
+        let mut assigned = pull_request("api", 2, "Fix parser", "Ada Lovelace");
+        assigned.participants = with_approvals(assigned.clone(), 1).participants;
+        assigned.participants[0].approved = false;
+        let mut commented = pull_request("api", 3, "New page", "Ada Lovelace");
+        commented.participants = assigned.participants.clone();
+        commented.participants[0].role = "PARTICIPANT".to_string();
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            assigned,
+            commented,
+            with_approvals(pull_request("api", 4, "Bump deps", "Ada Lovelace"), 1),
+        ]);
+
+        app.execute_command("/solo").expect("solo");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        app.execute_command("/solo").expect("solo");
+        assert_eq!(app.pull_requests.len(), 4);
+    }
+
+    #[test]
+    fn mainline_filter_keeps_prs_targeting_the_default_branch() {
+        let targeting = |id, branch: &str| {
+            let mut pr = pull_request("api", id, "Add retries", "Ada Lovelace");
+            pr.destination_branch = Some(branch.to_string());
+            pr
+        };
+        let mut app = app_with(vec![
+            targeting(1, "main"),
+            targeting(2, "develop"),
+            targeting(3, "master"),
+            pull_request("api", 4, "Fix retries", "Ada Lovelace"),
+        ]);
+
+        app.execute_command("/mainline").expect("mainline");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert!(
+            render(&app, 120, 16)
+                .iter()
+                .any(|line| line.contains("Mainline: main"))
+        );
+
+        app.execute_command("/mainline").expect("mainline");
+        assert_eq!(app.pull_requests.len(), 4);
+        app.config.default_branch = "develop".to_string();
+        app.execute_command("/mainline").expect("mainline");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+    }
+
+    #[test]
+    fn filters_by_approval_threshold_and_composes_with_search() {
+        let mut app = app_with(vec![
+            with_approvals(pull_request("api", 1, "Add retries", "Ada Lovelace"), 0),
+            with_approvals(pull_request("api", 2, "Fix retries", "Ada Lovelace"), 1),
+            with_approvals(pull_request("api", 3, "Fix login", "Ada Lovelace"), 2),
+            with_approvals(pull_request("web", 4, "Bump retries", "Ada Lovelace"), 3),
+        ]);
+        let ids = |app: &App| app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>();
+
+        app.handle_approved_command(&["2"]).expect("threshold");
+        assert_eq!(ids(&app), vec![3, 4]);
+
+        app.handle_approved_command(&["none"]).expect("none");
+        assert_eq!(ids(&app), vec![1]);
+
+        app.handle_approved_command(&["1"]).expect("threshold");
+        app.handle_search_command(&["retries"]);
+        assert_eq!(ids(&app), vec![2, 4]);
+
+        app.handle_approved_command(&["clear"]).expect("clear");
+        assert_eq!(ids(&app), vec![1, 2, 4]);
+        assert!(app.handle_approved_command(&["lots"]).is_err());
+    }
+
+    #[test]
+    fn offers_removing_dead_configured_repos_once() {
+        let mut config = Config::default();
+        let dead = RepoRef::parse("team/gone").expect("repo");
+        config.add_repo(dead.clone());
+        let mut app = App::new(config);
+        let workspace_repo = RepoRef::parse("team/from-workspace").expect("repo");
+
+        app.offer_dead_repo_removal(vec![dead.clone(), workspace_repo.clone()]);
+        let Some(PendingAction::RemoveRepos { repos, .. }) = app.pending_action.take() else {
+            panic!("expected a removal prompt");
+        };
+        assert_eq!(repos, vec![dead.clone()]);
+
+        app.offer_dead_repo_removal(vec![dead, workspace_repo]);
+        assert!(app.pending_action.is_none());
+    }
+
+    #[test]
+    fn size_filter_keeps_prs_of_one_size_and_shows_badges() {
+        let sized = |id, lines| {
+            let mut pr = pull_request("api", id, "Add retries", "Ada Lovelace");
+            pr.diffstat = Some(DiffStat {
+                files_changed: 1,
+                lines_added: lines,
+                lines_removed: 0,
+            });
+            pr
+        };
+        let mut app = app_with(vec![
+            sized(1, 5),
+            sized(2, 120),
+            pull_request("api", 3, "Fix parser", "Ada Lovelace"),
+        ]);
+        let screen = render(&app, 120, 16).join("\n");
+        assert!(screen.contains("[XS] 1. #1"));
+        assert!(screen.contains("[M] 2. #2"));
+        assert!(screen.contains("AL 3. #3"));
+
+        app.execute_command("/size m").expect("size");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![2]
+        );
+        assert!(render(&app, 120, 16).join("\n").contains("| Size: M"));
+        assert!(
+            app.logs
+                .last()
+                .is_some_and(|line| line.contains("run /stats-all"))
+        );
+
+        app.execute_command("/size clear").expect("clear");
+        assert_eq!(app.pull_requests.len(), 3);
+        assert!(app.execute_command("/size huge").is_err());
+    }
+
+    #[test]
+    fn applies_background_diffstats_and_reports_progress() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Fix login", "Ada Lovelace"),
+        ]);
+        let (sender, receiver) = mpsc::channel();
+        app.stats_job = Some(StatsJob {
+            receiver,
+            total: 2,
+            done: 0,
+            failed: 0,
+            rate_limited: false,
+        });
+
+        let diffstat = DiffStat {
+            files_changed: 2,
+            lines_added: 10,
+            lines_removed: 3,
+        };
+        sender
+            .send(StatsMessage::Fetched("team/api/1".to_string(), diffstat))
+            .expect("send");
+        app.poll_stats_job();
+        assert_eq!(app.pull_requests[0].diffstat, Some(diffstat));
+        assert_eq!(
+            app.stats_job
+                .as_ref()
+                .expect("job running")
+                .progress_label(),
+            "Diffstat [#####-----] 1/2"
+        );
+
+        sender
+            .send(StatsMessage::Failed(
+                "team/api/2".to_string(),
+                "boom".to_string(),
+            ))
+            .expect("send");
+        drop(sender);
+        app.poll_stats_job();
+        assert!(app.stats_job.is_none());
+        assert!(app.all_pull_requests[1].diffstat.is_none());
+        assert!(
+            render(&app, 90, 16)
+                .iter()
+                .any(|line| line.contains("+10/-3"))
+        );
+    }
+
+    #[test]
+    fn pinned_pull_requests_float_into_their_own_group() {
+        let mut app = app_with(vec![
+            pull_request("api", 12, "Add retries", "Ada Lovelace"),
+            pull_request("web", 4, "Polish header", "Grace Hopper"),
+        ]);
+        app.selected_index = 1;
+        app.set_selected_pinned(true).expect("pin");
+
+        assert_eq!(app.pull_requests[0].id, 4);
+        assert_eq!(app.selected_index, 0);
+        let (rows, selected_row) = app.grouped_rows();
+        let texts = rows.iter().map(|row| row.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(texts[0], "Pinned (1 PR):");
+        assert!(texts[1].starts_with("1. \u{1f4cc} team/web #4"));
+        assert_eq!(texts[2], "team/api (1 PR):");
+        assert_eq!(selected_row, Some(1));
+
+        app.set_selected_pinned(false).expect("unpin");
+        assert_eq!(app.pull_requests[0].id, 12);
+        assert_eq!(app.selected_index, 1);
+    }
+
+    #[test]
+    fn repo_manager_adds_and_confirms_removal() {
+        let mut app = app_with(Vec::new());
+        app.config
+            .add_repo(RepoRef::parse("team/api").expect("repo"));
+        app.config
+            .add_repo(RepoRef::parse("team/web").expect("repo"));
+        app.handle_repos_command(&["edit"]).expect("open manager");
+
+        app.handle_key(KeyEvent::from(KeyCode::Down)).expect("key");
+        app.handle_key(KeyEvent::from(KeyCode::Char('x')))
+            .expect("key");
+        let Some(PendingAction::RemoveRepos { repos, .. }) = &app.pending_action else {
+            panic!("expected a removal prompt");
+        };
+        assert_eq!(repos, &vec![RepoRef::parse("team/web").expect("repo")]);
+        assert!(
+            render(&app, 80, 20)
+                .iter()
+                .any(|line| line.contains("Remove repo(s) from config: team/web?"))
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Esc))
+            .expect("cancel");
+        assert!(app.pending_action.is_none());
+        assert!(app.repo_manager.is_some());
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('a')))
+            .expect("key");
+        assert_eq!(
+            app.repo_manager.as_ref().and_then(|m| m.adding.as_deref()),
+            Some("")
+        );
+        app.handle_key(KeyEvent::from(KeyCode::Esc))
+            .expect("cancel add");
+        app.handle_key(KeyEvent::from(KeyCode::Esc)).expect("close");
+        assert!(app.repo_manager.is_none());
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn default_search_applies_at_startup_and_on_reset() {
+        let mut config = Config::default();
+        config.default_search = Some("retries".to_string());
+        let mut app = App::new(config);
+        app.all_pull_requests = vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Bump serde", "Ada Lovelace"),
+        ];
+        app.apply_search_filter();
+        assert_eq!(app.pull_requests.len(), 1);
+        assert!(
+            render(&app, 120, 16)
+                .iter()
+                .any(|line| line.contains("Search: retries (default)"))
+        );
+
+        app.handle_search_command(&["serde"]);
+        assert_eq!(app.pull_requests[0].id, 2);
+        app.handle_search_command(&["clear"]);
+        assert_eq!(app.pull_requests.len(), 2);
+        app.handle_search_command(&["reset"]);
+        assert_eq!(app.search_query.as_deref(), Some("retries"));
+        assert_eq!(app.pull_requests[0].id, 1);
+    }
+
+    #[test]
+    fn shows_cached_latest_comment_preview() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        app.latest_comments.insert(
+            "team/api/12".to_string(),
+            (
+                2,
+                Some(Comment {
+                    author: "Bob".to_string(),
+                    created_on: "2026-03-01T10:00:00+00:00".to_string(),
+                    raw: "**Nit:** see [docs](https://x.y)".to_string(),
+                }),
+            ),
+        );
+
+        app.show_latest_comment().expect("comment");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Latest comment by Bob (2026-03-01): Nit: see docs")
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("open");
+        let screen = render(&app, 80, 24).join("\n");
+        assert!(screen.contains("Latest comment by Bob (2026-03-01):"));
+        assert!(screen.contains("**Nit:** see [docs](https://x.y)"));
+    }
+
+    #[test]
+    fn reports_why_the_latest_comment_could_not_load() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+
+        let err = app.show_latest_comment().expect_err("no client");
+        assert_eq!(
+            err.to_string(),
+            "cannot load comments without a Bitbucket client"
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("open");
+        let screen = render(&app, 80, 24).join("\n");
+        assert!(screen.contains("Latest comment unavailable: cannot load comments"));
+    }
+
+    #[test]
+    fn flags_pull_requests_found_behind_their_destination() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Fix parser", "Ada Lovelace"),
+        ]);
+        let behind = |count| Divergence {
+            ahead: CommitCount {
+                count: 1,
+                capped: false,
+            },
+            behind: CommitCount {
+                count,
+                capped: false,
+            },
+        };
+        app.divergences.insert("team/api/1".to_string(), behind(3));
+        app.divergences.insert("team/api/2".to_string(), behind(0));
+
+        let screen = render(&app, 120, 16).join("\n");
+        assert!(screen.contains("for review - behind by 3"));
+        assert_eq!(screen.matches("behind by").count(), 1);
+
+        app.show_divergence();
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Ahead/behind is not available for PR #1: its branches are unknown.")
+        );
+    }
+
+    #[test]
+    fn shows_cached_commit_summary() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        let updated_on = app.pull_requests[0].updated_on.clone();
+        app.commit_summaries.insert(
+            "team/api/12".to_string(),
+            (
+                updated_on.clone(),
+                CommitSummary {
+                    count: Some(3),
+                    latest_subject: Some("Handle timeouts".to_string()),
+                },
+            ),
+        );
+
+        app.show_commits().expect("commits");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("PR #12 has 3 commit(s); latest: Handle timeouts")
+        );
+
+        app.commit_summaries.insert(
+            "team/api/12".to_string(),
+            (updated_on, CommitSummary::default()),
+        );
+        app.show_commits().expect("commits");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("PR #12 has no commits.")
+        );
+    }
+
+    #[test]
+    fn marks_pull_requests_blocked_on_required_reviewers() {
+        let mut blocked = pull_request("api", 1, "Add retries", "Ada Lovelace");
+        blocked.destination_branch = Some("main".to_string());
+        blocked.comment_count = 0;
+        let mut app = app_with(vec![
+            blocked,
+            pull_request("web", 2, "Fix layout", "Ada Lovelace"),
+        ]);
+        let bob = Participant {
+            uuid: Some("{bob}".to_string()),
+            account_id: None,
+            nickname: None,
+            display_name: "Bob".to_string(),
+            role: "DEFAULT_REVIEWER".to_string(),
+            approved: false,
+        };
+        app.required_reviewers.insert(
+            "team/api".to_string(),
+            RequiredReviewers {
+                reviewers: vec![bob],
+                rules: vec![DefaultReviewerRule {
+                    pattern: "main".to_string(),
+                    approvals: 1,
+                }],
+            },
+        );
+
+        let (rows, _) = app.grouped_rows();
+        assert!(rows[1].text.starts_with("1. !#1 "));
+        assert!(rows[3].text.starts_with("1. #2 "));
+
+        app.show_required_reviewers().expect("required");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("PR #1 is waiting on required reviewers: Bob")
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("open");
+        assert!(
+            render(&app, 80, 24)
+                .join("\n")
+                .contains("Blocked  needs approval from Bob")
+        );
+    }
+
+    #[test]
+    fn reauth_prompt_masks_token_and_cancels() {
+        let mut app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        app.reauth = Some(ReauthPrompt::default());
+        for ch in "abc".chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(ch)))
+                .expect("key");
+        }
+        app.handle_key(KeyEvent::from(KeyCode::Backspace))
+            .expect("key");
+        assert_eq!(
+            app.reauth.as_ref().map(|prompt| prompt.token.as_str()),
+            Some("ab")
+        );
+
+        let screen = render(&app, 100, 30).join("\n");
+        assert!(screen.contains("Re-authenticate"), "{screen}");
+        assert!(screen.contains("**_"), "{screen}");
+        assert!(!screen.contains("ab_"), "{screen}");
+
+        app.handle_key(KeyEvent::from(KeyCode::Esc)).expect("key");
+        assert!(app.reauth.is_none());
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn confirm_dialog_previews_the_api_call() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        app.pending_action = Some(PendingAction::Approval {
+            workspace: "team".to_string(),
+            repo: "api".to_string(),
+            id: 12,
+            approve: false,
+        });
+
+        let screen = render(&app, 120, 30).join("\n");
+        assert!(
+            screen.contains(
+                "DELETE https://api.bitbucket.org/2.0/repositories/team/api/pullrequests/12/approve"
+            ),
+            "{screen}"
+        );
+        assert!(screen.contains("y/Enter: confirm"), "{screen}");
+    }
+
+    #[test]
+    fn command_palette_runs_fuzzy_matched_commands_and_actions() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        let ctrl_p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+        let type_text = |app: &mut App, text: &str| {
+            for ch in text.chars() {
+                app.handle_key(KeyEvent::from(KeyCode::Char(ch)))
+                    .expect("key");
+            }
+        };
+
+        app.handle_key(ctrl_p).expect("open");
+        type_text(&mut app, "mkallrd");
+        assert!(
+            render(&app, 100, 30)
+                .join("\n")
+                .contains("Command palette: mkallrd_")
+        );
+        app.handle_key(KeyEvent::from(KeyCode::Enter)).expect("run");
+        assert!(app.palette.is_none());
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Cleared 0 visited marker(s).")
+        );
+
+        app.handle_key(ctrl_p).expect("open");
+        type_text(&mut app, "focus mode");
+        app.handle_key(KeyEvent::from(KeyCode::Enter)).expect("run");
+        assert!(app.focus);
+
+        app.handle_key(ctrl_p).expect("open");
+        type_text(&mut app, "waiting");
+        app.handle_key(KeyEvent::from(KeyCode::Enter)).expect("run");
+        assert_eq!(app.input, "/waiting ");
+    }
+
+    #[test]
+    fn detail_overlay_wraps_and_scrolls_the_description() {
+        let mut pr = pull_request("api", 12, "Add retries", "Ada Lovelace");
+        pr.description = format!("Retry failed calls. {}\nlast line", "word ".repeat(30));
+        pr.comment_count = 0;
+        let mut app = app_with(vec![pr]);
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("open");
+        let screen = render(&app, 60, 20).join("\n");
+        assert!(screen.contains("PR #12"));
+        assert!(screen.contains("Ada Lovelace"));
+        assert!(screen.contains("d for review"));
+        assert!(screen.contains("Retry failed calls."));
+        assert!(screen.contains("last line"));
+
+        // Everything fits at this size, so there is nothing to scroll.
+        app.handle_key(KeyEvent::from(KeyCode::Down))
+            .expect("scroll");
+        assert_eq!(
+            app.detail.as_ref().map(|detail| detail.scroll.offset),
+            Some(0)
+        );
+
+        render(&app, 60, 12);
+        for _ in 0..3 {
+            app.handle_key(KeyEvent::from(KeyCode::Down))
+                .expect("scroll");
+        }
+        assert!(!render(&app, 60, 12).join("\n").contains("Add retries"));
+
+        app.handle_key(KeyEvent::from(KeyCode::Esc)).expect("close");
+        assert!(app.detail.is_none());
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn renders_description_markdown_lite() {
+        let raw = "## Summary\n\n\n\nRetry `upload()` on 5xx\n- first\n  * nested\n> quoted\n```rust\n# not a heading\n```\nunpaired ` tick\n\n";
+        let lines = description_lines(raw);
+        let text = lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            text,
+            vec![
+                "Summary",
+                "",
+                "Retry upload() on 5xx",
+                "\u{2022} first",
+                "  \u{2022} nested",
+                "\u{2502} quoted",
+                "# not a heading",
+                "unpaired ` tick",
+            ]
+        );
+        assert!(lines[0].style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(lines[2].spans[1].content, "upload()");
+        assert_eq!(lines[2].spans[1].style.fg, Some(Color::Yellow));
+        assert_eq!(lines[6].style.fg, Some(Color::DarkGray));
+    }
+
+    #[test]
+    fn detail_overlay_scrolls_to_the_end_of_a_wrapped_description() {
+        let mut pr = pull_request("api", 12, "Add retries", "Ada Lovelace");
+        pr.description = format!("{}the end", "word ".repeat(400));
+        let mut app = app_with(vec![pr]);
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("open");
+        assert!(!render(&app, 60, 20).join("\n").contains("the end"));
+
+        for _ in 0..100 {
+            app.handle_key(KeyEvent::from(KeyCode::Down))
+                .expect("scroll");
+        }
+        let screen = render(&app, 60, 20).join("\n");
+        assert!(screen.contains("the end"));
+        let bottom = app.detail.as_ref().map(|detail| detail.scroll.offset);
+
+        app.handle_key(KeyEvent::from(KeyCode::Home)).expect("home");
+        assert!(render(&app, 60, 20)[2].contains("PR #12 0%"));
+        app.handle_key(KeyEvent::from(KeyCode::PageDown))
+            .expect("page down");
+        // The overlay shows 14 rows of text at this size.
+        assert_eq!(
+            app.detail.as_ref().map(|detail| detail.scroll.offset),
+            Some(14)
+        );
+        app.handle_key(KeyEvent::from(KeyCode::End)).expect("end");
+        assert_eq!(
+            app.detail.as_ref().map(|detail| detail.scroll.offset),
+            bottom
+        );
+        let screen = render(&app, 60, 20);
+        assert!(screen[2].contains("PR #12 100%"));
+        assert!(screen.join("\n").contains("the end"));
+    }
+
+    /// Answers each PR listing after `delay` and records how many requests
+    /// were in flight at once. The `broken` repo gets a 500 and the
+    /// `explode` repo panics.
+    struct SlowRepos {
+        delay: Duration,
+        in_flight: AtomicUsize,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Transport for SlowRepos {
+        fn send(&self, _method: Method, endpoint: &Url) -> anyhow::Result<ApiResponse> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            std::thread::sleep(self.delay);
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            assert!(!endpoint.path().contains("/explode/"), "transport bug");
+            let (status, body) = if endpoint.path().contains("/broken/") {
+                (StatusCode::INTERNAL_SERVER_ERROR, "{}".to_string())
+            } else {
+                let repo = endpoint.path().split('/').nth(4).unwrap_or_default();
+                (
+                    StatusCode::OK,
+                    format!(
+                        r#"{{"values": [{{"id": 1, "title": "{repo} PR", "state": "OPEN",
+                            "updated_on": "2026-03-02T10:00:00+00:00",
+                            "author": {{"display_name": "Me"}},
+                            "links": {{"html": {{"href": ""}}}}}}]}}"#
+                    ),
+                )
+            };
+            Ok(ApiResponse { status, body })
+        }
+    }
+
+    #[test]
+    fn fetches_repos_concurrently_and_keeps_per_repo_failures() {
+        let mut app = app_with(Vec::new());
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let client = BitbucketClient::with_transport(
+            "https://api.bitbucket.org/2.0",
+            Box::new(SlowRepos {
+                delay: Duration::from_millis(50),
+                in_flight: AtomicUsize::new(0),
+                max_in_flight: Arc::clone(&max_in_flight),
+            }),
+        );
+        let repos = (0..9)
+            .map(|n| RepoRef::parse(&format!("team/repo{n}")).expect("repo"))
+            .chain([
+                RepoRef::parse("team/explode").expect("repo"),
+                RepoRef::parse("team/broken").expect("repo"),
+            ])
+            .collect::<Vec<_>>();
+
+        let (prs, failed) = app
+            .fetch_now(|fetcher| {
+                fetcher.fetch_per_repo(
+                    &client,
+                    &UserId::Uuid("{me}".to_string()),
+                    PrStatus::Open,
+                    &repos,
+                )
+            })
+            .expect("fetched");
+        let max_in_flight = max_in_flight.load(Ordering::SeqCst);
+        assert!(
+            (2..=FETCH_WORKERS).contains(&max_in_flight),
+            "{max_in_flight}"
+        );
+
+        assert_eq!(failed, 2);
+        assert_eq!(prs.len(), 9);
+        assert_eq!(prs[0].title, "repo0 PR");
+        assert_eq!(prs[8].title, "repo8 PR");
+        assert!(
+            app.logs
+                .iter()
+                .any(|line| line == "Failed loading team/explode: the fetch panicked")
+        );
+        assert!(
+            app.logs
+                .last()
+                .is_some_and(|line| line.starts_with("Failed loading team/broken"))
+        );
+        assert_eq!(app.repo_timings.len(), 11);
+    }
+
+    #[test]
+    fn first_refresh_does_not_block_the_ui() {
+        // Accepts connections but never answers, so every request hangs.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("listener");
+        let mut config = Config::default();
+        config.bitbucket_base_url = format!("http://{}", listener.local_addr().expect("addr"));
+        config.bitbucket_email = Some("me@example.com".to_string());
+        config.bitbucket_api_token = Some("token".to_string());
+        config.add_repo(RepoRef::parse("team/api").expect("repo"));
+        let mut app = App::new(config);
+        assert_eq!(app.poll_timeout(), Duration::ZERO);
+
+        let started = Instant::now();
+        app.refresh_pull_requests_if_due();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(app.refresh_job.is_some());
+        assert!(render(&app, 100, 20)[4].contains("Loading..."));
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('/')))
+            .expect("type");
+        assert_eq!(app.input, "/");
+        app.poll_refresh_job();
+        assert!(app.refresh_job.is_some());
+    }
+
+    /// Serves `team/api` in two pages of one PR each, holding the second
+    /// page back until `release` gets a message.
+    struct PagedRepo {
+        release: Mutex<mpsc::Receiver<()>>,
+    }
+
+    impl Transport for PagedRepo {
+        fn send(&self, _method: Method, endpoint: &Url) -> anyhow::Result<ApiResponse> {
+            let pull_request = |id: u64| {
+                format!(
+                    r#"{{"id": {id}, "title": "PR {id}", "state": "OPEN",
+                        "updated_on": "2026-03-02T10:00:00+00:00",
+                        "author": {{"display_name": "Me"}},
+                        "links": {{"html": {{"href": ""}}}}}}"#
+                )
+            };
+            let body = if endpoint.path().ends_with("/user") {
+                r#"{"uuid": "{me}"}"#.to_string()
+            } else if !endpoint.path().ends_with("/team/api/pullrequests") {
+                r#"{"values": []}"#.to_string()
+            } else if endpoint
+                .query()
+                .is_some_and(|query| query.contains("page=2"))
+            {
+                self.release.lock().expect("release").recv()?;
+                format!(r#"{{"values": [{}]}}"#, pull_request(2))
+            } else {
+                format!(
+                    r#"{{"values": [{}], "next": "{endpoint}&page=2"}}"#,
+                    pull_request(1)
+                )
+            };
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body,
+            })
+        }
+    }
+
+    #[test]
+    fn refreshes_in_the_background_and_shows_page_progress() {
+        let (release, pages) = mpsc::channel();
+        let client = BitbucketClient::with_transport(
+            "https://api.bitbucket.org/2.0",
+            Box::new(PagedRepo {
+                release: Mutex::new(pages),
+            }),
+        );
+        let mut app = app_with(Vec::new());
+        app.config
+            .add_repo(RepoRef::parse("team/api").expect("repo"));
+        app.start_refresh(FetchSource::Bitbucket(client), false);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !render(&app, 100, 20)[4].contains("Loading (team/api: page 1)") {
+            assert!(Instant::now() < deadline, "no page progress shown");
+            app.poll_refresh_job();
+            std::thread::sleep(Duration::from_millis(5));
         }
+        assert!(app.all_pull_requests.is_empty());
 
-        let mut parts = command.split_whitespace();
-        let name = parts.next().unwrap_or_default();
-        let args = parts.collect::<Vec<_>>();
+        release.send(()).expect("release page 2");
+        while app.refresh_job.is_some() {
+            assert!(Instant::now() < deadline, "refresh never finished");
+            app.poll_refresh_job();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(app.all_pull_requests.len(), 2);
+        assert!(!render(&app, 100, 20)[4].contains("Loading"));
+        assert!(
+            app.logs
+                .iter()
+                .any(|line| line == "Fetched 2 PR(s) from team/api in 2 pages.")
+        );
+    }
 
-        match name {
-            "/help" => {
-                self.log("Commands: /repo add <w>/<r>, /repo rm <w>/<r>, /repos, /status <open|merged|declined|all>, /refresh, /search <text|pr-number>, /search clear, /quit");
-                self.log(
-                    "Tip: type '/' to show command suggestions; use Up/Down + Tab to autocomplete.",
-                );
-                self.log("Tip: press Enter with empty command input to open selected PR.");
-                self.log(&format!(
-                    "Tip: auto refresh runs every {} seconds and rings terminal bell when updates are detected.",
-                    self.auto_refresh_interval.as_secs()
-                ));
-            }
-            "/quit" => {
-                self.should_quit = true;
-            }
-            "/repo" => {
-                self.handle_repo_command(&args)?;
-            }
-            "/repos" => {
-                self.show_repos();
-            }
-            "/status" => {
-                self.handle_status_command(&args)?;
-            }
-            "/refresh" => self.refresh_pull_requests(true),
-            "/search" => self.handle_search_command(&args),
-            _ => {
-                self.log("Unknown command. Try /help.");
-            }
+    /// Answers GitHub's `/user` with `octocat` and the search with two open
+    /// PRs in `team/api` and `team/web`.
+    struct CannedGithub;
+
+    impl Transport for CannedGithub {
+        fn send(&self, _method: Method, endpoint: &Url) -> anyhow::Result<ApiResponse> {
+            let body = if endpoint.path() == "/user" {
+                r#"{"login": "octocat"}"#.to_string()
+            } else {
+                let hit = |id: u64, repo: &str| {
+                    format!(
+                        r#"{{"number": {id}, "title": "PR {id}", "user": {{"login": "octocat"}},
+                            "state": "open", "created_at": "2026-03-01T10:00:00Z",
+                            "updated_at": "2026-03-02T10:00:00Z",
+                            "html_url": "https://github.com/team/{repo}/pull/{id}",
+                            "repository_url": "https://api.github.com/repos/team/{repo}",
+                            "pull_request": {{"merged_at": null}}}}"#
+                    )
+                };
+                format!(r#"{{"items": [{}, {}]}}"#, hit(1, "api"), hit(2, "web"))
+            };
+            Ok(ApiResponse {
+                status: StatusCode::OK,
+                body,
+            })
         }
+    }
 
-        Ok(())
+    #[test]
+    fn lists_github_pull_requests_narrowed_to_configured_repos() {
+        let client = GithubClient::with_transport("https://api.github.com", Box::new(CannedGithub));
+        let mut app = app_with(Vec::new());
+        let outcome = app
+            .fetch_now(|fetcher| fetcher.fetch_from_provider(&client, PrStatus::Open))
+            .expect("outcome");
+        assert_eq!((outcome.pull_requests.len(), outcome.repo_count), (2, 2));
+        assert_eq!(app.user_id, Some(UserId::Nickname("octocat".to_string())));
+
+        app.config
+            .add_repo(RepoRef::parse("team/web").expect("repo"));
+        let outcome = app
+            .fetch_now(|fetcher| fetcher.fetch_from_provider(&client, PrStatus::Open))
+            .expect("outcome");
+        assert_eq!(
+            outcome
+                .pull_requests
+                .iter()
+                .map(|pr| pr.id)
+                .collect::<Vec<_>>(),
+            vec![2]
+        );
     }
 
-    fn handle_repo_command(&mut self, args: &[&str]) -> Result<()> {
-        if args.is_empty() {
-            return Err(anyhow!(
-                "usage: /repo add <workspace>/<repo> | /repo rm <workspace>/<repo>"
-            ));
-        }
+    #[test]
+    fn bitbucket_only_commands_say_they_are_unsupported_for_github() {
+        let mut pr = pull_request("api", 12, "Add retries", "octocat");
+        pr.comment_count = 2;
+        let mut app = app_with(vec![pr]);
+        app.config.set_session_provider(Provider::Github);
 
-        match args[0] {
-            "add" => {
-                let repo = args
-                    .get(1)
-                    .ok_or_else(|| anyhow!("usage: /repo add <workspace>/<repo>"))?;
-                let repo_ref = RepoRef::parse(repo)?;
-                if self.config.add_repo(repo_ref.clone()) {
-                    self.config.save()?;
-                    self.log(&format!("Added repo {repo_ref}"));
-                } else {
-                    self.log(&format!("Repo {repo_ref} already exists"));
-                }
-            }
-            "rm" | "remove" => {
-                let repo = args
-                    .get(1)
-                    .ok_or_else(|| anyhow!("usage: /repo rm <workspace>/<repo>"))?;
-                let repo_ref = RepoRef::parse(repo)?;
-                if self.config.remove_repo(&repo_ref) {
-                    self.config.save()?;
-                    self.log(&format!("Removed repo {repo_ref}"));
-                } else {
-                    self.log(&format!("Repo {repo_ref} not found"));
-                }
-            }
-            repo_value => {
-                let repo_ref = RepoRef::parse(repo_value)?;
-                if self.config.add_repo(repo_ref.clone()) {
-                    self.config.save()?;
-                    self.log(&format!("Added repo {repo_ref}"));
-                } else {
-                    self.log(&format!("Repo {repo_ref} already exists"));
-                }
-            }
+        app.execute_command("/approve").expect("approve");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("/approve is not supported for GitHub.")
+        );
+        for command in [
+            "/q state = \"OPEN\"",
+            "/workspace add team",
+            "/project add team/API",
+        ] {
+            app.execute_command(command).expect("command");
+            let name = command.split_whitespace().next().unwrap_or_default();
+            assert_eq!(
+                app.logs.last(),
+                Some(&format!("{name} is not supported for GitHub."))
+            );
         }
+        assert!(app.custom_query.is_none());
+        assert!(app.config.workspaces().is_empty());
+        app.handle_key(KeyEvent::from(KeyCode::Char('b')))
+            .expect("pipeline");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Opening pipelines is not supported for GitHub.")
+        );
 
-        Ok(())
+        app.handle_key(KeyEvent::from(KeyCode::Char('d')))
+            .expect("detail");
+        assert!(
+            render(&app, 80, 30)
+                .join("\n")
+                .contains("Latest comment unavailable: comments are not supported for GitHub")
+        );
     }
 
-    fn show_repos(&mut self) {
-        if self.config.repos().is_empty() {
-            self.log("No repos configured. Add one with /repo add <workspace>/<repo>.");
-            return;
-        }
+    #[test]
+    fn quitting_during_a_confirmation_asks_first() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        app.execute_command("/quit").expect("quit");
+        assert!(app.should_quit);
 
-        self.log("Configured repos:");
-        let repo_lines = self
-            .config
-            .repos()
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        app.pending_action = Some(PendingAction::Approval {
+            workspace: "team".to_string(),
+            repo: "api".to_string(),
+            id: 12,
+            approve: true,
+        });
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+
+        app.handle_key(ctrl_c).expect("key");
+        assert!(!app.should_quit);
+        assert!(
+            render(&app, 120, 30)
+                .join("\n")
+                .contains("Quit anyway? This is still waiting: Approve team/api PR #12?")
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('n')))
+            .expect("key");
+        assert!(matches!(
+            app.pending_action,
+            Some(PendingAction::Approval { id: 12, .. })
+        ));
+
+        app.handle_key(ctrl_c).expect("key");
+        app.handle_key(ctrl_c).expect("key");
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn ctrl_c_in_the_repo_manager_or_token_prompt_asks_while_fetching() {
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        let (_sender, receiver) = mpsc::channel();
+        let mut app = app_with(Vec::new());
+        app.stats_job = Some(StatsJob {
+            receiver,
+            total: 5,
+            done: 2,
+            failed: 0,
+            rate_limited: false,
+        });
+
+        app.repo_manager = Some(RepoManager::default());
+        app.handle_key(ctrl_c).expect("key");
+        assert!(!app.should_quit);
+        assert!(matches!(
+            app.pending_action,
+            Some(PendingAction::Quit { .. })
+        ));
+        app.handle_key(KeyEvent::from(KeyCode::Char('n')))
+            .expect("key");
+        assert!(app.repo_manager.is_some());
+
+        app.repo_manager = None;
+        app.reauth = Some(ReauthPrompt::default());
+        app.handle_key(ctrl_c).expect("key");
+        assert!(!app.should_quit);
+        assert!(matches!(
+            app.pending_action,
+            Some(PendingAction::Quit { .. })
+        ));
+        app.handle_key(ctrl_c).expect("key");
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn read_only_mode_disables_write_commands() {
+        let mut app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        app.config.set_session_read_only();
+
+        app.input = "/st".to_string();
+        let suggestions = app
+            .command_suggestions()
             .iter()
-            .map(|repo| format!("- {repo}"))
+            .map(|spec| spec.name)
             .collect::<Vec<_>>();
-        for line in repo_lines {
-            self.log(&line);
-        }
+        assert_eq!(suggestions, vec!["/status", "/standup", "/stats-all"]);
+
+        app.execute_command("/stale 1").expect("stale");
+        assert!(app.pending_action.is_none());
+        assert_eq!(app.logs.last().map(String::as_str), Some(READ_ONLY_NOTICE));
+
+        app.run_pending_action(PendingAction::Approval {
+            workspace: "team".to_string(),
+            repo: "api".to_string(),
+            id: 1,
+            approve: true,
+        })
+        .expect("approval");
+        assert_eq!(app.logs.last().map(String::as_str), Some(READ_ONLY_NOTICE));
+
+        app.execute_command("/help").expect("help");
+        assert!(
+            app.logs
+                .iter()
+                .any(|line| line.starts_with("Commands:") && !line.contains("/approve,"))
+        );
     }
 
-    fn handle_status_command(&mut self, args: &[&str]) -> Result<()> {
-        let value = args
-            .first()
-            .ok_or_else(|| anyhow!("usage: /status <open|merged|declined|all>"))?;
-        let status = PrStatus::from_str(value)?;
-        self.status_filter = status;
+    #[test]
+    fn shows_onboarding_panel_without_repos() {
+        let mut config = Config::default();
+        config.bitbucket_email = Some("me@example.com".to_string());
+        let app = App::new(config);
 
-        if self.config.set_status(status) {
-            self.config.save()?;
-        }
+        let lines = render(&app, 100, 30);
+        assert!(lines.iter().any(|line| line.contains("Getting started")));
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.contains("/repo add <workspace>/<repo>"))
+        );
+        assert!(lines.iter().any(|line| {
+            line.contains("Credentials: missing (email: from config, API token: missing)")
+        }));
+    }
 
-        self.log(&format!("Status filter set to {}. Refreshing...", status));
-        self.refresh_pull_requests(false);
-        Ok(())
+    #[test]
+    fn renders_notice_when_terminal_is_too_small() {
+        let app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        let screen = render(&app, 30, 8).join("\n");
+        assert!(screen.contains("Terminal too small (30x8)"), "{screen}");
+        assert!(!screen.contains("Add retries"), "{screen}");
+
+        let screen = render(&app, 40, 12).join("\n");
+        assert!(!screen.contains("Terminal too small"), "{screen}");
     }
 
-    fn refresh_pull_requests_if_due(&mut self) {
-        let should_refresh = match self.last_refresh_at {
-            Some(last) => last.elapsed() >= self.auto_refresh_interval,
-            None => true,
-        };
+    #[test]
+    fn exports_grouped_markdown_list() {
+        let mut pr = pull_request("api", 1, "Add [WIP] retries", "Ada Lovelace");
+        pr.state = "MERGED".to_string();
+        let app = app_with(vec![pr, pull_request("web", 3, "New page", "Ada Lovelace")]);
+        assert_eq!(
+            app.markdown_export(),
+            "## team/api\n\n\
+             - [#1 Add \\[WIP\\] retries](https://bitbucket.org/team/api/pull-requests/1) - MERGED\n\
+             \n\
+             ## team/web\n\n\
+             - [#3 New page](https://bitbucket.org/team/web/pull-requests/3) - OPEN\n"
+        );
+    }
 
-        if should_refresh {
-            self.refresh_pull_requests(true);
-        }
+    #[test]
+    fn idle_backoff_stretches_refresh_until_next_keystroke() {
+        let mut app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        app.config.idle_backoff_after_seconds = 1;
+        app.config.idle_backoff_max_seconds = 600;
+        app.last_interaction = Instant::now() - Duration::from_secs(3);
+
+        assert_eq!(app.effective_refresh_interval(), Duration::from_secs(600));
+        assert!(
+            render(&app, 120, 16)
+                .iter()
+                .any(|line| line.contains("Auto refresh: 600s (idle)"))
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Down)).expect("key");
+        assert_eq!(app.effective_refresh_interval(), Duration::from_secs(120));
     }
 
-    fn refresh_pull_requests(&mut self, notify_updates: bool) {
-        self.last_refresh_at = Some(Instant::now());
-        let previous_by_key = self
-            .all_pull_requests
-            .iter()
-            .cloned()
-            .map(|pr| (Self::pr_key(&pr), pr))
-            .collect::<HashMap<_, _>>();
+    #[test]
+    fn terminal_title_counts_open_pull_requests() {
+        let mut merged = pull_request("api", 2, "Fix parser", "Ada Lovelace");
+        merged.state = "MERGED".to_string();
+        let app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            merged,
+            pull_request("web", 3, "New page", "Ada Lovelace"),
+        ]);
+        assert_eq!(app.terminal_title(), "myprs: 2 open");
+    }
 
-        let Some((email, api_token)) = self
-            .config
-            .credentials()
-            .map(|(email, token)| (email.to_string(), token.to_string()))
-        else {
-            self.log("Missing credentials. Set BITBUCKET_EMAIL and BITBUCKET_API_TOKEN.");
-            return;
-        };
+    #[test]
+    fn collapsed_groups_show_only_their_header() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Fix parser", "Ada Lovelace"),
+            pull_request("web", 3, "New page", "Ada Lovelace"),
+        ]);
+        app.selected_index = 1;
 
-        let repos = self.config.repos().to_vec();
-        if repos.is_empty() {
-            self.log("No repos configured. Add repos via /repo add <workspace>/<repo>.");
-            return;
-        }
+        app.handle_key(KeyEvent::from(KeyCode::Char('z')))
+            .expect("key");
+        assert_eq!(app.selected_index, 0);
+        let (rows, selected) = app.grouped_rows();
+        assert_eq!(
+            rows.iter().map(|row| row.text.as_str()).collect::<Vec<_>>()[..2],
+            ["\u{25b8} team/api (2 PRs):", "team/web (1 PR):"]
+        );
+        assert_eq!(selected, Some(0));
 
-        let client = BitbucketClient::new(self.config.bitbucket_base_url.clone(), email, api_token);
-        let user_uuid = match client.current_user_uuid() {
-            Ok(uuid) => uuid,
-            Err(err) => {
-                self.log(&format!("Failed to fetch current user: {err}"));
-                return;
-            }
-        };
+        app.handle_key(KeyEvent::from(KeyCode::Down)).expect("key");
+        assert_eq!(app.selected_index, 2);
+        app.handle_key(KeyEvent::from(KeyCode::Up)).expect("key");
+        assert_eq!(app.selected_index, 0);
 
-        let mut all_prs = Vec::new();
-        let mut failed_repos = 0usize;
-        for repo in &repos {
-            match client.list_pull_requests_created_by(
-                &repo.workspace,
-                &repo.repo,
-                &user_uuid,
-                self.status_filter,
-            ) {
-                Ok(mut prs) => all_prs.append(&mut prs),
-                Err(err) => {
-                    failed_repos += 1;
-                    self.log(&format!("Failed loading {}: {err}", repo));
-                }
-            }
-        }
+        app.handle_key(KeyEvent::from(KeyCode::Enter)).expect("key");
+        assert!(app.collapsed.is_empty());
+        assert_eq!(app.grouped_rows().0.len(), 5);
+    }
 
-        all_prs.sort_by(|left, right| {
-            left.workspace
-                .cmp(&right.workspace)
-                .then(left.repo.cmp(&right.repo))
-                .then_with(|| right.updated_on.cmp(&left.updated_on))
-        });
+    #[test]
+    fn dims_visited_pull_requests_until_new_activity() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Fix layout", "Ada Lovelace"),
+        ]);
+        app.mark_selected_visited();
+        let (rows, _) = app.grouped_rows();
+        assert!(rows[1].visited);
+        assert!(!rows[2].visited);
 
-        let updates = if notify_updates && !previous_by_key.is_empty() {
-            self.collect_refresh_updates(&previous_by_key, &all_prs)
-        } else {
-            Vec::new()
-        };
+        app.pull_requests[0].updated_on = "2026-04-01T00:00:00+00:00".to_string();
+        let (rows, _) = app.grouped_rows();
+        assert!(!rows[1].visited);
 
-        self.selected_index = 0;
-        self.all_pull_requests = all_prs;
-        self.apply_search_filter();
+        app.mark_selected_visited();
+        app.execute_command("/mark-all-read")
+            .expect("mark all read");
+        assert!(app.visited.is_empty());
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Cleared 1 visited marker(s).")
+        );
+    }
 
-        if let Some(query) = &self.search_query {
-            self.log(&format!(
-                "Loaded {} matching PR(s) out of {} total with status '{}' across {} repo(s) | search='{}'",
-                self.pull_requests.len(),
-                self.all_pull_requests.len(),
-                self.status_filter,
-                repos.len(),
-                query
-            ));
-        } else {
-            self.log(&format!(
-                "Loaded {} PR(s) with status '{}' across {} repo(s)",
-                self.pull_requests.len(),
-                self.status_filter,
-                repos.len()
-            ));
-        }
+    #[test]
+    fn marks_own_pull_requests_in_reviewing_list() {
+        let mut own = pull_request("api", 1, "Self review", "Ada Lovelace");
+        own.relation = Relation::Both;
+        let mut reviewing = pull_request("api", 2, "Fix layout", "Bob");
+        reviewing.relation = Relation::Reviewing;
+        let mut app = app_with(vec![own, reviewing]);
 
-        if failed_repos > 0 {
-            self.log(&format!("{} repo(s) failed during refresh", failed_repos));
-        }
+        let (rows, _) = app.grouped_rows();
+        assert!(!rows.iter().any(|row| row.own));
 
-        if !updates.is_empty() {
-            self.emit_update_notifications(&updates);
-        }
+        app.relation = Relation::Reviewing;
+        let (rows, _) = app.grouped_rows();
+        assert!(rows[1].own);
+        assert!(!rows[2].own);
+        let lines = render(&app, 120, 16);
+        assert!(lines.iter().any(|line| line.contains("1. (mine) #1")));
+        assert!(lines.iter().any(|line| line.contains("2. #2")));
     }
 
-    fn open_pull_request(&mut self, index: usize) -> Result<()> {
-        if index == 0 {
-            return Err(anyhow!("pull request index must be >= 1"));
-        }
-        let zero_index = index - 1;
+    #[test]
+    fn shows_linked_issue_keys_as_tags() {
+        let mut pr = pull_request("api", 1, "OPS-12: Add retries", "Ada Lovelace");
+        pr.issue_keys = vec!["OPS-12".to_string(), "OPS-14".to_string()];
+        let mut app = app_with(vec![pr]);
 
-        let Some(pr) = self.pull_requests.get(zero_index) else {
-            return Err(anyhow!("no pull request at index {index}"));
+        let (rows, _) = app.grouped_rows();
+        assert!(rows[1].text.contains("(Ada Lovelace) [OPS-12] [OPS-14]"));
+
+        assert!(app.open_issue(None).is_err());
+        app.config.issue_url_template = Some("https://jira.example.com/browse/{key}".to_string());
+        assert!(app.open_issue(Some("3")).is_err());
+        assert!(app.open_issue(Some("OPS-99")).is_err());
+    }
+
+    #[test]
+    fn search_scope_limits_matches_to_titles() {
+        let mut described = pull_request("api", 1, "Add retries", "Ada Lovelace");
+        described.description = "Also touches the parser".to_string();
+        let mut app = app_with(vec![
+            described,
+            pull_request("api", 2, "Parser cleanup", "Ada Lovelace"),
+        ]);
+
+        app.handle_search_command(&["parser"]);
+        assert_eq!(app.pull_requests.len(), 2);
+
+        app.config.search_scope = SearchScope::Title;
+        app.apply_search_filter();
+        assert_eq!(app.pull_requests[0].id, 2);
+        assert_eq!(app.pull_requests.len(), 1);
+        assert!(
+            render(&app, 120, 16)
+                .iter()
+                .any(|line| line.contains("| Scope: title"))
+        );
+    }
+
+    #[test]
+    fn fills_standup_template_placeholders() {
+        let mut pr = pull_request("app", 123, "Fix login", "Ada Lovelace");
+        pr.url = "https://bitbucket.org/team/app/pull-requests/123".to_string();
+
+        assert_eq!(
+            standup_line(&Config::default().standup_template, &pr),
+            "#123 Fix login (team/app)"
+        );
+        assert_eq!(
+            standup_line("Reviewing #{id} {title} ({repo}) {url} {unknown}", &pr),
+            "Reviewing #123 Fix login (team/app) https://bitbucket.org/team/app/pull-requests/123 {unknown}"
+        );
+    }
+
+    #[test]
+    fn splits_search_into_terms_keeping_quoted_phrases() {
+        let texts = |query: &str| {
+            search_terms(query)
+                .into_iter()
+                .map(|term| (term.text, term.exclude))
+                .collect::<Vec<_>>()
         };
+        assert_eq!(
+            texts(r#"Retry "Connection  Pool" #12 "unclosed phrase"#),
+            vec![
+                ("retry".to_string(), false),
+                ("connection  pool".to_string(), false),
+                ("#12".to_string(), false),
+                ("unclosed phrase".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            texts(r#"-Dependabot -"bump deps" - x-y"#),
+            vec![
+                ("dependabot".to_string(), true),
+                ("bump deps".to_string(), true),
+                ("-".to_string(), false),
+                ("x-y".to_string(), false),
+            ]
+        );
+        assert!(search_terms("  \"\"  ").is_empty());
+    }
+
+    #[test]
+    fn search_terms_must_all_match_in_any_order() {
+        let mut described = pull_request("api", 12, "Add retries", "Ada Lovelace");
+        described.description = "Tunes the connection pool".to_string();
+        let mut app = app_with(vec![
+            described,
+            pull_request(
+                "api",
+                2,
+                "Pool retries for connection errors",
+                "Ada Lovelace",
+            ),
+            pull_request("api", 3, "Add caching", "Ada Lovelace"),
+        ]);
+
+        app.execute_command("/search pool retries").expect("search");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![12, 2]
+        );
+
+        app.execute_command(r#"/search "connection pool" retries"#)
+            .expect("search");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![12]
+        );
 
-        webbrowser::open(&pr.url)?;
-        self.log(&format!(
-            "Opened {}/{} PR #{} in browser.",
-            pr.workspace, pr.repo, pr.id
-        ));
-        Ok(())
+        app.execute_command("/search 12 add").expect("search");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![12]
+        );
     }
 
-    fn handle_search_command(&mut self, args: &[&str]) {
-        let query = args.join(" ").trim().to_string();
-        if query.is_empty() || query.eq_ignore_ascii_case("clear") {
-            self.search_query = None;
-            self.apply_search_filter();
-            self.log("Search cleared.");
-            return;
-        }
+    #[test]
+    fn search_excludes_terms_by_title_or_author() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Bump serde from 1.0 to 1.1", "dependabot[bot]"),
+            pull_request("api", 3, "Update rust crate tokio", "renovate[bot]"),
+            pull_request("api", 4, "Revert renovate config", "Ada Lovelace"),
+        ]);
 
-        self.search_query = Some(query.clone());
-        self.apply_search_filter();
-        self.log(&format!(
-            "Search set to '{query}'. {} matching PR(s).",
-            self.pull_requests.len()
-        ));
-    }
+        app.execute_command("/search -dependabot -renovate")
+            .expect("search");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1]
+        );
 
-    fn clear_search_filter_if_active(&mut self) {
-        if self.search_query.is_none() {
-            return;
-        }
-        self.search_query = None;
-        self.apply_search_filter();
-        self.log("Search cleared due to non-search command.");
+        app.execute_command("/search -bot add").expect("search");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1]
+        );
     }
 
-    fn log(&mut self, message: &str) {
-        self.logs.push(message.to_string());
+    #[test]
+    fn summarizes_refresh_timing_with_slowest_repo() {
+        let timings = vec![
+            ("team/api".to_string(), Duration::from_millis(400)),
+            ("team/app".to_string(), Duration::from_millis(1800)),
+        ];
+        assert_eq!(
+            timing_summary(Duration::from_millis(3200), &timings),
+            "Refresh completed in 3.2s (slowest: team/app 1.8s of 2 repo(s))"
+        );
+        assert_eq!(
+            timing_summary(Duration::from_millis(900), &[]),
+            "Refresh completed in 0.9s"
+        );
     }
 
-    fn grouped_rows(&self) -> (Vec<(String, bool)>, Option<usize>) {
-        let mut rows = Vec::new();
-        let mut selected_row = None;
-        let mut current_repo: Option<String> = None;
-        let mut repo_pr_index = 0usize;
-        let selected_pr_index = self
-            .selected_index
-            .min(self.pull_requests.len().saturating_sub(1));
+    #[test]
+    fn hints_at_source_branches_left_after_merge() {
+        let mut kept = pull_request("api", 1, "Kept branch", "Ada Lovelace");
+        kept.state = "MERGED".to_string();
+        kept.source_branch = Some("feature/kept".to_string());
+        let mut closed = kept.clone();
+        closed.id = 2;
+        closed.close_source_branch = true;
+        let app = app_with(vec![kept, closed]);
 
-        let mut repo_counts = std::collections::HashMap::<String, usize>::new();
-        for pr in &self.pull_requests {
-            let key = format!("{}/{}", pr.workspace, pr.repo);
-            *repo_counts.entry(key).or_insert(0) += 1;
-        }
+        let (rows, _) = app.grouped_rows();
+        assert!(
+            rows[1]
+                .text
+                .ends_with("(Ada Lovelace) - branch not deleted")
+        );
+        assert!(rows[2].text.ends_with("(Ada Lovelace)"));
+    }
 
-        for (pr_index, pr) in self.pull_requests.iter().enumerate() {
-            let repo_key = format!("{}/{}", pr.workspace, pr.repo);
-            if current_repo.as_deref() != Some(repo_key.as_str()) {
-                repo_pr_index = 0;
-                let count = repo_counts.get(&repo_key).copied().unwrap_or(0);
-                let label = if count == 1 { "PR" } else { "PRs" };
-                rows.push((format!("{} ({} {}):", repo_key, count, label), true));
-                current_repo = Some(repo_key);
-            }
+    #[test]
+    fn shows_repo_health_next_to_group_headers() {
+        let mut app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        let succeeded = Local::now();
+        app.repo_health.insert(
+            "team/api".to_string(),
+            RepoHealth {
+                last_success: Some(succeeded),
+                last_error: Some((succeeded - chrono::Duration::minutes(5), "boom".to_string())),
+            },
+        );
+        app.repo_health.insert(
+            "team/web".to_string(),
+            RepoHealth {
+                last_success: Some(succeeded - chrono::Duration::minutes(5)),
+                last_error: Some((succeeded, "boom".to_string())),
+            },
+        );
 
-            if pr_index == selected_pr_index {
-                selected_row = Some(rows.len());
-            }
+        let (rows, _) = app.grouped_rows();
+        assert_eq!(rows[0].health, Some(HealthBadge::Ok));
+        assert_eq!(rows[2].text, "team/web (0 PRs):");
+        assert_eq!(
+            rows[2].health,
+            Some(HealthBadge::Failed(succeeded.format("%H:%M").to_string()))
+        );
+        assert!(
+            render(&app, 100, 20)
+                .iter()
+                .any(|line| line.contains("team/web (0 PRs): \u{25cf} failed"))
+        );
+    }
 
-            repo_pr_index += 1;
-            rows.push((
-                format!(
-                    "  {}. #{} [{} | comments:{}] {} ({})",
-                    repo_pr_index, pr.id, pr.state, pr.comment_count, pr.title, pr.author
-                ),
-                false,
-            ));
-        }
+    #[test]
+    fn retry_failed_targets_repos_whose_last_fetch_failed() {
+        let mut app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        app.execute_command("/retry-failed").expect("retry");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("No failed repos to retry.")
+        );
 
-        (rows, selected_row)
+        let now = Local::now();
+        app.repo_health.insert(
+            "team/api".to_string(),
+            RepoHealth {
+                last_success: Some(now),
+                last_error: Some((now - chrono::Duration::minutes(5), "boom".to_string())),
+            },
+        );
+        app.repo_health.insert(
+            "team/web".to_string(),
+            RepoHealth {
+                last_success: None,
+                last_error: Some((now, "boom".to_string())),
+            },
+        );
+        assert_eq!(
+            app.failed_repos(),
+            vec![RepoRef::parse("team/web").expect("repo")]
+        );
+
+        app.execute_command("/retry-failed").expect("retry");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Nothing to retry yet; use /refresh.")
+        );
     }
 
-    fn apply_search_filter(&mut self) {
-        let Some(raw_query) = self.search_query.as_ref() else {
-            self.pull_requests = self.all_pull_requests.clone();
-            self.selected_index = self
-                .selected_index
-                .min(self.pull_requests.len().saturating_sub(1));
-            return;
+    #[test]
+    fn stale_command_offers_to_decline_old_open_pull_requests() {
+        let mut fresh = pull_request("api", 2, "Fresh", "Ada Lovelace");
+        fresh.created_on = Utc::now().to_rfc3339();
+        let mut merged = pull_request("api", 3, "Merged", "Ada Lovelace");
+        merged.state = "MERGED".to_string();
+        let mut reviewing = pull_request("api", 4, "Reviewing", "Bob");
+        reviewing.relation = Relation::Reviewing;
+        let mut unmatched = pull_request("api", 5, "Unmatched", "Carol");
+        unmatched.relation = Relation::Other;
+        let mut both = pull_request("api", 6, "Both", "Ada Lovelace");
+        both.relation = Relation::Both;
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Ancient", "Ada Lovelace"),
+            fresh,
+            merged,
+            reviewing,
+            unmatched,
+            both,
+        ]);
+
+        app.handle_stale_command(&["30"]).expect("stale");
+        let Some(PendingAction::DeclineStale { targets }) = &app.pending_action else {
+            panic!("expected a decline confirmation");
         };
+        assert_eq!(
+            targets.iter().map(|target| target.id).collect::<Vec<_>>(),
+            vec![1, 6]
+        );
+        assert!(app.logs.iter().any(|line| line.contains("team/api #1 (")));
 
-        let query = raw_query.trim().to_ascii_lowercase();
-        if query.is_empty() {
-            self.pull_requests = self.all_pull_requests.clone();
-            self.selected_index = self
-                .selected_index
-                .min(self.pull_requests.len().saturating_sub(1));
-            return;
-        }
+        app.pending_action = None;
+        app.handle_stale_command(&["100000"]).expect("stale");
+        assert!(app.pending_action.is_none());
+    }
 
-        self.pull_requests = self
-            .all_pull_requests
-            .iter()
-            .filter(|pr| {
-                if pr.id.to_string().contains(&query) {
-                    return true;
-                }
+    #[test]
+    fn focus_mode_hides_log_and_reveals_input_while_typing() {
+        let mut app = app_with(vec![pull_request("api", 1, "Add retries", "Ada Lovelace")]);
+        app.handle_key(KeyEvent::from(KeyCode::Char('f')))
+            .expect("key");
+        assert!(app.focus);
 
-                let searchable = format!("{} {}", pr.title, pr.description).to_ascii_lowercase();
-                searchable.contains(&query)
-            })
-            .cloned()
-            .collect();
+        let screen = render(&app, 100, 20);
+        assert!(!screen.iter().any(|line| line.contains("Log")));
+        assert!(!screen.iter().any(|line| line.contains("Command (")));
 
-        self.selected_index = self
-            .selected_index
-            .min(self.pull_requests.len().saturating_sub(1));
+        app.handle_key(KeyEvent::from(KeyCode::Char('/')))
+            .expect("key");
+        assert!(
+            render(&app, 100, 20)
+                .iter()
+                .any(|line| line.contains("Command ("))
+        );
     }
 
-    fn command_query(&self) -> Option<&str> {
-        let trimmed = self.input.trim_start();
-        if !trimmed.starts_with('/') {
-            return None;
-        }
-        if trimmed.ends_with(' ') {
-            return None;
-        }
+    #[test]
+    fn remapped_keys_move_selection_only_with_empty_input() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("api", 2, "Fix layout", "Ada Lovelace"),
+        ]);
+        app.keymap = Keymap::resolve(&BTreeMap::from([(
+            "down".to_string(),
+            vec!["j".to_string()],
+        )]))
+        .expect("keymap");
 
-        let mut parts = trimmed.split_whitespace();
-        let command = parts.next().unwrap_or_default();
-        if parts.next().is_some() {
-            return None;
-        }
+        app.handle_key(KeyEvent::from(KeyCode::Down)).expect("key");
+        assert_eq!(app.selected_index, 0);
+        app.handle_key(KeyEvent::from(KeyCode::Char('j')))
+            .expect("key");
+        assert_eq!(app.selected_index, 1);
 
-        Some(command)
+        app.input = "/sear".to_string();
+        app.handle_key(KeyEvent::from(KeyCode::Char('j')))
+            .expect("key");
+        assert_eq!(app.input, "/searj");
     }
 
-    fn command_suggestions(&self) -> Vec<CommandSpec> {
-        let Some(query) = self.command_query() else {
-            return Vec::new();
-        };
+    #[test]
+    fn comfortable_spacing_separates_repo_groups() {
+        let mut app = app_with(vec![
+            pull_request("api", 1, "Add retries", "Ada Lovelace"),
+            pull_request("web", 2, "Fix layout", "Ada Lovelace"),
+        ]);
+        app.selected_index = 1;
+        let (rows, selected) = app.grouped_rows();
+        assert_eq!((rows.len(), selected), (4, Some(3)));
 
-        COMMAND_SPECS
-            .iter()
-            .copied()
-            .filter(|spec| spec.name.starts_with(query))
-            .collect()
+        app.config.list_spacing = ListSpacing::Comfortable;
+        app.config.group_separators = true;
+        let (rows, selected) = app.grouped_rows();
+        assert_eq!((rows.len(), selected), (6, Some(5)));
+        assert!(rows[2].is_spacer && rows[2].text.is_empty());
+        assert!(rows[3].is_spacer && rows[3].text.starts_with('\u{2500}'));
+        assert!(rows[4].is_header);
     }
 
-    fn has_command_suggestions(&self) -> bool {
-        !self.command_suggestions().is_empty()
-    }
+    #[test]
+    fn waiting_filter_keeps_old_unapproved_pull_requests() {
+        let mut fresh = pull_request("api", 2, "Fresh", "Ada Lovelace");
+        fresh.created_on = Utc::now().to_rfc3339();
+        let mut app = app_with(vec![
+            with_approvals(pull_request("api", 1, "Stale", "Ada Lovelace"), 0),
+            fresh,
+            with_approvals(pull_request("api", 3, "Approved", "Ada Lovelace"), 1),
+        ]);
 
-    fn move_command_selection(&mut self, direction: i32) {
-        let suggestions = self.command_suggestions();
-        if suggestions.is_empty() {
-            self.command_suggestion_index = 0;
-            return;
-        }
+        app.handle_waiting_command(&["2"]).expect("filter");
+        assert_eq!(
+            app.pull_requests.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![1]
+        );
+        assert!(
+            render(&app, 120, 16)
+                .iter()
+                .any(|line| line.contains("Waiting > 2d"))
+        );
 
-        let len = suggestions.len() as i32;
-        let next = (self.command_suggestion_index as i32 + direction).rem_euclid(len);
-        self.command_suggestion_index = next as usize;
+        app.handle_waiting_command(&["clear"]).expect("clear");
+        assert_eq!(app.pull_requests.len(), 3);
     }
 
-    fn apply_command_completion(&mut self) -> bool {
-        let suggestions = self.command_suggestions();
-        if suggestions.is_empty() {
-            return false;
-        }
+    #[test]
+    fn merge_adds_updates_and_removes_pull_requests() {
+        let mut kept = pull_request("api", 1, "Old title", "Ada Lovelace");
+        kept.build_url = Some("https://ci.example.com/1".to_string());
+        let old = vec![kept, pull_request("api", 2, "Merged away", "Ada Lovelace")];
 
-        let selected = suggestions[self.command_suggestion_index.min(suggestions.len() - 1)];
-        self.input = if selected.accepts_args {
-            format!("{} ", selected.name)
-        } else {
-            selected.name.to_string()
-        };
-        self.command_suggestion_index = 0;
-        true
+        let new = vec![
+            pull_request("api", 3, "Brand new", "Ada Lovelace"),
+            pull_request("api", 1, "New title", "Ada Lovelace"),
+        ];
+        let merged = merge_pull_requests(old, new);
+
+        assert_eq!(
+            merged.iter().map(|pr| pr.id).collect::<Vec<_>>(),
+            vec![3, 1]
+        );
+        assert_eq!(merged[1].title, "New title");
+        assert_eq!(
+            merged[1].build_url.as_deref(),
+            Some("https://ci.example.com/1")
+        );
+        assert!(merged[0].build_url.is_none());
     }
 
-    fn apply_command_completion_if_partial(&mut self) -> bool {
-        let Some(query) = self.command_query() else {
-            return false;
-        };
+    #[test]
+    fn live_search_filters_after_debounce() {
+        let mut config = Config::default();
+        config.live_search = true;
+        let mut app = App::new(config);
+        app.all_pull_requests = vec![
+            pull_request("api", 12, "Add retries", "Ada Lovelace"),
+            pull_request("api", 9, "Fix login", "Ada Lovelace"),
+        ];
+        app.apply_search_filter();
 
-        let suggestions = self.command_suggestions();
-        if suggestions.is_empty() {
-            return false;
+        for ch in "/search retr".chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(ch)))
+                .expect("key handled");
         }
+        let edited_at = app.live_search_edited_at.expect("live search pending");
 
-        let selected = suggestions[self.command_suggestion_index.min(suggestions.len() - 1)];
-        if query == selected.name {
-            return false;
-        }
+        app.apply_live_search_if_due(edited_at + Duration::from_millis(50));
+        assert_eq!(app.pull_requests.len(), 2);
 
-        self.input = if selected.accepts_args {
-            format!("{} ", selected.name)
-        } else {
-            selected.name.to_string()
-        };
-        self.command_suggestion_index = 0;
-        true
+        app.apply_live_search_if_due(edited_at + LIVE_SEARCH_DEBOUNCE);
+        assert_eq!(app.search_query.as_deref(), Some("retr"));
+        assert_eq!(app.pull_requests.len(), 1);
+        assert_eq!(app.pull_requests[0].id, 12);
     }
 
-    fn collect_refresh_updates(
-        &self,
-        previous_by_key: &HashMap<String, PullRequest>,
-        latest_pull_requests: &[PullRequest],
-    ) -> Vec<String> {
-        let mut updates = Vec::new();
-        let mut latest_keys = HashSet::new();
+    #[test]
+    fn live_search_is_off_by_default() {
+        let mut app = app_with(vec![pull_request("api", 12, "Add retries", "Ada Lovelace")]);
+        for ch in "/search x".chars() {
+            app.handle_key(KeyEvent::from(KeyCode::Char(ch)))
+                .expect("key handled");
+        }
+        assert!(app.live_search_edited_at.is_none());
+    }
 
-        for pr in latest_pull_requests {
-            let key = Self::pr_key(pr);
-            latest_keys.insert(key.clone());
+    #[test]
+    fn renders_grouped_pull_requests_with_selection() {
+        let mut app = app_with(vec![
+            pull_request("api", 12, "Add retries", "Ada Lovelace"),
+            pull_request("api", 9, "Fix login", "Ada Lovelace"),
+            pull_request("web", 3, "New header", "Grace Hopper"),
+        ]);
+        app.selected_index = 1;
 
-            match previous_by_key.get(&key) {
-                Some(previous_pr) => {
-                    let mut has_specific_change = false;
+        let lines = render(&app, 100, 30);
+        let screen = lines.join("\n");
 
-                    if previous_pr.comment_count != pr.comment_count {
-                        let delta = pr.comment_count as i64 - previous_pr.comment_count as i64;
-                        let signed_delta = if delta > 0 {
-                            format!("+{delta}")
-                        } else {
-                            delta.to_string()
-                        };
-                        updates.push(format!(
-                            "PR {}/{} #{} comments: {} -> {} ({signed_delta}).",
-                            pr.workspace,
-                            pr.repo,
-                            pr.id,
-                            previous_pr.comment_count,
-                            pr.comment_count
-                        ));
-                        has_specific_change = true;
-                    }
+        assert!(screen.contains("team/api (2 PRs):"), "{screen}");
+        assert!(screen.contains("team/web (1 PR):"), "{screen}");
+        assert!(screen.contains("AL 1. #12 [OPEN | comments:2] Add retries"));
+        assert!(screen.contains("GH 1. #3 [OPEN | comments:2] New header"));
 
-                    if previous_pr.state != pr.state {
-                        updates.push(format!(
-                            "PR {}/{} #{} state: {} -> {}.",
-                            pr.workspace, pr.repo, pr.id, previous_pr.state, pr.state
-                        ));
-                        has_specific_change = true;
-                    }
+        let selected = lines
+            .iter()
+            .find(|line| line.contains("> "))
+            .expect("highlighted row");
+        assert!(selected.contains("#9"), "{selected}");
+    }
 
-                    if !has_specific_change && previous_pr.updated_on != pr.updated_on {
-                        updates.push(format!(
-                            "PR {}/{} #{} has new activity.",
-                            pr.workspace, pr.repo, pr.id
-                        ));
-                    }
-                }
-                None => updates.push(format!(
-                    "New PR detected: {}/{} #{} {}.",
-                    pr.workspace, pr.repo, pr.id, pr.title
-                )),
-            }
-        }
+    #[test]
+    fn first_refresh_starts_right_after_the_first_paint() {
+        let mut app = app_with(Vec::new());
+        assert_eq!(app.poll_timeout(), Duration::ZERO);
 
-        for (key, previous_pr) in previous_by_key {
-            if latest_keys.contains(key) {
-                continue;
-            }
-            updates.push(format!(
-                "PR {}/{} #{} no longer in '{}' results.",
-                previous_pr.workspace, previous_pr.repo, previous_pr.id, self.status_filter
-            ));
-        }
+        app.last_refresh_at = Some(Instant::now());
+        assert_eq!(app.poll_timeout(), Duration::from_millis(200));
+    }
 
-        updates
+    #[test]
+    fn renders_empty_state_without_pull_requests() {
+        let mut app = app_with(Vec::new());
+        app.config
+            .add_repo(RepoRef::parse("team/api").expect("repo"));
+        let screen = render(&app, 100, 30).join("\n");
+        assert!(screen.contains("No pull requests loaded."), "{screen}");
     }
 
-    fn emit_update_notifications(&mut self, updates: &[String]) {
-        self.log(&format!("{} PR update(s) detected.", updates.len()));
-        for update in updates.iter().take(MAX_LOGGED_UPDATES) {
-            self.log(update);
-        }
-        if updates.len() > MAX_LOGGED_UPDATES {
-            self.log(&format!(
-                "...and {} more update(s).",
-                updates.len() - MAX_LOGGED_UPDATES
-            ));
-        }
-        self.ring_terminal_bell();
+    #[test]
+    fn derives_author_initials() {
+        assert_eq!(author_initials("Ada Lovelace"), "AL");
+        assert_eq!(author_initials("Grace Brewster Hopper"), "GH");
+        assert_eq!(author_initials("shaun"), "SH");
+        assert_eq!(author_initials("Émile Zola"), "ÉZ");
+        assert_eq!(author_initials("李"), "李");
+        assert_eq!(author_initials("  "), "??");
     }
 
-    fn ring_terminal_bell(&self) {
-        let _ = io::stdout().write_all(b"\x07");
-        let _ = io::stdout().flush();
+    #[test]
+    fn diff_popup_colors_pages_and_shows_its_position() {
+        let pr = pull_request("api", 12, "Add retries", "Ada Lovelace");
+        let mut app = app_with(vec![pr.clone()]);
+
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn main() {\n-    old();\n+    new();\n";
+        let lines = diff_lines(diff);
+        assert_eq!(lines[1].style.fg, Some(Color::DarkGray));
+        assert_eq!(lines[3].style.fg, Some(Color::Cyan));
+        assert_eq!(lines[4].style.fg, None);
+        assert_eq!(lines[5].style.fg, Some(Color::Red));
+        assert_eq!(lines[6].style.fg, Some(Color::Green));
+
+        let long_diff = (1..=60)
+            .map(|line| format!("+line {line}\n"))
+            .collect::<String>();
+        app.execute_command("/file").expect("usage");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("Usage: /file <path>")
+        );
+        app.open_diff_view(pr.clone(), Some("src/lib.rs".to_string()), diff.to_string());
+        let screen = render(&app, 80, 20).join("\n");
+        assert!(screen.contains("PR #12 src/lib.rs 100%"));
+        assert!(screen.contains("+    new();"));
+
+        app.open_diff_view(pr, None, long_diff);
+        assert!(render(&app, 60, 20)[2].contains("PR #12 diff 0%"));
+
+        app.handle_key(KeyEvent::from(KeyCode::PageDown))
+            .expect("page down");
+        // The popup shows 14 rows of text at this size.
+        assert_eq!(
+            app.diff_view.as_ref().map(|view| view.scroll.offset),
+            Some(14)
+        );
+        app.handle_key(KeyEvent::from(KeyCode::End)).expect("end");
+        let screen = render(&app, 60, 20);
+        assert!(screen[2].contains("PR #12 diff 100%"));
+        assert!(screen.join("\n").contains("+line 60"));
+        app.handle_key(KeyEvent::from(KeyCode::Home)).expect("home");
+        assert_eq!(
+            app.diff_view.as_ref().map(|view| view.scroll.offset),
+            Some(0)
+        );
+
+        app.handle_key(KeyEvent::from(KeyCode::Esc)).expect("close");
+        assert!(app.diff_view.is_none());
+        assert!(!app.should_quit);
     }
 
-    fn pr_key(pr: &PullRequest) -> String {
-        format!("{}/{}/{}", pr.workspace, pr.repo, pr.id)
+    #[test]
+    fn reopen_explains_that_declined_prs_cannot_be_reopened() {
+        let mut declined = pull_request("api", 2, "Old idea", "Ada Lovelace");
+        declined.state = "DECLINED".to_string();
+        declined.source_branch = Some("feature/old-idea".to_string());
+        let mut app = app_with(vec![declined]);
+
+        app.execute_command("/reopen").expect("reopen");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some(
+                "Bitbucket Cloud cannot reopen declined PRs; create a new PR from feature/old-idea to replace #2."
+            )
+        );
+
+        app.all_pull_requests[0].state = "OPEN".to_string();
+        app.apply_search_filter();
+        app.execute_command("/reopen").expect("reopen");
+        assert_eq!(
+            app.logs.last().map(String::as_str),
+            Some("PR #2 is OPEN; only declined PRs could be reopened.")
+        );
     }
 }
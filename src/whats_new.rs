@@ -0,0 +1,52 @@
+/// Highlights per release, oldest first. Keep each entry to a few words so
+/// the combined note fits on one log line.
+const HIGHLIGHTS: &[(&str, &str)] = &[(
+    "0.2.0",
+    "/stale, /focus, /issue, /project, /export-md, z to fold repo groups, --read-only",
+)];
+
+/// One-line "what's new" note for upgrading from `last_seen` to `current`,
+/// or `None` on a first run, a downgrade, or when nothing notable changed.
+pub fn note(last_seen: Option<&str>, current: &str) -> Option<String> {
+    let last_seen = parse_version(last_seen?)?;
+    let current_version = parse_version(current)?;
+    let highlights = HIGHLIGHTS
+        .iter()
+        .filter(|(version, _)| {
+            parse_version(version)
+                .is_some_and(|version| version > last_seen && version <= current_version)
+        })
+        .map(|(_, highlights)| *highlights)
+        .collect::<Vec<_>>();
+    if highlights.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "What's new in {current}: {}. See /help for details.",
+        highlights.join("; ")
+    ))
+}
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().split('.').map(|part| part.parse().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::note;
+
+    #[test]
+    fn notes_highlights_only_after_an_upgrade() {
+        let upgraded = note(Some("0.1.4"), "0.2.0").expect("note");
+        assert!(
+            upgraded.starts_with("What's new in 0.2.0: /stale"),
+            "{upgraded}"
+        );
+
+        assert_eq!(note(None, "0.2.0"), None);
+        assert_eq!(note(Some("0.2.0"), "0.2.0"), None);
+        assert_eq!(note(Some("0.3.0"), "0.2.0"), None);
+        assert_eq!(note(Some("garbage"), "0.2.0"), None);
+    }
+}